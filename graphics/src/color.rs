@@ -0,0 +1,137 @@
+// src/color.rs - pixel channel layouts and ARGB helpers
+
+/// Bit position and width of each color channel within a pixel
+#[derive(Clone, Copy)]
+pub struct ColorLayout {
+    pub red_pos: u8,
+    pub red_size: u8,
+    pub green_pos: u8,
+    pub green_size: u8,
+    pub blue_pos: u8,
+    pub blue_size: u8,
+}
+
+impl ColorLayout {
+    /// Layout assumed when the bootloader gives no color info: xRGB8888 / RGB888 / RGB565 / RGB555
+    pub const fn default_for_bpp(bpp: usize) -> Self {
+        match bpp {
+            15 => ColorLayout { red_pos: 10, red_size: 5, green_pos: 5, green_size: 5, blue_pos: 0, blue_size: 5 },
+            16 => ColorLayout { red_pos: 11, red_size: 5, green_pos: 5, green_size: 6, blue_pos: 0, blue_size: 5 },
+            _ => ColorLayout { red_pos: 16, red_size: 8, green_pos: 8, green_size: 8, blue_pos: 0, blue_size: 8 },
+        }
+    }
+
+    /// Every channel must be 1..=8 bits wide and fit inside a `bpp`-bit pixel
+    pub fn is_valid_for(&self, bpp: usize) -> bool {
+        let fits = |pos: u8, size: u8| (1..=8).contains(&size) && (pos as usize + size as usize) <= bpp;
+        fits(self.red_pos, self.red_size)
+            && fits(self.green_pos, self.green_size)
+            && fits(self.blue_pos, self.blue_size)
+    }
+
+    /// Convert a 0xAARRGGBB color into the framebuffer's native pixel value
+    pub fn pack(&self, color: u32) -> u32 {
+        let r = (color >> 16) & 0xFF;
+        let g = (color >> 8) & 0xFF;
+        let b = color & 0xFF;
+        (scale_channel(r, self.red_size) << self.red_pos)
+            | (scale_channel(g, self.green_size) << self.green_pos)
+            | (scale_channel(b, self.blue_size) << self.blue_pos)
+    }
+
+    /// Convert a native pixel value back into 0xFFRRGGBB (opaque)
+    pub fn unpack(&self, pixel: u32) -> u32 {
+        let channel = |pos: u8, size: u8| {
+            let mask = (1u32 << size) - 1;
+            expand_channel((pixel >> pos) & mask, size)
+        };
+        let r = channel(self.red_pos, self.red_size);
+        let g = channel(self.green_pos, self.green_size);
+        let b = channel(self.blue_pos, self.blue_size);
+        0xFF00_0000 | (r << 16) | (g << 8) | b
+    }
+}
+
+/// Expand a `bits`-wide channel back to 8 bits, replicating the high bits into the low ones
+fn expand_channel(value: u32, bits: u8) -> u32 {
+    if bits >= 8 {
+        return value >> (bits - 8);
+    }
+    let v = value << (8 - bits);
+    (v | (v >> bits)) & 0xFF
+}
+
+/// Reduce (or widen) an 8-bit channel value to `bits` bits. Narrowing rounds to
+/// the nearest level, so after expand_channel a channel is off by at most half a
+/// step (±4 for 5 bits, ±2 for 6) instead of up to a whole one.
+fn scale_channel(value: u32, bits: u8) -> u32 {
+    if bits >= 8 {
+        value << (bits - 8)
+    } else {
+        let max = (1u32 << bits) - 1;
+        (value * max + 127) / 255
+    }
+}
+
+/// Scale the RGB channels of `color` by factor/255, rounded; alpha is kept.
+/// 255 leaves the color alone, 0 makes it black.
+pub(crate) fn scale_argb(color: u32, factor: u8) -> u32 {
+    let f = factor as u32;
+    let mut out = color & 0xFF00_0000;
+    for shift in [16, 8, 0] {
+        let c = (color >> shift) & 0xFF;
+        out |= ((c * f + 127) / 255) << shift;
+    }
+    out
+}
+
+/// Linearly interpolate each ARGB channel from `start` to `end`, t in 0..=255
+pub(crate) fn lerp_argb(start: u32, end: u32, t: u32) -> u32 {
+    let mut out = 0;
+    for shift in [24, 16, 8, 0] {
+        let s = ((start >> shift) & 0xFF) as i32;
+        let e = ((end >> shift) & 0xFF) as i32;
+        let c = s + (e - s) * t as i32 / 255;
+        out |= (c as u32 & 0xFF) << shift;
+    }
+    out
+}
+
+/// Composite `src` over `dst` at `src`'s alpha, all in integers: each channel is
+/// (s*a + d*(255-a)) / 255 rounded to nearest, via the exact x/255 = (x + 1 +
+/// (x >> 8)) >> 8 identity. The result is opaque.
+pub(crate) fn blend_argb(src: u32, dst: u32) -> u32 {
+    let a = (src >> 24) & 0xFF;
+    let mix = |shift: u32| {
+        let s = (src >> shift) & 0xFF;
+        let d = (dst >> shift) & 0xFF;
+        let x = s * a + d * (255 - a) + 127;
+        ((x + 1 + (x >> 8)) >> 8) << shift
+    };
+    0xFF00_0000 | mix(16) | mix(8) | mix(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_blend_matches_float_blend() {
+        let float_mix = |s: u32, d: u32, a: u32| {
+            let a = a as f32 / 255.0;
+            (s as f32 * a + d as f32 * (1.0 - a)).round().clamp(0.0, 255.0) as i32
+        };
+        for a in [0, 1, 64, 127, 128, 200, 254, 255] {
+            for (s, d) in [(0, 255), (255, 0), (17, 230), (128, 127), (255, 255)] {
+                let src = (a << 24) | (s << 16) | (s << 8) | s;
+                let dst = (d << 16) | (d << 8) | d;
+                let out = blend_argb(src, dst);
+                assert_eq!(out >> 24, 0xFF);
+                for shift in [16, 8, 0] {
+                    let got = ((out >> shift) & 0xFF) as i32;
+                    assert!((got - float_mix(s, d, a)).abs() <= 1, "a={a} s={s} d={d}: {got}");
+                }
+            }
+        }
+    }
+}
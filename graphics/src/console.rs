@@ -0,0 +1,414 @@
+// src/console.rs - text console on a Framebuffer: character grid, scrolling, scrollback
+//
+// The console keeps every character it shows in a backing array of cells
+// (character + colors), so the screen can be redrawn from memory. Lines live in
+// a ring of LINES entries: the visible screen plus whatever older lines still fit,
+// which is the scrollback. Absolute line n (counting from the first line ever
+// written) is stored at index n % LINES.
+use core::fmt;
+use crate::font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::Framebuffer;
+
+/// Tab stops every TAB_WIDTH columns
+pub const TAB_WIDTH: usize = 8;
+
+pub const DEFAULT_FG: u32 = 0xFF_C0_C0_C0;
+pub const DEFAULT_BG: u32 = 0xFF_00_00_00;
+
+/// Rows of each cell covered by the underline cursor
+const CURSOR_HEIGHT: usize = 2;
+
+const BACKSPACE: u8 = 0x08;
+
+/// One character position: the byte shown and its colors (0xAARRGGBB)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub ch: u8,
+    pub fg: u32,
+    pub bg: u32,
+}
+
+impl Cell {
+    pub const BLANK: Cell = Cell { ch: b' ', fg: DEFAULT_FG, bg: DEFAULT_BG };
+}
+
+/// Text console with an underline cursor, drawn with the built-in 8x16 font.
+///
+/// COLS and LINES size the backing store, so a Console can live in a static
+/// without a heap: the grid uses at most COLS columns, and LINES lines are kept
+/// in total (visible rows plus scrollback). Wider or taller framebuffers only use
+/// the top-left part that fits.
+pub struct Console<const COLS: usize, const LINES: usize> {
+    fb: Option<Framebuffer>,
+    lines: [[Cell; COLS]; LINES],
+    cols: usize,
+    rows: usize,
+    /// Absolute line number at screen row 0 when showing live output
+    top: usize,
+    row: usize,
+    col: usize,
+    fg: u32,
+    bg: u32,
+    /// How many lines the view is scrolled back; 0 shows live output
+    view_back: usize,
+    /// Rows scroll_top..scroll_end scroll on newline; the whole screen by default
+    scroll_top: usize,
+    scroll_end: usize,
+}
+
+impl<const COLS: usize, const LINES: usize> Default for Console<COLS, LINES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const COLS: usize, const LINES: usize> Console<COLS, LINES> {
+    /// A console with no framebuffer yet; output is dropped until attach()
+    pub const fn new() -> Self {
+        Console {
+            fb: None,
+            lines: [[Cell::BLANK; COLS]; LINES],
+            cols: 0,
+            rows: 0,
+            top: 0,
+            row: 0,
+            col: 0,
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            view_back: 0,
+            scroll_top: 0,
+            scroll_end: 0,
+        }
+    }
+
+    /// Start drawing on `fb` with an empty grid and the cursor at the top left.
+    /// The framebuffer contents are left alone; call clear() for a blank screen.
+    pub unsafe fn attach(&mut self, fb: Framebuffer) {
+        self.cols = (fb.width / GLYPH_WIDTH).min(COLS);
+        self.rows = (fb.height / GLYPH_HEIGHT).min(LINES);
+        self.fb = Some(fb);
+        // in place: a fresh array would be built on the stack first
+        for line in self.lines.iter_mut() {
+            line.fill(Cell::BLANK);
+        }
+        self.top = 0;
+        self.row = 0;
+        self.col = 0;
+        self.view_back = 0;
+        self.scroll_top = 0;
+        self.scroll_end = self.rows;
+        self.draw_cursor();
+    }
+
+    /// Whether attach() has given the console a framebuffer
+    pub fn is_attached(&self) -> bool {
+        self.fb.is_some()
+    }
+
+    /// Grid size in characters (columns, rows)
+    pub fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    /// Cursor position (column, row)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.col, self.row)
+    }
+
+    /// Colors used for characters written from now on
+    pub fn set_colors(&mut self, fg: u32, bg: u32) {
+        self.fg = fg;
+        self.bg = bg;
+    }
+
+    /// The cell at (col,row) of the live screen
+    pub fn cell(&self, col: usize, row: usize) -> Option<Cell> {
+        if col >= self.cols || row >= self.rows { return None; }
+        Some(self.lines[(self.top + row) % LINES][col])
+    }
+
+    /// Blank every cell on screen in the current background and move the cursor
+    /// to the top left of the scroll region. Scrollback is kept.
+    pub unsafe fn clear(&mut self) {
+        self.view_back = 0;
+        let blank = self.blank_cell();
+        for row in 0..self.rows {
+            self.lines[(self.top + row) % LINES][..self.cols].fill(blank);
+        }
+        self.row = self.scroll_top;
+        self.col = 0;
+        self.redraw();
+    }
+
+    /// Limit scrolling to rows top_row..=bottom_row, like a terminal's DECSTBM:
+    /// newlines at the bottom of the region scroll only that band, so rows above
+    /// and below it (a status bar, a banner) stay put. Lines scrolled out of a
+    /// partial region are dropped rather than kept as scrollback. The cursor moves
+    /// to the region's first row and stays inside the region from then on, so write
+    /// any header before setting the region. Returns false, changing nothing, if
+    /// the region is empty or runs off the screen.
+    pub unsafe fn set_scroll_region(&mut self, top_row: usize, bottom_row: usize) -> bool {
+        if top_row > bottom_row || bottom_row >= self.rows { return false; }
+        self.hide_cursor();
+        self.scroll_top = top_row;
+        self.scroll_end = bottom_row + 1;
+        self.row = top_row;
+        self.col = 0;
+        self.draw_cursor();
+        true
+    }
+
+    /// Scroll the whole screen again, keeping scrollback as usual
+    pub unsafe fn reset_scroll_region(&mut self) {
+        self.set_scroll_region(0, self.rows.saturating_sub(1));
+    }
+
+    /// Write one byte. '\n' starts a new line, '\r' returns to column 0, '\t'
+    /// advances to the next tab stop and backspace erases the previous character
+    /// on the line. Anything unprintable shows as '?'. Long lines wrap and the
+    /// screen scrolls up once the last row is full.
+    pub unsafe fn put_char(&mut self, ch: u8) {
+        if self.fb.is_none() || self.cols == 0 || self.rows == 0 { return; }
+
+        // New output always shows up on the live screen
+        if self.view_back != 0 {
+            self.view_back = 0;
+            self.redraw();
+        }
+        self.hide_cursor();
+
+        match ch {
+            b'\n' => self.newline(),
+            b'\r' => self.col = 0,
+            b'\t' => loop {
+                self.write_cell(b' ');
+                if self.col.is_multiple_of(TAB_WIDTH) || self.col >= self.cols { break; }
+            },
+            BACKSPACE => {
+                if self.col > 0 {
+                    // a wrap is pending when col == cols; the last column is the one to erase
+                    self.col = self.col.min(self.cols) - 1;
+                    let blank = self.blank_cell();
+                    self.set_cell(self.row, self.col, blank);
+                }
+            }
+            _ => self.write_cell(ch),
+        }
+
+        self.draw_cursor();
+    }
+
+    /// Lines of scrollback above the live screen
+    pub fn scrollback_len(&self) -> usize {
+        self.top.min(LINES - self.rows)
+    }
+
+    /// Show the screen as it was `lines_back` lines ago (0 = live output),
+    /// clamped to the scrollback available. The next character written jumps
+    /// back to live output.
+    pub unsafe fn scroll_view(&mut self, lines_back: usize) {
+        let lines_back = lines_back.min(self.scrollback_len());
+        if lines_back == self.view_back { return; }
+        self.view_back = lines_back;
+        self.redraw();
+        self.draw_cursor();
+    }
+
+    fn blank_cell(&self) -> Cell {
+        Cell { ch: b' ', fg: self.fg, bg: self.bg }
+    }
+
+    /// Put a printable character at the cursor, wrapping first if the line is full
+    unsafe fn write_cell(&mut self, ch: u8) {
+        if self.col >= self.cols {
+            self.newline();
+        }
+        let cell = Cell { ch, fg: self.fg, bg: self.bg };
+        self.set_cell(self.row, self.col, cell);
+        self.col += 1;
+    }
+
+    unsafe fn newline(&mut self) {
+        self.col = 0;
+        if self.row + 1 < self.scroll_end {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Move the screen up one text row: the top line becomes scrollback and the
+    /// bottom row is a fresh blank line.
+    unsafe fn scroll(&mut self) {
+        if self.scroll_top != 0 || self.scroll_end != self.rows {
+            self.scroll_region();
+            return;
+        }
+        self.top += 1;
+        let blank = self.blank_cell();
+        self.lines[(self.top + self.rows - 1) % LINES][..self.cols].fill(blank);
+
+        let Some(fb) = &self.fb else { return };
+        let width = self.cols * GLYPH_WIDTH;
+        let height = self.rows * GLYPH_HEIGHT;
+        fb.copy_rect(0, GLYPH_HEIGHT, 0, 0, width, height - GLYPH_HEIGHT);
+        fb.fill_rect(0, height - GLYPH_HEIGHT, width, GLYPH_HEIGHT, self.bg);
+    }
+
+    /// Move rows scroll_top+1..scroll_end up one and blank the region's last row.
+    /// The ring can't do this by moving `top`, so the cells are copied.
+    unsafe fn scroll_region(&mut self) {
+        for row in self.scroll_top..self.scroll_end - 1 {
+            let src = (self.top + row + 1) % LINES;
+            self.lines.copy_within(src..src + 1, (self.top + row) % LINES);
+        }
+        let blank = self.blank_cell();
+        self.lines[(self.top + self.scroll_end - 1) % LINES][..self.cols].fill(blank);
+
+        let Some(fb) = &self.fb else { return };
+        let width = self.cols * GLYPH_WIDTH;
+        let band = (self.scroll_end - self.scroll_top - 1) * GLYPH_HEIGHT;
+        let y = self.scroll_top * GLYPH_HEIGHT;
+        fb.copy_rect(0, y + GLYPH_HEIGHT, 0, y, width, band);
+        fb.fill_rect(0, y + band, width, GLYPH_HEIGHT, self.bg);
+    }
+
+    unsafe fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        self.lines[(self.top + row) % LINES][col] = cell;
+        self.draw_cell(row, col);
+    }
+
+    /// Paint screen position (row,col) from the backing array, honouring view_back
+    unsafe fn draw_cell(&self, row: usize, col: usize) {
+        let Some(fb) = &self.fb else { return };
+        let cell = self.lines[(self.top - self.view_back + row) % LINES][col];
+        let x = col * GLYPH_WIDTH;
+        let y = row * GLYPH_HEIGHT;
+        for (dy, bits) in glyph(cell.ch).iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                let color = if bits & (0x80 >> dx) != 0 { cell.fg } else { cell.bg };
+                fb.put_pixel(x + dx, y + dy, color);
+            }
+        }
+    }
+
+    unsafe fn redraw(&self) {
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                self.draw_cell(row, col);
+            }
+        }
+    }
+
+    /// Underline the cell the next character goes into. Not shown while the
+    /// view is scrolled back or while a line wrap is pending.
+    unsafe fn draw_cursor(&self) {
+        let Some(fb) = &self.fb else { return };
+        if self.view_back != 0 || self.col >= self.cols || self.row >= self.rows { return; }
+        let y = (self.row + 1) * GLYPH_HEIGHT - CURSOR_HEIGHT;
+        fb.fill_rect(self.col * GLYPH_WIDTH, y, GLYPH_WIDTH, CURSOR_HEIGHT, self.fg);
+    }
+
+    /// Redrawing the cell under the cursor removes the underline
+    unsafe fn hide_cursor(&self) {
+        if self.col < self.cols && self.row < self.rows {
+            self.draw_cell(self.row, self.col);
+        }
+    }
+}
+
+impl<const COLS: usize, const LINES: usize> fmt::Write for Console<COLS, LINES> {
+    /// Characters outside ASCII show as '?', one per char
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let byte = if c.is_ascii() { c as u8 } else { b'?' };
+            unsafe { self.put_char(byte) };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    /// 4x3 character console over a 32bpp memory framebuffer
+    fn console(buf: &mut Vec<u32>) -> Console<4, 6> {
+        let (w, h) = (4 * GLYPH_WIDTH, 3 * GLYPH_HEIGHT);
+        *buf = vec![0; w * h];
+        let mut con = Console::new();
+        unsafe { con.attach(Framebuffer::from_xrgb_buffer(buf, w, h)) };
+        con
+    }
+
+    fn row_text(con: &Console<4, 6>, row: usize) -> String {
+        (0..4).map(|col| con.cell(col, row).unwrap().ch as char).collect()
+    }
+
+    #[test]
+    fn grid_comes_from_the_font_size() {
+        let mut buf = Vec::new();
+        let con = console(&mut buf);
+        assert_eq!(con.size(), (4, 3));
+    }
+
+    #[test]
+    fn newline_tab_and_backspace_move_the_cursor() {
+        let mut buf = Vec::new();
+        let mut con = console(&mut buf);
+        write!(con, "ab\nc\td\x08").unwrap();
+        assert_eq!(row_text(&con, 0), "ab  ");
+        // the tab fills to the end of the 4-wide line and 'd' wraps; backspace erases it
+        assert_eq!(row_text(&con, 1), "c   ");
+        assert_eq!(row_text(&con, 2), "    ");
+        assert_eq!(con.cursor(), (0, 2));
+    }
+
+    #[test]
+    fn carriage_return_rewrites_the_same_line() {
+        let mut buf = Vec::new();
+        let mut con = console(&mut buf);
+        write!(con, "abc\rX").unwrap();
+        assert_eq!(row_text(&con, 0), "Xbc ");
+        assert_eq!(con.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn scroll_region_keeps_the_header_row() {
+        let mut buf = Vec::new();
+        let mut con = console(&mut buf);
+        write!(con, "HEAD").unwrap();
+        assert!(!unsafe { con.set_scroll_region(2, 3) });
+        assert!(unsafe { con.set_scroll_region(1, 2) });
+        assert_eq!(con.cursor(), (0, 1));
+
+        write!(con, "a\nb\nc").unwrap();
+        assert_eq!(row_text(&con, 0), "HEAD");
+        assert_eq!(row_text(&con, 1), "b   ");
+        assert_eq!(row_text(&con, 2), "c   ");
+        assert_eq!(con.scrollback_len(), 0);
+
+        // the header's pixels were not scrolled away either
+        let lit = (0..GLYPH_HEIGHT).map(|y| (0..GLYPH_WIDTH).filter(|&x| buf[y * 4 * GLYPH_WIDTH + x] != 0).count());
+        assert!(lit.zip(glyph(b'H')).all(|(n, bits)| n == bits.count_ones() as usize));
+    }
+
+    #[test]
+    fn scrolling_moves_pixels_up_and_keeps_scrollback() {
+        let mut buf = Vec::new();
+        let mut con = console(&mut buf);
+        write!(con, "1\n2\n3\n4").unwrap();
+        assert_eq!(row_text(&con, 0), "2   ");
+        assert_eq!(row_text(&con, 2), "4   ");
+        assert_eq!(con.scrollback_len(), 1);
+
+        // the top-left cell now shows the '2' glyph that was drawn on row 1
+        let pitch = 4 * GLYPH_WIDTH;
+        let lit = |y: usize| (0..GLYPH_WIDTH).filter(|&x| buf[y * pitch + x] != 0).count();
+        let expected = glyph(b'2');
+        for (y, bits) in expected.iter().enumerate() {
+            assert_eq!(lit(y), bits.count_ones() as usize, "row {y}");
+        }
+    }
+}
@@ -0,0 +1,1179 @@
+// src/framebuffer.rs - linear framebuffer drawing primitives
+use core::ptr::{read_volatile, write_volatile};
+use crate::color::{blend_argb, lerp_argb, scale_argb, ColorLayout};
+
+/// Axis along which draw_gradient_ex interpolates
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GradientDir {
+    /// left to right
+    Horizontal,
+    /// top to bottom
+    Vertical,
+    /// top-left to bottom-right
+    Diagonal,
+}
+
+/// Sprite pixels with this value are not drawn by draw_cursor
+pub const CURSOR_TRANSPARENT: u32 = 0x0000_0000;
+
+/// Side of one draw_test_pattern checkerboard square, in pixels
+const TEST_PATTERN_SQUARE: usize = 16;
+
+/// draw_test_pattern color bars, left to right (75% intensity, as in SMPTE bars)
+const TEST_PATTERN_BARS: [u32; 7] = [
+    0xFF_BF_BF_BF, // white
+    0xFF_BF_BF_00, // yellow
+    0xFF_00_BF_BF, // cyan
+    0xFF_00_BF_00, // green
+    0xFF_BF_00_BF, // magenta
+    0xFF_BF_00_00, // red
+    0xFF_00_00_BF, // blue
+];
+
+/// Pixels left blank at the start of row `row` (0 = outermost) of a rounded
+/// corner of radius `r`: those whose centers lie outside the corner circle.
+/// Works in doubled coordinates so pixel centers (k + 0.5) stay integers.
+fn corner_inset(r: usize, row: usize) -> usize {
+    let dy = 2 * (r - row) - 1;
+    let limit = 4 * r * r - dy * dy;
+    // pixel k (counted from the circle's center column outwards) is inside
+    // while (2k + 1)^2 <= limit
+    let mut inside = 0;
+    while inside < r && (2 * inside + 1) * (2 * inside + 1) <= limit {
+        inside += 1;
+    }
+    r - inside
+}
+
+/// Why Framebuffer::new_checked rejected a mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FbError {
+    /// Not one of 8, 15, 16, 24 or 32
+    UnsupportedBpp(usize),
+    /// Width or height is 0
+    ZeroSize,
+    /// A row of `width` pixels needs `min` bytes but the pitch is only `pitch`
+    PitchTooSmall { pitch: usize, min: usize },
+}
+
+/// A linear framebuffer. Every drawing method is `unsafe` because it writes
+/// through `phys_addr`, which must point at `pitch * height` bytes of mapped memory
+/// (identity-mapped under GRUB, an HHDM address under Limine).
+pub struct Framebuffer {
+    pub phys_addr: usize,
+    pub pitch: usize,
+    pub width: usize,
+    pub height: usize,
+    pub bpp: usize,
+    pub layout: ColorLayout,
+}
+
+impl Framebuffer {
+    /// A framebuffer from bootloader-reported geometry, in the default channel
+    /// layout for `bpp`, after checking the numbers make sense: a supported bpp,
+    /// a non-empty size and rows that fit in the pitch. Every draw call computes
+    /// offsets from these, so garbage here would scribble over random memory.
+    pub fn new_checked(phys_addr: usize, pitch: usize, width: usize, height: usize, bpp: usize) -> Result<Self, FbError> {
+        if !matches!(bpp, 8 | 15 | 16 | 24 | 32) {
+            return Err(FbError::UnsupportedBpp(bpp));
+        }
+        if width == 0 || height == 0 {
+            return Err(FbError::ZeroSize);
+        }
+        let min = width * bpp.div_ceil(8);
+        if pitch < min {
+            return Err(FbError::PitchTooSmall { pitch, min });
+        }
+        Ok(Framebuffer { phys_addr, pitch, width, height, bpp, layout: ColorLayout::default_for_bpp(bpp) })
+    }
+
+    /// Bytes one pixel occupies in memory; 15bpp pixels take 2
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bpp.div_ceil(8)
+    }
+
+    /// Pack a 0xAARRGGBB color into the framebuffer format and write at (x,y).
+    /// Supports common bpps: 32 (4 bytes), 24 (3 bytes), 16 and 15 (2 bytes).
+    /// 16bpp with a 5-bit green channel in `layout` is treated as RGB555 too.
+    /// Channels are placed according to `layout`, so BGR framebuffers work too.
+    /// 8bpp paletted is not handled here.
+    pub unsafe fn put_pixel(&self, x: usize, y: usize, color: u32) {
+        if x >= self.width || y >= self.height { return; }
+
+        let base = self.phys_addr as *mut u8;
+        let offset = y * self.pitch + x * self.bytes_per_pixel();
+        let p = base.add(offset);
+        let pixel = self.layout.pack(color);
+
+        match self.bpp {
+            32 => {
+                // write full u32
+                let ptr = p as *mut u32;
+                write_volatile(ptr, pixel);
+            }
+            24 => {
+                // little-endian: lowest byte of the packed pixel first
+                core::ptr::write_volatile(p, pixel as u8);
+                core::ptr::write_volatile(p.add(1), (pixel >> 8) as u8);
+                core::ptr::write_volatile(p.add(2), (pixel >> 16) as u8);
+            }
+            15 | 16 => {
+                // RGB555 leaves bit 15 clear: pack only sets the channel bits
+                let ptr16 = p as *mut u16;
+                write_volatile(ptr16, pixel as u16);
+            }
+            other => {
+                // unsupported bpp: do nothing or fallback
+                let _ = other;
+            }
+        }
+    }
+
+    /// Read the pixel at (x,y) back as 0xAARRGGBB (alpha is always 0xFF).
+    /// Returns None when out of bounds or for unsupported bpps.
+    pub unsafe fn get_pixel(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height { return None; }
+
+        let base = self.phys_addr as *const u8;
+        let offset = y * self.pitch + x * self.bytes_per_pixel();
+        let p = base.add(offset);
+
+        let pixel = match self.bpp {
+            32 => read_volatile(p as *const u32),
+            24 => {
+                (read_volatile(p) as u32)
+                    | ((read_volatile(p.add(1)) as u32) << 8)
+                    | ((read_volatile(p.add(2)) as u32) << 16)
+            }
+            15 | 16 => read_volatile(p as *const u16) as u32,
+            _ => return None,
+        };
+
+        Some(self.layout.unpack(pixel))
+    }
+
+    /// Alpha-composite a 0xAARRGGBB color over the pixel at (x,y).
+    /// Integer-only: out = (src*a + dst*(255-a)) / 255 per channel.
+    /// Formats that can't be read back fall back to an opaque write when alpha >= 128.
+    pub unsafe fn blend_pixel(&self, x: usize, y: usize, src_argb: u32) {
+        let a = (src_argb >> 24) & 0xFF;
+        if a == 0 { return; }
+        if a == 0xFF {
+            self.put_pixel(x, y, src_argb);
+            return;
+        }
+
+        let dst = match self.get_pixel(x, y) {
+            Some(dst) => dst,
+            None => {
+                if a >= 128 { self.put_pixel(x, y, src_argb); }
+                return;
+            }
+        };
+
+        self.put_pixel(x, y, blend_argb(src_argb, dst));
+    }
+
+    /// XOR the pixel at (x,y) with `color`. Doing it twice restores the original,
+    /// so XOR shapes can be erased by redrawing them (rubber-band selections,
+    /// drag outlines) without saving what was underneath. The XOR is done on the
+    /// packed pixel, which keeps that true at 15/16bpp where a round trip through
+    /// 0xAARRGGBB would lose bits. Alpha is ignored.
+    pub unsafe fn xor_pixel(&self, x: usize, y: usize, color: u32) {
+        if x >= self.width || y >= self.height { return; }
+
+        let p = (self.phys_addr as *mut u8).add(y * self.pitch + x * self.bytes_per_pixel());
+        let mask = self.layout.pack(color);
+
+        match self.bpp {
+            32 => {
+                let ptr = p as *mut u32;
+                write_volatile(ptr, read_volatile(ptr) ^ mask);
+            }
+            24 => {
+                for i in 0..3 {
+                    write_volatile(p.add(i), read_volatile(p.add(i)) ^ (mask >> (8 * i)) as u8);
+                }
+            }
+            15 | 16 => {
+                let ptr16 = p as *mut u16;
+                write_volatile(ptr16, read_volatile(ptr16) ^ mask as u16);
+            }
+            _ => {}
+        }
+    }
+
+    /// Rectangle outline drawn with xor_pixel; drawing it again with the same
+    /// arguments erases it. Edges are one pixel wide and each pixel is XORed
+    /// exactly once, corners included, so a second pass restores everything.
+    pub unsafe fn draw_rect_xor(&self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        if w == 0 || h == 0 { return; }
+        let right = x + w - 1;
+        let bottom = y + h - 1;
+
+        for xx in x..=right {
+            self.xor_pixel(xx, y, color);
+            if h > 1 {
+                self.xor_pixel(xx, bottom, color);
+            }
+        }
+        for yy in y + 1..bottom {
+            self.xor_pixel(x, yy, color);
+            if w > 1 {
+                self.xor_pixel(right, yy, color);
+            }
+        }
+    }
+
+    /// Darken the w×h region at (x,y) toward black, multiplying each RGB channel
+    /// by factor/255: 255 changes nothing, 0 paints it black, 96 or so gives a
+    /// dialog backdrop. Clipped to the framebuffer.
+    pub unsafe fn dim(&self, x: usize, y: usize, w: usize, h: usize, factor: u8) {
+        if factor == 0xFF { return; }
+        let x1 = (x + w).min(self.width);
+        let y1 = (y + h).min(self.height);
+        if self.bpp == 32 {
+            for yy in y..y1 {
+                let mut ptr = (self.phys_addr as *mut u8).add(yy * self.pitch + x * 4) as *mut u32;
+                for _ in x..x1 {
+                    let color = self.layout.unpack(read_volatile(ptr));
+                    write_volatile(ptr, self.layout.pack(scale_argb(color, factor)));
+                    ptr = ptr.add(1);
+                }
+            }
+        } else {
+            for yy in y..y1 {
+                for xx in x..x1 {
+                    if let Some(color) = self.get_pixel(xx, yy) {
+                        self.put_pixel(xx, yy, scale_argb(color, factor));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill pixels x0..x1 of row y with `color`, using the widest writes each bpp
+    /// allows: two 32bpp pixels per u64, one u16 per 15/16bpp pixel. At 8bpp the
+    /// low byte of `color` is the palette index. Clipped to the framebuffer.
+    unsafe fn fill_row(&self, y: usize, x0: usize, x1: usize, color: u32) {
+        let x1 = x1.min(self.width);
+        if y >= self.height || x0 >= x1 { return; }
+        let pixel = self.layout.pack(color);
+        let row = (self.phys_addr as *mut u8).add(y * self.pitch + x0 * self.bytes_per_pixel());
+        let count = x1 - x0;
+
+        match self.bpp {
+            32 => {
+                let mut p = row as *mut u32;
+                let mut left = count;
+                // one u32 first if the span isn't 8-byte aligned, then two pixels per u64
+                if (p as usize) & 7 != 0 {
+                    write_volatile(p, pixel);
+                    p = p.add(1);
+                    left -= 1;
+                }
+                let pair = ((pixel as u64) << 32) | pixel as u64;
+                let mut q = p as *mut u64;
+                for _ in 0..left / 2 {
+                    write_volatile(q, pair);
+                    q = q.add(1);
+                }
+                if left % 2 == 1 {
+                    write_volatile(q as *mut u32, pixel);
+                }
+            }
+            24 => {
+                let bytes = [pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8];
+                let mut p = row;
+                for _ in 0..count {
+                    write_volatile(p, bytes[0]);
+                    write_volatile(p.add(1), bytes[1]);
+                    write_volatile(p.add(2), bytes[2]);
+                    p = p.add(3);
+                }
+            }
+            15 | 16 => {
+                let p = row as *mut u16;
+                for x in 0..count {
+                    write_volatile(p.add(x), pixel as u16);
+                }
+            }
+            8 => {
+                for x in 0..count {
+                    write_volatile(row.add(x), color as u8);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Draw filled rectangle, clipped to the framebuffer; each row goes through fill_row.
+    pub unsafe fn fill_rect(&self, x: usize, y: usize, w: usize, h: usize, color: u32) {
+        let x1 = x.saturating_add(w);
+        let y1 = y.saturating_add(h).min(self.height);
+        for yy in y..y1 {
+            self.fill_row(yy, x, x1, color);
+        }
+    }
+
+    /// Filled rectangle with quarter-circle corners of `radius` pixels. The
+    /// middle band goes through fill_rect; each corner row is one span, inset by
+    /// how far the corner circle is from the edge on that row. Radius is clamped
+    /// to half the shorter side (a full pill/circle); 0 is a plain fill_rect.
+    pub unsafe fn fill_rounded_rect(&self, x: usize, y: usize, w: usize, h: usize, radius: usize, color: u32) {
+        let r = radius.min(w.min(h) / 2);
+        if r == 0 {
+            self.fill_rect(x, y, w, h, color);
+            return;
+        }
+
+        self.fill_rect(x, y + r, w, h - 2 * r, color);
+        for row in 0..r {
+            let inset = corner_inset(r, row);
+            let (left, right) = ((x + inset) as isize, (x + w - 1 - inset) as isize);
+            self.fill_span((y + row) as isize, left, right, color);
+            self.fill_span((y + h - 1 - row) as isize, left, right, color);
+        }
+    }
+
+    /// Rectangle outline `thickness` pixels wide, drawn inside (x,y,w,h).
+    /// The top and bottom edges span the full width and the sides only fill the rows
+    /// in between, so corners are neither gaps nor drawn twice. Thickness 0 draws
+    /// nothing; a thickness of half the shorter side or more fills the rectangle.
+    pub unsafe fn draw_rect(&self, x: usize, y: usize, w: usize, h: usize, thickness: usize, color: u32) {
+        if thickness == 0 || w == 0 || h == 0 { return; }
+        if thickness >= w.min(h) / 2 {
+            self.fill_rect(x, y, w, h, color);
+            return;
+        }
+
+        let side_h = h - 2 * thickness;
+        self.fill_rect(x, y, w, thickness, color);                            // top
+        self.fill_rect(x, y + h - thickness, w, thickness, color);            // bottom
+        self.fill_rect(x, y + thickness, thickness, side_h, color);           // left
+        self.fill_rect(x + w - thickness, y + thickness, thickness, side_h, color); // right
+    }
+
+    /// Copy a w×h rectangle from (src_x,src_y) to (dst_x,dst_y) within the framebuffer.
+    /// Overlapping regions are handled like memmove: rows are walked top-down when
+    /// moving up and bottom-up when moving down, columns likewise. Both rectangles
+    /// are clipped to the framebuffer.
+    pub unsafe fn copy_rect(&self, src_x: usize, src_y: usize, dst_x: usize, dst_y: usize, w: usize, h: usize) {
+        if src_x >= self.width || dst_x >= self.width || src_y >= self.height || dst_y >= self.height {
+            return;
+        }
+        let w = w.min(self.width - src_x).min(self.width - dst_x);
+        let h = h.min(self.height - src_y).min(self.height - dst_y);
+        if w == 0 || h == 0 { return; }
+
+        let bytes_pp = self.bytes_per_pixel();
+        if bytes_pp == 0 { return; }
+        let base = self.phys_addr as *mut u8;
+
+        for i in 0..h {
+            // moving down: copy the bottom row first so we never read an overwritten row
+            let row = if dst_y > src_y { h - 1 - i } else { i };
+            let src = base.add((src_y + row) * self.pitch + src_x * bytes_pp);
+            let dst = base.add((dst_y + row) * self.pitch + dst_x * bytes_pp);
+
+            if self.bpp == 32 {
+                // word copies; walk right-to-left when shifting right within a row
+                let src = src as *const u32;
+                let dst = dst as *mut u32;
+                for j in 0..w {
+                    let col = if dst_x > src_x { w - 1 - j } else { j };
+                    write_volatile(dst.add(col), read_volatile(src.add(col)));
+                }
+            } else {
+                let n = w * bytes_pp;
+                for j in 0..n {
+                    let k = if dst_x > src_x { n - 1 - j } else { j };
+                    write_volatile(dst.add(k), read_volatile(src.add(k)));
+                }
+            }
+        }
+    }
+
+    /// Shift the whole image up by `pixels` rows and fill the exposed bottom rows
+    /// with `fill`. Scrolling by the full height or more just fills the screen.
+    pub unsafe fn scroll_up(&self, pixels: usize, fill: u32) {
+        if pixels == 0 { return; }
+        if pixels >= self.height {
+            self.fill_rect(0, 0, self.width, self.height, fill);
+            return;
+        }
+        self.copy_rect(0, pixels, 0, 0, self.width, self.height - pixels);
+        self.fill_rect(0, self.height - pixels, self.width, pixels, fill);
+    }
+
+    /// Bresenham line (integer) — draws a 1px wide line.
+    pub unsafe fn draw_line(&self, x0: isize, y0: isize, x1: isize, y1: isize, color: u32) {
+        bresenham(x0, y0, x1, y1, |x, y| {
+            if x >= 0 && (x as usize) < self.width && y >= 0 && (y as usize) < self.height {
+                self.put_pixel(x as usize, y as usize, color);
+            }
+        });
+    }
+
+    /// Bresenham line `thickness` pixels wide: each step fills a span across the
+    /// major axis (vertical for flat lines, horizontal for steep ones) centred on
+    /// the 1px line, so the ends are square and diagonals come out a little
+    /// thinner than flat lines. Spans are clipped to the framebuffer. A thickness
+    /// of 0 draws nothing, 1 is draw_line.
+    pub unsafe fn draw_line_thick(&self, x0: isize, y0: isize, x1: isize, y1: isize, thickness: usize, color: u32) {
+        if thickness <= 1 {
+            if thickness == 1 { self.draw_line(x0, y0, x1, y1, color); }
+            return;
+        }
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        let before = ((thickness - 1) / 2) as isize;
+        let len = thickness as isize;
+        bresenham(x0, y0, x1, y1, |x, y| {
+            let (sx, sy, w, h) = if steep { (x - before, y, len, 1) } else { (x, y - before, 1, len) };
+            // clip the span's negative side here; fill_rect clips the far edges
+            let (cx, cy) = (sx.max(0), sy.max(0));
+            let (w, h) = (w - (cx - sx), h - (cy - sy));
+            if w > 0 && h > 0 {
+                self.fill_rect(cx as usize, cy as usize, w as usize, h as usize, color);
+            }
+        });
+    }
+
+    /// Anti-aliased line (Xiaolin Wu) between sub-pixel endpoints; pixel centers
+    /// sit on integer coordinates. The endpoints are converted to 24.8 fixed point
+    /// up front, so the per-pixel coverage math is integer-only, and each pixel is
+    /// composited with blend_pixel at an alpha equal to its coverage.
+    pub unsafe fn draw_line_aa(&self, x0: f32, y0: f32, x1: f32, y1: f32, color: u32) {
+        const FRAC: u32 = 8;
+        const ONE: i64 = 1 << FRAC;
+        const HALF: i64 = ONE / 2;
+
+        let to_fixed = |v: f32| (v * ONE as f32) as i32 as i64;
+        let (mut x0, mut y0, mut x1, mut y1) = (to_fixed(x0), to_fixed(y0), to_fixed(x1), to_fixed(y1));
+
+        // walk along the major axis, left to right
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            core::mem::swap(&mut x0, &mut y0);
+            core::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            core::mem::swap(&mut x0, &mut x1);
+            core::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0 { ONE } else { (y1 - y0) * ONE / dx };
+        let fpart = |v: i64| v & (ONE - 1);
+        let rfpart = |v: i64| ONE - fpart(v);
+
+        // coverage is 0..=ONE; major/minor are in the swapped space
+        let plot = |major: i64, minor: i64, coverage: i64| {
+            let (x, y) = if steep { (minor, major) } else { (major, minor) };
+            if coverage <= 0 || x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+                return;
+            }
+            let alpha = (coverage * 255 / ONE) as u32;
+            self.blend_pixel(x as usize, y as usize, (alpha << 24) | (color & 0x00FF_FFFF));
+        };
+
+        // Endpoints: coverage is also weighted by how much of the end pixel the line spans
+        let endpoint = |x: i64, y: i64, xgap: i64| {
+            let xend = (x + HALF) & !(ONE - 1);
+            let yend = y + gradient * (xend - x) / ONE;
+            let px = xend >> FRAC;
+            plot(px, yend >> FRAC, rfpart(yend) * xgap / ONE);
+            plot(px, (yend >> FRAC) + 1, fpart(yend) * xgap / ONE);
+            (px, yend)
+        };
+        let (first, yend) = endpoint(x0, y0, rfpart(x0 + HALF));
+        let (last, _) = endpoint(x1, y1, fpart(x1 + HALF));
+
+        // Interior pixels, clipped along the major axis so huge lines stay cheap
+        let limit = if steep { self.height } else { self.width } as i64;
+        let start = (first + 1).max(0);
+        let end = last.min(limit);
+        let mut intery = yend + gradient * (start - first);
+        for major in start..end {
+            plot(major, intery >> FRAC, rfpart(intery));
+            plot(major, (intery >> FRAC) + 1, fpart(intery));
+            intery += gradient;
+        }
+    }
+
+    /// Fill the inclusive span x0..=x1 on row y, clipped to the framebuffer.
+    unsafe fn fill_span(&self, y: isize, x0: isize, x1: isize, color: u32) {
+        if y < 0 || y >= self.height as isize || x1 < 0 || x0 >= self.width as isize {
+            return;
+        }
+        let left = x0.max(0) as usize;
+        let right = x1 as usize + 1;
+        self.fill_row(y as usize, left, right, color);
+    }
+
+    /// Filled triangle using scanline spans. Vertices are sorted by y, the left/right
+    /// edges are interpolated per row and every span goes through fill_row.
+    /// Degenerate triangles (all vertices on one row or one line) still draw their pixels.
+    pub unsafe fn fill_triangle(&self, p0: (isize, isize), p1: (isize, isize), p2: (isize, isize), color: u32) {
+        // sort so that a.y <= b.y <= c.y
+        let (mut a, mut b, mut c) = (p0, p1, p2);
+        if a.1 > b.1 { core::mem::swap(&mut a, &mut b); }
+        if b.1 > c.1 { core::mem::swap(&mut b, &mut c); }
+        if a.1 > b.1 { core::mem::swap(&mut a, &mut b); }
+
+        // flat (zero height): one span covering all three x values
+        if a.1 == c.1 {
+            let x0 = a.0.min(b.0).min(c.0);
+            let x1 = a.0.max(b.0).max(c.0);
+            self.fill_span(a.1, x0, x1, color);
+            return;
+        }
+
+        // x on the edge (p,q) at row y; callers guarantee p.1 <= y <= q.1 and p.1 != q.1
+        let edge_x = |p: (isize, isize), q: (isize, isize), y: isize| {
+            p.0 + (q.0 - p.0) * (y - p.1) / (q.1 - p.1)
+        };
+
+        let y_start = a.1.max(0);
+        let y_end = c.1.min(self.height as isize - 1);
+        for y in y_start..=y_end {
+            // long edge a->c spans the whole height; the short side switches at b
+            let xa = edge_x(a, c, y);
+            let xb = if y < b.1 {
+                edge_x(a, b, y)
+            } else if b.1 == c.1 {
+                // flat bottom: only the last row gets here, running from c (xa) to b
+                b.0
+            } else {
+                edge_x(b, c, y)
+            };
+            self.fill_span(y, xa.min(xb), xa.max(xb), color);
+        }
+    }
+
+    /// Triangle outline via three draw_line calls (handy for checking fill_triangle).
+    pub unsafe fn draw_triangle(&self, p0: (isize, isize), p1: (isize, isize), p2: (isize, isize), color: u32) {
+        self.draw_line(p0.0, p0.1, p1.0, p1.1, color);
+        self.draw_line(p1.0, p1.1, p2.0, p2.1, color);
+        self.draw_line(p2.0, p2.1, p0.0, p0.1, color);
+    }
+
+    /// Draw a simple gradient background (horizontal).
+    pub unsafe fn draw_gradient(&self) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                // mix two colors based on x/width
+                let t = (x * 255) / (self.width.saturating_sub(1));
+                // red to blue gradient
+                let r = t as u32;
+                let g = ((y * 128) / (self.height.saturating_sub(1))) as u32;
+                let b = (255 - t) as u32;
+                let color = (0xFF << 24) | (r << 16) | (g << 8) | b;
+                self.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Draw a w×h sprite at (x,y), first copying the pixels it covers into `save`
+    /// (row-major, w per row) so restore_cursor can undo it. Sprite pixels equal to
+    /// CURSOR_TRANSPARENT are skipped. Only the on-screen part is saved and drawn.
+    pub unsafe fn draw_cursor(&self, x: usize, y: usize, sprite: &[u32], w: usize, h: usize, save: &mut [u32]) {
+        if sprite.len() < w * h || save.len() < w * h { return; }
+        let visible_w = w.min(self.width.saturating_sub(x));
+        let visible_h = h.min(self.height.saturating_sub(y));
+
+        for row in 0..visible_h {
+            for col in 0..visible_w {
+                let i = row * w + col;
+                save[i] = self.get_pixel(x + col, y + row).unwrap_or(0);
+                if sprite[i] != CURSOR_TRANSPARENT {
+                    self.put_pixel(x + col, y + row, sprite[i]);
+                }
+            }
+        }
+    }
+
+    /// Put back the pixels saved by draw_cursor at the same (x,y) and size
+    pub unsafe fn restore_cursor(&self, x: usize, y: usize, w: usize, h: usize, save: &[u32]) {
+        if save.len() < w * h { return; }
+        let visible_w = w.min(self.width.saturating_sub(x));
+        let visible_h = h.min(self.height.saturating_sub(y));
+
+        for row in 0..visible_h {
+            for col in 0..visible_w {
+                self.put_pixel(x + col, y + row, save[row * w + col]);
+            }
+        }
+    }
+
+    /// Blit a w×h row-major 0xAARRGGBB image at (x,y). Opaque pixels are written
+    /// as they are and the rest blended by their alpha (alpha 0 leaves the screen
+    /// alone). Clipped at the right and bottom edges; does nothing if `pixels`
+    /// holds fewer than w*h entries.
+    pub unsafe fn draw_bitmap(&self, x: usize, y: usize, w: usize, h: usize, pixels: &[u32]) {
+        if pixels.len() < w * h { return; }
+        let visible_w = w.min(self.width.saturating_sub(x));
+        let visible_h = h.min(self.height.saturating_sub(y));
+
+        for row in 0..visible_h {
+            for (col, &pixel) in pixels[row * w..row * w + visible_w].iter().enumerate() {
+                if pixel >> 24 == 0xFF {
+                    self.put_pixel(x + col, y + row, pixel);
+                } else {
+                    self.blend_pixel(x + col, y + row, pixel);
+                }
+            }
+        }
+    }
+
+    /// draw_bitmap for pre-composited images: every pixel is written as opaque,
+    /// whatever its alpha byte says
+    pub unsafe fn draw_bitmap_opaque(&self, x: usize, y: usize, w: usize, h: usize, pixels: &[u32]) {
+        if pixels.len() < w * h { return; }
+        let visible_w = w.min(self.width.saturating_sub(x));
+        let visible_h = h.min(self.height.saturating_sub(y));
+
+        for row in 0..visible_h {
+            for (col, &pixel) in pixels[row * w..row * w + visible_w].iter().enumerate() {
+                self.put_pixel(x + col, y + row, pixel);
+            }
+        }
+    }
+
+    /// Blit a w×h row-major image where pixels equal to `key` (e.g. magenta
+    /// 0xFFFF00FF) are transparent and the rest are written opaque. Clipped at
+    /// the right and bottom edges; does nothing if `pixels` holds fewer than w*h
+    /// entries.
+    pub unsafe fn draw_bitmap_keyed(&self, x: usize, y: usize, w: usize, h: usize, pixels: &[u32], key: u32) {
+        if pixels.len() < w * h { return; }
+        let visible_w = w.min(self.width.saturating_sub(x));
+        let visible_h = h.min(self.height.saturating_sub(y));
+
+        for row in 0..visible_h {
+            for (col, &pixel) in pixels[row * w..row * w + visible_w].iter().enumerate() {
+                if pixel != key {
+                    self.put_pixel(x + col, y + row, pixel);
+                }
+            }
+        }
+    }
+
+    /// Fill the whole screen with a start→end ARGB gradient along `direction`.
+    /// t runs 0..=255 across the chosen axis and is computed per pixel in integer math.
+    pub unsafe fn draw_gradient_ex(&self, start_argb: u32, end_argb: u32, direction: GradientDir) {
+        let max_x = self.width.saturating_sub(1);
+        let max_y = self.height.saturating_sub(1);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (pos, span) = match direction {
+                    GradientDir::Horizontal => (x, max_x),
+                    GradientDir::Vertical => (y, max_y),
+                    GradientDir::Diagonal => (x + y, max_x + max_y),
+                };
+                let t = (pos * 255).checked_div(span).unwrap_or(0) as u32;
+                self.put_pixel(x, y, lerp_argb(start_argb, end_argb, t));
+            }
+        }
+    }
+
+    /// Bring-up pattern for checking a new mode at a glance:
+    /// - top third: SMPTE-style 75% bars (white, yellow, cyan, green, magenta, red,
+    ///   blue). Swapped red/blue shows as cyan and yellow trading places.
+    /// - middle third: checkerboard of TEST_PATTERN_SQUARE px squares. A wrong pitch
+    ///   shears it into diagonal stripes.
+    /// - bottom third: black-to-red, -green and -blue ramps, drawn on every other
+    ///   row (1px black gaps), so each channel's depth and order can be read off.
+    pub unsafe fn draw_test_pattern(&self) {
+        let band = self.height / 3;
+
+        let bar_w = self.width.div_ceil(TEST_PATTERN_BARS.len());
+        for (i, &color) in TEST_PATTERN_BARS.iter().enumerate() {
+            self.fill_rect(i * bar_w, 0, bar_w, band, color);
+        }
+
+        for y in band..2 * band {
+            for x in 0..self.width {
+                let white = (x / TEST_PATTERN_SQUARE + (y - band) / TEST_PATTERN_SQUARE).is_multiple_of(2);
+                self.put_pixel(x, y, if white { 0xFF_FF_FF_FF } else { 0xFF_00_00_00 });
+            }
+        }
+
+        let ramps_top = 2 * band;
+        let ramp_h = (self.height - ramps_top) / 3;
+        let max_x = self.width.saturating_sub(1);
+        for y in ramps_top..self.height {
+            let channel = ((y - ramps_top) / ramp_h.max(1)).min(2);
+            let gap = (y - ramps_top) % 2 == 1;
+            for x in 0..self.width {
+                let level = if gap { 0 } else { (x * 255).checked_div(max_x).unwrap_or(0) as u32 };
+                // red, green, blue in ARGB bit order
+                self.put_pixel(x, y, 0xFF00_0000 | level << (16 - 8 * channel));
+            }
+        }
+    }
+
+    /// Write a pixel in 32bpp (channel order taken from `layout`).
+    /// color is 0xAARRGGBB (alpha ignored for many modes).
+    /// This assumes physical == virtual (identity mapping). Map if using paging.
+    pub unsafe fn put_pixel_32(&self, x: usize, y: usize, color: u32) {
+        if x >= self.width || y >= self.height { return; }
+        let base = self.phys_addr as *mut u8;
+        let offset = y * self.pitch + x * 4;
+        let ptr = base.add(offset) as *mut u32;
+        write_volatile(ptr, self.layout.pack(color));
+    }
+
+    /// Clear screen (32bpp) to color.
+    pub unsafe fn clear_32(&self, color: u32) {
+        self.fill(color);
+    }
+
+    /// Fill the whole screen with `color`, using the widest writes each bpp allows.
+    /// Rows are addressed through `pitch`, so padding at the end of a row is left alone.
+    pub unsafe fn fill(&self, color: u32) {
+        for y in 0..self.height {
+            self.fill_row(y, 0, self.width, color);
+        }
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
+impl Framebuffer {
+    /// Host backend: draw into a 0x00RRGGBB pixel buffer such as the one minifb displays.
+    /// The Framebuffer does not borrow `buf`, so `buf` must outlive every draw call
+    /// and must not be reallocated while it is in use.
+    pub fn from_xrgb_buffer(buf: &mut [u32], width: usize, height: usize) -> Self {
+        assert!(buf.len() >= width * height, "buffer smaller than width * height");
+        Framebuffer {
+            phys_addr: buf.as_mut_ptr() as usize,
+            pitch: width * 4,
+            width,
+            height,
+            bpp: 32,
+            layout: ColorLayout::default_for_bpp(32),
+        }
+    }
+
+    /// Memory backend for any supported bpp, mainly for tests: `buf` holds `height`
+    /// rows of `pitch` bytes in the default layout for `bpp`. Same lifetime rules
+    /// as from_xrgb_buffer.
+    pub fn from_slice(buf: &mut [u8], width: usize, height: usize, pitch: usize, bpp: usize) -> Self {
+        assert!(pitch >= width * bpp.div_ceil(8), "pitch smaller than a row of pixels");
+        assert!(buf.len() >= pitch * height, "buffer smaller than pitch * height");
+        Framebuffer {
+            phys_addr: buf.as_mut_ptr() as usize,
+            pitch,
+            width,
+            height,
+            bpp,
+            layout: ColorLayout::default_for_bpp(bpp),
+        }
+    }
+}
+
+/// Walk the Bresenham line from (x0,y0) to (x1,y1), both ends included,
+/// calling `plot` once per step (unclipped)
+fn bresenham(x0: isize, y0: isize, x1: isize, y1: isize, mut plot: impl FnMut(isize, isize)) {
+    let dx = (x1 - x0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let dy = -(y1 - y0).abs();
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut x = x0;
+    let mut y = y0;
+    loop {
+        plot(x, y);
+        if x == x1 && y == y1 { break; }
+        let e2 = 2*err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A zeroed buffer and a Framebuffer drawing into it
+    fn memory_fb(width: usize, height: usize, pitch: usize, bpp: usize) -> (Vec<u8>, Framebuffer) {
+        let mut buf = vec![0u8; pitch * height];
+        let fb = Framebuffer::from_slice(&mut buf, width, height, pitch, bpp);
+        (buf, fb)
+    }
+
+    #[test]
+    fn new_checked_rejects_bad_geometry() {
+        assert_eq!(Framebuffer::new_checked(0x1000, 4096, 1024, 768, 12).err(), Some(FbError::UnsupportedBpp(12)));
+        assert_eq!(Framebuffer::new_checked(0x1000, 4096, 0, 768, 32).err(), Some(FbError::ZeroSize));
+        assert_eq!(
+            Framebuffer::new_checked(0x1000, 2000, 1024, 768, 15).err(),
+            Some(FbError::PitchTooSmall { pitch: 2000, min: 2048 })
+        );
+        let fb = Framebuffer::new_checked(0x1000, 3072, 1024, 768, 24).unwrap();
+        assert_eq!((fb.width, fb.height, fb.bytes_per_pixel()), (1024, 768, 3));
+    }
+
+    #[test]
+    fn put_pixel_32bpp_is_little_endian_xrgb() {
+        let (buf, fb) = memory_fb(4, 3, 16, 32);
+        unsafe { fb.put_pixel(1, 2, 0xFF11_2233) };
+        assert_eq!(&buf[36..40], &[0x33, 0x22, 0x11, 0x00]);
+        assert_eq!(buf.iter().filter(|&&b| b != 0).count(), 3);
+        assert_eq!(unsafe { fb.get_pixel(1, 2) }, Some(0xFF11_2233));
+    }
+
+    #[test]
+    fn put_pixel_24bpp_writes_three_bytes_and_respects_pitch() {
+        // 4 pixels = 12 bytes per row, padded to 16
+        let (buf, fb) = memory_fb(4, 2, 16, 24);
+        unsafe { fb.put_pixel(3, 1, 0xFFAA_BBCC) };
+        assert_eq!(&buf[25..28], &[0xCC, 0xBB, 0xAA]);
+        assert!(buf[..25].iter().chain(&buf[28..]).all(|&b| b == 0));
+        assert_eq!(unsafe { fb.get_pixel(3, 1) }, Some(0xFFAA_BBCC));
+    }
+
+    #[test]
+    fn put_pixel_16bpp_packs_rgb565() {
+        let (buf, fb) = memory_fb(2, 2, 4, 16);
+        unsafe {
+            fb.put_pixel(0, 0, 0xFFFF_0000);
+            fb.put_pixel(1, 0, 0xFF00_FF00);
+            fb.put_pixel(0, 1, 0xFF00_00FF);
+        }
+        assert_eq!(&buf[0..2], &0xF800u16.to_le_bytes());
+        assert_eq!(&buf[2..4], &0x07E0u16.to_le_bytes());
+        assert_eq!(&buf[4..6], &0x001Fu16.to_le_bytes());
+        assert_eq!(&buf[6..8], &[0, 0]);
+    }
+
+    #[test]
+    fn rgb565_round_trips_within_quantization_error() {
+        let colors = [0xFF12_3456, 0xFFFF_FFFF, 0xFF00_0000, 0xFF7F_8081, 0xFF07_0307, 0xFFF8_FCF8, 0xFFAB_CDEF];
+        let (_buf, fb) = memory_fb(colors.len(), 1, colors.len() * 2, 16);
+        for (x, &color) in colors.iter().enumerate() {
+            unsafe { fb.put_pixel(x, 0, color) };
+        }
+        for (x, &color) in colors.iter().enumerate() {
+            let back = unsafe { fb.get_pixel(x, 0) }.unwrap();
+            assert_eq!(back >> 24, 0xFF);
+            // 5-bit red/blue lose 3 bits, 6-bit green loses 2
+            for (shift, max_err) in [(16, 4), (8, 2), (0, 4)] {
+                let want = ((color >> shift) & 0xFF) as i32;
+                let got = ((back >> shift) & 0xFF) as i32;
+                assert!((want - got).abs() <= max_err, "{color:#010x} -> {back:#010x}, channel at {shift}");
+            }
+        }
+    }
+
+    #[test]
+    fn put_pixel_15bpp_packs_rgb555_and_reads_back() {
+        let (buf, fb) = memory_fb(2, 1, 4, 15);
+        unsafe {
+            fb.put_pixel(0, 0, 0xFF00_FF00);
+            fb.put_pixel(1, 0, 0xFFFF_FFFF);
+        }
+        assert_eq!(&buf[0..2], &0x03E0u16.to_le_bytes());
+        // bit 15 stays clear even for white
+        assert_eq!(&buf[2..4], &0x7FFFu16.to_le_bytes());
+        assert_eq!(unsafe { fb.get_pixel(0, 0) }, Some(0xFF00_FF00));
+        assert_eq!(unsafe { fb.get_pixel(1, 0) }, Some(0xFFFF_FFFF));
+    }
+
+    #[test]
+    fn put_pixel_out_of_bounds_is_ignored() {
+        // Padding bytes past the visible width must stay untouched too
+        let (buf, fb) = memory_fb(3, 2, 16, 32);
+        unsafe {
+            fb.put_pixel(3, 0, 0xFFFF_FFFF);
+            fb.put_pixel(0, 2, 0xFFFF_FFFF);
+            fb.put_pixel(usize::MAX, usize::MAX, 0xFFFF_FFFF);
+        }
+        assert!(buf.iter().all(|&b| b == 0));
+        assert_eq!(unsafe { fb.get_pixel(3, 0) }, None);
+    }
+
+    #[test]
+    fn fill_rect_is_clipped_to_the_edges() {
+        let (_buf, fb) = memory_fb(4, 4, 16, 32);
+        unsafe { fb.fill_rect(2, 3, 10, 10, 0xFFFF_FFFF) };
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x >= 2 && y == 3 { 0xFFFF_FFFF } else { 0xFF00_0000 };
+                assert_eq!(unsafe { fb.get_pixel(x, y) }, Some(expected), "pixel ({x},{y})");
+            }
+        }
+    }
+
+    #[test]
+    fn fill_rect_word_writes_stop_at_the_span_ends() {
+        // 32bpp: spans starting on and off an 8-byte boundary, odd and even lengths
+        for (x, w) in [(0, 5), (1, 4), (1, 5), (2, 1), (3, 6)] {
+            let (buf, fb) = memory_fb(9, 2, 40, 32);
+            unsafe { fb.fill_rect(x, 1, w, 1, 0xFF12_3456) };
+            for (i, pixel) in buf.chunks(4).enumerate() {
+                let inside = i >= 10 + x && i < 10 + (x + w).min(9);
+                let want: &[u8] = if inside { &[0x56, 0x34, 0x12, 0] } else { &[0; 4] };
+                assert_eq!(pixel, want, "x={x} w={w} pixel {i}");
+            }
+        }
+
+        let (buf, fb) = memory_fb(5, 1, 12, 16);
+        unsafe { fb.fill_rect(1, 0, 3, 1, 0xFFFF_0000) };
+        assert_eq!(buf[..2], [0, 0]);
+        assert!(buf[2..8].chunks(2).all(|p| p == 0xF800u16.to_le_bytes()));
+        assert!(buf[8..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn draw_rect_outlines_with_thickness() {
+        let (_buf, fb) = memory_fb(9, 8, 36, 32);
+        unsafe { fb.draw_rect(1, 1, 7, 6, 2, 0xFFFF_FFFF) };
+        for y in 0..8 {
+            for x in 0..9 {
+                let inside = (1..8).contains(&x) && (1..7).contains(&y);
+                let hole = (3..6).contains(&x) && (3..5).contains(&y);
+                let expected = if inside && !hole { 0xFFFF_FFFF } else { 0xFF00_0000 };
+                assert_eq!(unsafe { fb.get_pixel(x, y) }, Some(expected), "pixel ({x},{y})");
+            }
+        }
+
+        // too thick for a hole: filled; zero thickness: nothing
+        let (_buf, fb) = memory_fb(4, 4, 16, 32);
+        unsafe {
+            fb.draw_rect(0, 0, 4, 4, 0, 0xFFFF_FFFF);
+            assert_eq!(fb.get_pixel(0, 0), Some(0xFF00_0000));
+            fb.draw_rect(0, 0, 4, 4, 2, 0xFFFF_FFFF);
+            assert!((0..16).all(|i| fb.get_pixel(i % 4, i / 4) == Some(0xFFFF_FFFF)));
+        }
+    }
+
+    #[test]
+    fn draw_line_aa_splits_coverage_between_rows() {
+        let (_buf, fb) = memory_fb(8, 6, 32, 32);
+        unsafe {
+            // On pixel centers: the interior row is fully covered, neighbours untouched
+            fb.draw_line_aa(0.0, 1.0, 7.0, 1.0, 0xFFFF_FFFF);
+            for x in 1..7 {
+                assert_eq!(fb.get_pixel(x, 0), Some(0xFF00_0000));
+                assert_eq!(fb.get_pixel(x, 1), Some(0xFFFF_FFFF));
+                assert_eq!(fb.get_pixel(x, 2), Some(0xFF00_0000));
+            }
+            // the end pixels are only half covered along x
+            let end = fb.get_pixel(0, 1).unwrap() & 0xFF;
+            assert!((0x70..0x90).contains(&end), "endpoint coverage {end:#x}");
+
+            // Halfway between rows 3 and 4: both get about half the color
+            fb.draw_line_aa(0.0, 3.5, 7.0, 3.5, 0xFFFF_FFFF);
+            for x in 1..7 {
+                for y in [3, 4] {
+                    let v = fb.get_pixel(x, y).unwrap() & 0xFF;
+                    assert!((0x70..0x90).contains(&v), "pixel ({x},{y}) = {v:#x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draw_line_clips_offscreen_parts() {
+        let (buf, fb) = memory_fb(4, 4, 16, 32);
+        unsafe { fb.draw_line(-3, 1, 10, 1, 0xFFFF_FFFF) };
+        for x in 0..4 {
+            assert_eq!(unsafe { fb.get_pixel(x, 1) }, Some(0xFFFF_FFFF));
+        }
+        // Only row 1 was touched: 4 pixels * 3 color bytes
+        assert_eq!(buf.iter().filter(|&&b| b != 0).count(), 12);
+    }
+
+    #[test]
+    fn draw_line_thick_fills_spans_across_the_major_axis_and_clips() {
+        // flat line, 3px: rows 0..=2 around y=1, the top one past the edge at y=0
+        let mut buf = [0u32; 5 * 4];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 5, 4);
+        unsafe { fb.draw_line_thick(-2, 0, 2, 0, 3, 1) };
+        let lit: Vec<usize> = (0..20).filter(|&i| buf[i] != 0).collect();
+        assert_eq!(lit, [0, 1, 2, 5, 6, 7]);
+
+        // steep line, 2px: columns x and x+1 on every row, x+1 past the right edge
+        let mut buf = [0u32; 4 * 3];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 4, 3);
+        unsafe { fb.draw_line_thick(3, 0, 3, 5, 2, 1) };
+        assert_eq!(buf, [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]);
+
+        // 0 draws nothing, 1 matches draw_line
+        let (thin, fb) = memory_fb(4, 4, 16, 32);
+        unsafe {
+            fb.draw_line_thick(0, 0, 3, 3, 0, 0xFFFF_FFFF);
+            fb.draw_line_thick(0, 3, 3, 0, 1, 0xFFFF_FFFF);
+        }
+        let (reference, fb) = memory_fb(4, 4, 16, 32);
+        unsafe { fb.draw_line(0, 3, 3, 0, 0xFFFF_FFFF) };
+        assert_eq!(thin, reference);
+    }
+
+    #[test]
+    fn draw_line_hits_both_endpoints_with_one_pixel_per_step() {
+        for &(x0, y0, x1, y1) in &[(0, 0, 7, 3), (7, 3, 0, 0), (1, 6, 4, 0), (5, 5, 5, 5)] {
+            let (buf, fb) = memory_fb(8, 8, 32, 32);
+            unsafe { fb.draw_line(x0, y0, x1, y1, 0xFFFF_FFFF) };
+            assert_eq!(unsafe { fb.get_pixel(x0 as usize, y0 as usize) }, Some(0xFFFF_FFFF));
+            assert_eq!(unsafe { fb.get_pixel(x1 as usize, y1 as usize) }, Some(0xFFFF_FFFF));
+
+            // Bresenham sets exactly one pixel per step along the major axis
+            let steps = (x1 - x0).abs().max((y1 - y0).abs()) as usize + 1;
+            let set = buf.chunks_exact(4).filter(|px| px[0] != 0).count();
+            assert_eq!(set, steps, "line ({x0},{y0})-({x1},{y1})");
+        }
+    }
+
+    #[test]
+    fn draw_bitmap_blends_translucent_pixels_and_clips() {
+        let mut buf = vec![0u32; 3 * 2];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 3, 2);
+        // 2x2 image at (2,1): only its top-left pixel is on screen
+        let image = [0xFF11_2233, 0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF];
+        unsafe {
+            fb.fill_rect(0, 0, 3, 2, 0xFF00_0000);
+            fb.draw_bitmap(2, 1, 2, 2, &image);
+            // half-transparent white over black, fully transparent, then opaque
+            fb.draw_bitmap(0, 0, 3, 1, &[0x80FF_FFFF, 0x00FF_FFFF, 0xFF00_00FF]);
+            // too few pixels for the size: nothing drawn
+            fb.draw_bitmap(0, 1, 2, 1, &[0xFFFF_FFFF]);
+        }
+        assert_eq!(buf, [0x0080_8080, 0, 0x0000_00FF, 0, 0, 0x0011_2233]);
+    }
+
+    #[test]
+    fn draw_bitmap_keyed_skips_the_key_color_and_clips() {
+        const KEY: u32 = 0xFFFF_00FF;
+        let mut buf = [0x0011_2233u32; 6];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 3, 2);
+        unsafe {
+            // 2x2 sprite at (2,0): the right column falls off the screen
+            fb.draw_bitmap_keyed(2, 0, 2, 2, &[KEY, 0xFF00_00FF, 0xFFAB_CDEF, KEY], KEY);
+            // a key-colored pixel with a different alpha is still drawn
+            fb.draw_bitmap_keyed(0, 1, 2, 1, &[0x00FF_00FF, KEY], KEY);
+            // too few pixels for the size: nothing drawn
+            fb.draw_bitmap_keyed(0, 0, 2, 1, &[0xFFFF_FFFF], KEY);
+        }
+        assert_eq!(buf, [0x0011_2233, 0x0011_2233, 0x0011_2233, 0x00FF_00FF, 0x0011_2233, 0x00AB_CDEF]);
+    }
+
+    #[test]
+    fn fill_rounded_rect_cuts_the_corners() {
+        let (buf, fb) = memory_fb(10, 8, 40, 32);
+        let px = |x: usize, y: usize| u32::from_le_bytes(buf[y * 40 + x * 4..][..4].try_into().unwrap());
+        unsafe { fb.fill_rounded_rect(1, 1, 8, 6, 3, 0xFFFF_FFFF) };
+        let rows: Vec<String> = (0..8)
+            .map(|y| (0..10).map(|x| if px(x, y) != 0 { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(rows, [
+            "..........",
+            "..######..",
+            ".########.",
+            ".########.",
+            ".########.",
+            ".########.",
+            "..######..",
+            "..........",
+        ]);
+
+        // radius 0 is a plain rectangle; a huge radius clamps to half the short side
+        let (buf, fb) = memory_fb(4, 4, 16, 32);
+        unsafe { fb.fill_rounded_rect(0, 0, 4, 4, 0, 0xFF00_00FF) };
+        assert!(buf.chunks(4).all(|p| p == [0xFF, 0, 0, 0]));
+        let (buf, fb) = memory_fb(4, 4, 16, 32);
+        unsafe { fb.fill_rounded_rect(0, 0, 4, 4, 100, 0xFF00_00FF) };
+        assert_eq!(buf[..4], [0, 0, 0, 0]);
+        assert_eq!(buf[4..8], [0xFF, 0, 0, 0]);
+    }
+
+    #[test]
+    fn dim_scales_channels_inside_the_clipped_region() {
+        let mut buf = [0x0080_40FFu32; 6];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 3, 2);
+        unsafe {
+            // runs off the right and bottom edges
+            fb.dim(1, 1, 10, 10, 128);
+            // 255 is a no-op, 0 is black
+            fb.dim(0, 0, 1, 1, 255);
+            fb.dim(1, 0, 1, 1, 0);
+        }
+        assert_eq!(buf, [0x0080_40FF, 0, 0x0080_40FF, 0x0080_40FF, 0x0040_2080, 0x0040_2080]);
+
+        // other depths go through get_pixel/put_pixel
+        let (buf, fb) = memory_fb(2, 1, 6, 24);
+        unsafe {
+            fb.fill_rect(0, 0, 2, 1, 0xFFFF_FFFF);
+            fb.dim(0, 0, 1, 1, 64);
+        }
+        assert_eq!(&buf[..6], &[64, 64, 64, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn scroll_up_moves_rows_and_fills_the_bottom() {
+        let mut buf = [1, 1, 2, 2, 3, 3, 4, 4u32];
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 2, 4);
+        unsafe {
+            fb.scroll_up(0, 9);
+            fb.scroll_up(1, 9);
+        }
+        assert_eq!(buf, [2, 2, 3, 3, 4, 4, 9, 9]);
+
+        let fb = Framebuffer::from_xrgb_buffer(&mut buf, 2, 4);
+        unsafe { fb.scroll_up(5, 7) };
+        assert_eq!(buf, [7; 8]);
+    }
+
+    #[test]
+    fn draw_rect_xor_twice_restores_the_original() {
+        for bpp in [32, 24, 16] {
+            let bytes = bpp / 8;
+            let pitch = 8 * bytes;
+            let (buf, fb) = memory_fb(8, 6, pitch, bpp);
+            let px = |buf: &[u8], x: usize, y: usize| buf[y * pitch + x * bytes..][..bytes].to_vec();
+            unsafe {
+                fb.draw_gradient_ex(0xFF12_3456, 0xFFFE_DCBA, GradientDir::Diagonal);
+                let before = buf.clone();
+                fb.draw_rect_xor(1, 1, 5, 4, 0xFFFF_FFFF);
+                // outline inverted, corners included, inside untouched
+                assert_ne!(px(&buf, 1, 1), px(&before, 1, 1));
+                assert_ne!(px(&buf, 5, 4), px(&before, 5, 4));
+                assert_eq!(px(&buf, 3, 2), px(&before, 3, 2));
+                fb.draw_rect_xor(1, 1, 5, 4, 0xFFFF_FFFF);
+                assert_eq!(buf, before, "{}bpp", bpp);
+                // one-pixel-wide rectangles must not XOR any pixel twice
+                fb.draw_rect_xor(7, 0, 1, 6, 0xFF80_4020);
+                assert_ne!(px(&buf, 7, 3), px(&before, 7, 3));
+                fb.draw_rect_xor(7, 0, 1, 6, 0xFF80_4020);
+                assert_eq!(buf, before, "{}bpp", bpp);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_test_pattern_lays_out_bars_checkerboard_and_ramps() {
+        let (buf, fb) = memory_fb(64, 48, 64 * 4, 32);
+        unsafe { fb.draw_test_pattern() };
+        let px = |x: usize, y: usize| u32::from_le_bytes(buf[y * 256 + x * 4..][..4].try_into().unwrap());
+
+        // bars are ceil(64/7) = 10 px wide; red is the sixth
+        assert_eq!(px(0, 0), 0x00BF_BFBF);
+        assert_eq!(px(50, 15), 0x00BF_0000);
+        // checkerboard starts white at the top of the middle band (y = 16)
+        assert_eq!(px(0, 16), 0x00FF_FFFF);
+        assert_eq!(px(16, 16), 0x0000_0000);
+        assert_eq!(px(16, 32 - 1), 0x0000_0000);
+        // ramps: 5 rows each from y = 32, with black gaps on odd rows
+        assert_eq!(px(63, 32), 0x00FF_0000);
+        assert_eq!(px(0, 32), 0x0000_0000);
+        assert_eq!(px(63, 33), 0x0000_0000);
+        assert_eq!(px(63, 38), 0x0000_FF00);
+        assert_eq!(px(63, 42), 0x0000_00FF);
+    }
+}
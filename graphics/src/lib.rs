@@ -0,0 +1,15 @@
+// src/lib.rs - framebuffer drawing shared by osone, the limine kernel and the minifb prototype
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+// All drawing goes through raw framebuffer pointers; the safety contract is
+// documented once on Framebuffer rather than on every method.
+#![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+
+mod color;
+mod console;
+mod font;
+mod framebuffer;
+
+pub use color::ColorLayout;
+pub use console::{Cell, Console, DEFAULT_BG, DEFAULT_FG, TAB_WIDTH};
+pub use font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+pub use framebuffer::{FbError, Framebuffer, GradientDir, CURSOR_TRANSPARENT};
@@ -1,67 +1,253 @@
-use minifb::{Key, Window, WindowOptions};
-use rusttype::{Font, Scale, point};
-
-const WIDTH: usize = 1366;
-const HEIGHT: usize = 786;
-
-fn put_pixel(buffer: &mut [u32], x: isize, y: isize, color: u32) {
-    if x < 0 || y < 0 { return; }
-    let x = x as usize;
-    let y = y as usize;
-    if x >= WIDTH || y >= HEIGHT { return; }
-    buffer[y * WIDTH + x] = color;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use rusttype::{Font, GlyphId, Scale, point};
+use std::collections::HashMap;
+use std::time::Instant;
+use graphics::Framebuffer;
+
+// initial window size; the window is resizable so the real size is read every frame
+const INITIAL_WIDTH: usize = 1366;
+const INITIAL_HEIGHT: usize = 768;
+const GLYPH_CACHE_CAPACITY: usize = 256;
+// weight of the newest frame in the smoothed FPS readout
+const FPS_SMOOTHING: f32 = 0.1;
+// the input cursor is shown for this long, then hidden for as long
+const CURSOR_BLINK_SECS: f32 = 0.5;
+
+// the pixel buffer shown in the window; width doubles as the row stride
+struct Canvas {
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
 }
 
-// simple alpha blend src_color (ARGB with alpha in top 8 bits) over dest (u32)
-fn blend_pixel(buffer: &mut [u32], x: isize, y: isize, src_color: u32) {
-    if x < 0 || y < 0 { return; }
-    let x = x as usize;
-    let y = y as usize;
-    if x >= WIDTH || y >= HEIGHT { return; }
-
-    let dst_idx = y * WIDTH + x;
-    let dst = buffer[dst_idx];
-
-    // extract components
-    let sa = ((src_color >> 24) & 0xFF) as f32 / 255.0;
-    let sr = ((src_color >> 16) & 0xFF) as f32;
-    let sg = ((src_color >> 8) & 0xFF) as f32;
-    let sb = (src_color & 0xFF) as f32;
-
-    let da = ((dst >> 24) & 0xFF) as f32 / 255.0;
-    let dr = ((dst >> 16) & 0xFF) as f32;
-    let dg = ((dst >> 8) & 0xFF) as f32;
-    let db = (dst & 0xFF) as f32;
-
-    // premultiplied-like blend: out = src*sa + dst*(1-sa)
-    let out_r = (sr * sa + dr * (1.0 - sa)).round().clamp(0.0, 255.0) as u32;
-    let out_g = (sg * sa + dg * (1.0 - sa)).round().clamp(0.0, 255.0) as u32;
-    let out_b = (sb * sa + db * (1.0 - sa)).round().clamp(0.0, 255.0) as u32;
-    let out_a = ((sa + da * (1.0 - sa)) * 255.0).round().clamp(0.0, 255.0) as u32;
-
-    buffer[dst_idx] = (out_a << 24) | (out_r << 16) | (out_g << 8) | out_b;
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas { buffer: vec![0xFF000000; width * height], width, height }
+    }
+
+    // reallocate the buffer if the window size changed
+    fn resize(&mut self, width: usize, height: usize) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![0xFF000000; width * height];
+    }
 }
 
-fn draw_text_rusttype(buffer: &mut [u32], font: &Font, text: &str, x: f32, y: f32, scale: f32, color: u32) {
-    let scale = Scale::uniform(scale);
-    // baseline point: rusttype positions glyphs relative to baseline.
-    let v_metrics = font.v_metrics(scale);
-    let start = point(x, y + v_metrics.ascent);
+// wall-clock frame timing, so animation speed doesn't depend on the frame rate
+struct FrameClock {
+    last: Instant,
+    elapsed: f32, // seconds since the first frame
+    fps: f32,     // smoothed frames per second
+    frames: u64,
+}
 
-    for glyph in font.layout(text, scale, start) {
-        if let Some(bb) = glyph.pixel_bounding_box() {
-            // draw the glyph: rusttype provides coverage [0.0..1.0] as 'v' in the closure
-            glyph.draw(|gx, gy, v| {
-                let px = gx as i32 + bb.min.x;
-                let py = gy as i32 + bb.min.y;
-                if px >= 0 && py >= 0 && (px as usize) < WIDTH && (py as usize) < HEIGHT {
-                    // create src_color with alpha = v
-                    let alpha = (v * 255.0).round() as u32;
-                    let src_color = (alpha << 24) | ( ( (color >> 16) & 0xFF) << 16 ) | ( ( (color >> 8) & 0xFF) << 8 ) | (color & 0xFF);
-                    blend_pixel(buffer, px as isize, py as isize, src_color);
+impl FrameClock {
+    fn new() -> Self {
+        FrameClock { last: Instant::now(), elapsed: 0.0, fps: 0.0, frames: 0 }
+    }
+
+    // start a new frame; returns the seconds since the previous one
+    fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+        self.elapsed += dt;
+        self.frames += 1;
+        if dt > 0.0 {
+            // exponential moving average; the first real delta seeds it
+            let instant_fps = 1.0 / dt;
+            self.fps = if self.fps == 0.0 { instant_fps } else { self.fps + (instant_fps - self.fps) * FPS_SMOOTHING };
+        }
+        dt
+    }
+}
+
+// a rasterized glyph: coverage bitmap plus placement relative to the pen/baseline
+struct CachedGlyph {
+    width: usize,
+    height: usize,
+    coverage: Vec<u8>, // alpha 0..255, row-major width*height
+    offset_x: i32,     // bounding box min.x relative to the pen position
+    offset_y: i32,     // bounding box min.y relative to the baseline
+    advance: f32,
+    id: GlyphId,       // for pair kerning lookups
+    last_used: u64,
+}
+
+// lazily filled cache of rasterized glyphs keyed by (char, scale bits), LRU eviction
+struct GlyphCache<'f> {
+    font: &'f Font<'f>,
+    entries: HashMap<(char, u32), CachedGlyph>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl<'f> GlyphCache<'f> {
+    fn new(font: &'f Font<'f>, capacity: usize) -> Self {
+        GlyphCache { font, entries: HashMap::new(), capacity, clock: 0 }
+    }
+
+    fn get(&mut self, ch: char, scale: f32) -> &CachedGlyph {
+        self.clock += 1;
+        let key = (ch, scale.to_bits());
+
+        if !self.entries.contains_key(&key) {
+            if self.entries.len() >= self.capacity {
+                // evict the least recently used glyph
+                let oldest = self.entries.iter().min_by_key(|(_, g)| g.last_used).map(|(k, _)| *k);
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
                 }
+            }
+            self.entries.insert(key, rasterize_glyph(self.font, ch, scale));
+        }
+
+        let glyph = self.entries.get_mut(&key).unwrap();
+        glyph.last_used = self.clock;
+        glyph
+    }
+}
+
+fn rasterize_glyph(font: &Font, ch: char, scale: f32) -> CachedGlyph {
+    let scaled = font.glyph(ch).scaled(Scale::uniform(scale));
+    let id = scaled.id();
+    let advance = scaled.h_metrics().advance_width;
+    // position at the origin so the bounding box is relative to pen/baseline
+    let glyph = scaled.positioned(point(0.0, 0.0));
+
+    match glyph.pixel_bounding_box() {
+        Some(bb) => {
+            let width = bb.width() as usize;
+            let height = bb.height() as usize;
+            let mut coverage = vec![0u8; width * height];
+            glyph.draw(|gx, gy, v| {
+                coverage[gy as usize * width + gx as usize] = (v * 255.0).round() as u8;
             });
+            CachedGlyph { width, height, coverage, offset_x: bb.min.x, offset_y: bb.min.y, advance, id, last_used: 0 }
+        }
+        // whitespace and other empty glyphs only advance the pen
+        None => CachedGlyph { width: 0, height: 0, coverage: Vec::new(), offset_x: 0, offset_y: 0, advance, id, last_used: 0 },
+    }
+}
+
+fn draw_text_rusttype(fb: &Framebuffer, cache: &mut GlyphCache, text: &str, x: f32, y: f32, scale: f32, color: u32) {
+    // baseline point: glyphs are positioned relative to baseline.
+    let font = cache.font;
+    let v_metrics = font.v_metrics(Scale::uniform(scale));
+    let baseline = (y + v_metrics.ascent).round() as i32;
+    let mut pen_x = x;
+    let mut prev: Option<GlyphId> = None;
+
+    for ch in text.chars() {
+        let glyph = cache.get(ch, scale);
+        // pair kerning ("AV", "To") against the previous glyph; none before the first
+        if let Some(prev) = prev {
+            pen_x += font.pair_kerning(Scale::uniform(scale), prev, glyph.id);
         }
+        prev = Some(glyph.id);
+        let origin_x = pen_x.round() as i32 + glyph.offset_x;
+        let origin_y = baseline + glyph.offset_y;
+
+        for gy in 0..glyph.height {
+            for gx in 0..glyph.width {
+                let alpha = glyph.coverage[gy * glyph.width + gx] as u32;
+                if alpha == 0 { continue; }
+                let px = origin_x + gx as i32;
+                let py = origin_y + gy as i32;
+                if px >= 0 && py >= 0 {
+                    // create src_color with alpha = coverage; blend_pixel clips to the framebuffer
+                    let src_color = (alpha << 24) | (color & 0x00FF_FFFF);
+                    unsafe { fb.blend_pixel(px as usize, py as usize, src_color) };
+                }
+            }
+        }
+
+        pen_x += glyph.advance;
+    }
+}
+
+// width of a single line of text in pixels: glyph advances plus pair kerning,
+// laid out the same way draw_text_rusttype does
+fn measure_text(cache: &mut GlyphCache, text: &str, scale: f32) -> f32 {
+    let font = cache.font;
+    let mut width = 0.0;
+    let mut prev: Option<GlyphId> = None;
+    for ch in text.chars() {
+        let glyph = cache.get(ch, scale);
+        if let Some(prev) = prev {
+            width += font.pair_kerning(Scale::uniform(scale), prev, glyph.id);
+        }
+        prev = Some(glyph.id);
+        width += glyph.advance;
+    }
+    width
+}
+
+// draw text wrapped at spaces so no line exceeds max_width; '\n' forces a new line.
+// a single word wider than max_width gets its own line and overflows.
+// returns the y just below the last line so callers can stack paragraphs.
+#[allow(clippy::too_many_arguments)]
+fn draw_text_wrapped(fb: &Framebuffer, cache: &mut GlyphCache, text: &str, x: f32, y: f32, max_width: f32, scale: f32, color: u32) -> f32 {
+    let v_metrics = cache.font.v_metrics(Scale::uniform(scale));
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+    let space_width = cache.get(' ', scale).advance;
+
+    let mut line_y = y;
+    let mut line = String::new();
+    for paragraph in text.split('\n') {
+        let mut line_width = 0.0;
+        for word in paragraph.split_whitespace() {
+            let word_width = measure_text(cache, word, scale);
+            if !line.is_empty() && line_width + space_width + word_width > max_width {
+                draw_text_rusttype(fb, cache, &line, x, line_y, scale, color);
+                line_y += line_height;
+                line.clear();
+                line_width = 0.0;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+                line_width += space_width;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+
+        // flush the paragraph; empty paragraphs still take up a line
+        draw_text_rusttype(fb, cache, &line, x, line_y, scale, color);
+        line_y += line_height;
+        line.clear();
+    }
+
+    line_y
+}
+
+// map a key to the character it types on a US layout, honoring shift
+fn key_to_char(key: Key, shift: bool) -> Option<char> {
+    let letter = |c: char| Some(if shift { c.to_ascii_uppercase() } else { c });
+    let pick = |plain: char, shifted: char| Some(if shift { shifted } else { plain });
+    match key {
+        Key::A => letter('a'), Key::B => letter('b'), Key::C => letter('c'), Key::D => letter('d'),
+        Key::E => letter('e'), Key::F => letter('f'), Key::G => letter('g'), Key::H => letter('h'),
+        Key::I => letter('i'), Key::J => letter('j'), Key::K => letter('k'), Key::L => letter('l'),
+        Key::M => letter('m'), Key::N => letter('n'), Key::O => letter('o'), Key::P => letter('p'),
+        Key::Q => letter('q'), Key::R => letter('r'), Key::S => letter('s'), Key::T => letter('t'),
+        Key::U => letter('u'), Key::V => letter('v'), Key::W => letter('w'), Key::X => letter('x'),
+        Key::Y => letter('y'), Key::Z => letter('z'),
+        Key::Key1 => pick('1', '!'), Key::Key2 => pick('2', '@'), Key::Key3 => pick('3', '#'),
+        Key::Key4 => pick('4', '$'), Key::Key5 => pick('5', '%'), Key::Key6 => pick('6', '^'),
+        Key::Key7 => pick('7', '&'), Key::Key8 => pick('8', '*'), Key::Key9 => pick('9', '('),
+        Key::Key0 => pick('0', ')'),
+        Key::Minus => pick('-', '_'), Key::Equal => pick('=', '+'),
+        Key::LeftBracket => pick('[', '{'), Key::RightBracket => pick(']', '}'),
+        Key::Backslash => pick('\\', '|'), Key::Semicolon => pick(';', ':'),
+        Key::Apostrophe => pick('\'', '"'), Key::Backquote => pick('`', '~'),
+        Key::Comma => pick(',', '<'), Key::Period => pick('.', '>'), Key::Slash => pick('/', '?'),
+        Key::Space => Some(' '),
+        Key::Enter => Some('\n'),
+        _ => None,
     }
 }
 
@@ -69,18 +255,46 @@ fn main() {
     let font_data = include_bytes!("../fonts/DejaVuSans.ttf") as &[u8]; // put a ttf next to src
     let font = Font::try_from_bytes(font_data).expect("Error constructing Font");
 
-    let mut buffer: Vec<u32> = vec![0xFF000000; WIDTH * HEIGHT]; // opaque black
-    let mut window = Window::new("Text (rusttype) - ESC to exit", WIDTH, HEIGHT, WindowOptions::default()).unwrap();
+    let mut canvas = Canvas::new(INITIAL_WIDTH, INITIAL_HEIGHT); // opaque black
+    let options = WindowOptions { resize: true, ..WindowOptions::default() };
+    let mut window = Window::new("Text (rusttype) - ESC to exit", INITIAL_WIDTH, INITIAL_HEIGHT, options).unwrap();
     window.set_target_fps(60);
+    let mut glyph_cache = GlyphCache::new(&font, GLYPH_CACHE_CAPACITY);
 
-    let mut t = 0u32;
+    let mut input = String::new();
+    let mut clock = FrameClock::new();
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        // simple clear
-        for p in buffer.iter_mut() { *p = 0xFF000000; }
+        clock.tick();
+
+        // follow the window size; a minimized window reports 0x0, keep the old buffer then
+        let (width, height) = window.get_size();
+        if width > 0 && height > 0 {
+            canvas.resize(width, height);
+        }
+
+        // keyboard input (with key repeat so holding a key keeps typing)
+        let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            if key == Key::Backspace {
+                input.pop();
+            } else if let Some(ch) = key_to_char(key, shift) {
+                input.push(ch);
+            }
+        }
+
+        // draw through the shared Framebuffer; canvas.buffer is not touched again until update
+        let fb = Framebuffer::from_xrgb_buffer(&mut canvas.buffer, canvas.width, canvas.height);
+        unsafe { fb.fill(0xFF000000) };
+
+        draw_text_rusttype(&fb, &mut glyph_cache, "This is Rust Program ! Using Rusttype for Text Rendering", 20.0, 50.0, 32.0, 0x00FF_FFFF); // cyan-ish (RRGGBB)
+        draw_text_rusttype(&fb, &mut glyph_cache, &format!("Frame: {}  FPS: {:.1}", clock.frames, clock.fps), 20.0, 100.0, 20.0, 0xFF00_FF00); // green
+        draw_text_wrapped(&fb, &mut glyph_cache, "Long text is wrapped at word boundaries so it stays inside the given width instead of running off the right edge of the window.\nA newline always starts a new line.", 20.0, 150.0, 600.0, 24.0, 0xFFFF_FFFF);
+
+        // typed text with a blinking cursor, wrapped to stay inside the window
+        let cursor = if ((clock.elapsed / CURSOR_BLINK_SECS) as u64).is_multiple_of(2) { "_" } else { "" };
+        let typed = format!("> {}{}", input, cursor);
+        draw_text_wrapped(&fb, &mut glyph_cache, &typed, 20.0, 300.0, canvas.width.saturating_sub(40) as f32, 24.0, 0xFF00_FFFF);
 
-        draw_text_rusttype(&mut buffer, &font, "This is Rust Program ! Using Rusttype for Text Rendering", 20.0, 50.0, 32.0, 0x00FF_FFFF); // cyan-ish (RRGGBB)
-        draw_text_rusttype(&mut buffer, &font, &format!("Frame: {}", t), 20.0, 100.0, 20.0, 0xFF00_FF00); // green
-        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
-        t += 1;
+        window.update_with_buffer(&canvas.buffer, canvas.width, canvas.height).unwrap();
     }
 }
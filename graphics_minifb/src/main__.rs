@@ -1,7 +1,13 @@
 use minifb::{Key, Window, WindowOptions};
+use std::time::Instant;
 
-const WIDTH: usize = 1366;
-const HEIGHT: usize = 786;
+// initial window size; the window is resizable so the real size is read every frame
+const INITIAL_WIDTH: usize = 1366;
+const INITIAL_HEIGHT: usize = 768;
+// weight of the newest frame in the smoothed FPS readout
+const FPS_SMOOTHING: f32 = 0.1;
+// how fast the moving number counts, per second
+const COUNTER_RATE: f32 = 10.0;
 
 // A tiny 8x8 font for characters '0'..'9' and space — expand as needed.
 const FONT8X8_DIGITS: [[u8; 8]; 11] = [
@@ -29,15 +35,45 @@ const FONT8X8_DIGITS: [[u8; 8]; 11] = [
     [0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00],
 ];
 
-fn put_pixel(buffer: &mut [u32], x: isize, y: isize, color: u32) {
+// wall-clock frame timing, so animation speed doesn't depend on the frame rate
+struct FrameClock {
+    last: Instant,
+    elapsed: f32, // seconds since the first frame
+    fps: f32,     // smoothed frames per second
+    frames: u64,
+}
+
+impl FrameClock {
+    fn new() -> Self {
+        FrameClock { last: Instant::now(), elapsed: 0.0, fps: 0.0, frames: 0 }
+    }
+
+    // start a new frame; returns the seconds since the previous one
+    fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+        self.elapsed += dt;
+        self.frames += 1;
+        if dt > 0.0 {
+            // exponential moving average; the first real delta seeds it
+            let instant_fps = 1.0 / dt;
+            self.fps = if self.fps == 0.0 { instant_fps } else { self.fps + (instant_fps - self.fps) * FPS_SMOOTHING };
+        }
+        dt
+    }
+}
+
+// buffer rows are `stride` pixels wide; the height follows from the buffer length
+fn put_pixel(buffer: &mut [u32], stride: usize, x: isize, y: isize, color: u32) {
     if x < 0 || y < 0 { return; }
     let x = x as usize;
     let y = y as usize;
-    if x >= WIDTH || y >= HEIGHT { return; }
-    buffer[y * WIDTH + x] = color;
+    if x >= stride || y >= buffer.len() / stride { return; }
+    buffer[y * stride + x] = color;
 }
 
-fn draw_char_8x8(buffer: &mut [u32], ch: char, x: isize, y: isize, color: u32) {
+fn draw_char_8x8(buffer: &mut [u32], stride: usize, ch: char, x: isize, y: isize, color: u32) {
     let idx = match ch {
         '0'..='9' => (ch as u8 - b'0') as usize,
         ' ' => 10,
@@ -48,38 +84,53 @@ fn draw_char_8x8(buffer: &mut [u32], ch: char, x: isize, y: isize, color: u32) {
         let bits = glyph[row];
         for col in 0..8 {
             if (bits >> (7 - col)) & 1 == 1 {
-                put_pixel(buffer, x + col as isize, y + row as isize, color);
+                put_pixel(buffer, stride, x + col as isize, y + row as isize, color);
             }
         }
     }
 }
 
-fn draw_text_8x8(buffer: &mut [u32], text: &str, x: isize, y: isize, color: u32) {
+fn draw_text_8x8(buffer: &mut [u32], stride: usize, text: &str, x: isize, y: isize, color: u32) {
     let mut ox = x;
     for ch in text.chars() {
-        draw_char_8x8(buffer, ch, ox, y, color);
+        draw_char_8x8(buffer, stride, ch, ox, y, color);
         ox += 8; // move by 8 pixels per char
     }
 }
 
 fn main() {
-    let mut buffer: Vec<u32> = vec![0; WIDTH * HEIGHT];
-    let mut window = Window::new("Text - ESC to exit", WIDTH, HEIGHT, WindowOptions::default()).unwrap();
+    let (mut width, mut height) = (INITIAL_WIDTH, INITIAL_HEIGHT);
+    let mut buffer: Vec<u32> = vec![0; width * height];
+    let options = WindowOptions { resize: true, ..WindowOptions::default() };
+    let mut window = Window::new("Text - ESC to exit", width, height, options).unwrap();
     window.set_target_fps(60);
 
-    let mut t = 0u32;
+    let mut clock = FrameClock::new();
     while window.is_open() && !window.is_key_down(Key::Escape) {
+        clock.tick();
+
+        // reallocate when the window is resized (a minimized window reports 0x0)
+        let (new_width, new_height) = window.get_size();
+        if new_width > 0 && new_height > 0 && (new_width, new_height) != (width, height) {
+            width = new_width;
+            height = new_height;
+            buffer = vec![0; width * height];
+        }
+
         // clear black
         for p in buffer.iter_mut() { *p = 0xFF000000; }
 
         // draw some text
-        draw_text_8x8(&mut buffer, "0123456789", 20, 20, 0xFFFFFFFF);
-        draw_text_8x8(&mut buffer, "score:", 20, 40, 0xFFFFFF00);
+        draw_text_8x8(&mut buffer, width, "0123456789", 20, 20, 0xFFFFFFFF);
+        draw_text_8x8(&mut buffer, width, "score:", 20, 40, 0xFFFFFF00);
+
+        // moving number, counting at COUNTER_RATE whatever the frame rate
+        let counter = (clock.elapsed * COUNTER_RATE) as u64 % 100;
+        draw_text_8x8(&mut buffer, width, &format!("{}", counter), 100, 40, 0xFF00FF00);
 
-        // moving number
-        draw_text_8x8(&mut buffer, &format!("{}", t % 100), 100, 40, 0xFF00FF00);
+        // frame count and smoothed FPS (the font only has digits)
+        draw_text_8x8(&mut buffer, width, &format!("{} {}", clock.frames, clock.fps.round() as u32), 20, 60, 0xFFFFFFFF);
 
-        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
-        t += 1;
+        window.update_with_buffer(&buffer, width, height).unwrap();
     }
 }
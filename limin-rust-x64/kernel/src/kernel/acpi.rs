@@ -0,0 +1,98 @@
+// src/kernel/acpi.rs - finding ACPI tables through the RSDP Limine gives us
+//
+// RSDP -> XSDT (64-bit pointers, ACPI 2.0+) or RSDT (32-bit pointers) -> tables.
+// All of them live in ACPI reclaimable/NVS memory, which the HHDM covers.
+use core::ptr::read_unaligned;
+use crate::kernel::serial::SERIAL_PORT;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// Size of the ACPI 1.0 RSDP, which the checksum covers
+const RSDP_V1_SIZE: usize = 20;
+
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    checksum: u8,
+    oem_id: [u8; 6],
+    revision: u8,
+    rsdt_address: u32,
+    // ACPI 2.0+ (revision >= 2)
+    length: u32,
+    xsdt_address: u64,
+    extended_checksum: u8,
+    reserved: [u8; 3],
+}
+
+/// Common header of every system description table
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
+
+/// Physical address as a pointer through the HHDM
+unsafe fn phys_to_ptr(phys: u64) -> Option<*const u8> {
+    let hhdm = crate::HHDM_REQUEST.get_response()?.offset();
+    Some((phys + hhdm) as *const u8)
+}
+
+/// The table at `phys`, if its checksum over `length` bytes is good
+unsafe fn sdt_at(phys: u64) -> Option<*const SdtHeader> {
+    let ptr = phys_to_ptr(phys)?;
+    let header = read_unaligned(ptr as *const SdtHeader);
+    let length = header.length as usize;
+    if length < size_of::<SdtHeader>() || !checksum_ok(core::slice::from_raw_parts(ptr, length)) {
+        return None;
+    }
+    Some(ptr as *const SdtHeader)
+}
+
+/// Find the table with `signature` (e.g. b"HPET"), preferring the XSDT.
+/// Returns a pointer to its header; the table body follows it.
+pub unsafe fn find_table(signature: &[u8; 4]) -> Option<*const SdtHeader> {
+    let Some(response) = crate::RSDP_REQUEST.get_response() else {
+        SERIAL_PORT.write_str("ACPI: no RSDP from bootloader\n");
+        return None;
+    };
+    // Base revision 3 hands over the physical address
+    let rsdp_ptr = phys_to_ptr(response.address() as u64)?;
+    let rsdp = read_unaligned(rsdp_ptr as *const Rsdp);
+    if rsdp.signature != *RSDP_SIGNATURE || !checksum_ok(core::slice::from_raw_parts(rsdp_ptr, RSDP_V1_SIZE)) {
+        SERIAL_PORT.write_str("ACPI: bad RSDP\n");
+        return None;
+    }
+
+    // XSDT entries are 8 bytes wide, RSDT entries 4
+    let (root, entry_size) = if rsdp.revision >= 2 && rsdp.xsdt_address != 0 {
+        (sdt_at(rsdp.xsdt_address)?, 8)
+    } else {
+        (sdt_at(rsdp.rsdt_address as u64)?, 4)
+    };
+
+    let count = ((*root).length as usize - size_of::<SdtHeader>()) / entry_size;
+    let entries = (root as *const u8).add(size_of::<SdtHeader>());
+    for i in 0..count {
+        let phys = if entry_size == 8 {
+            read_unaligned(entries.add(i * 8) as *const u64)
+        } else {
+            read_unaligned(entries.add(i * 4) as *const u32) as u64
+        };
+        if let Some(table) = sdt_at(phys) {
+            if (*table).signature == *signature {
+                return Some(table);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,86 @@
+// src/kernel/array_string.rs - fixed-capacity string for formatting without a heap
+//
+// For building a message with write!() where there is no allocator, notably in
+// interrupt handlers:
+//     let mut s = ArrayString::<128>::new();
+//     let _ = write!(s, "tick {}", n);
+//     SERIAL_PORT.write_str(&s);
+// Output past the capacity is dropped, never panics.
+use core::fmt;
+use core::ops::Deref;
+
+/// A string of at most N bytes stored inline (on the stack, or in a static)
+pub struct ArrayString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> ArrayString<N> {
+    pub const fn new() -> Self {
+        ArrayString { buf: [0; N], len: 0, truncated: false }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole UTF-8 characters are ever appended
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// True if some output was dropped because the buffer was full
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+
+    /// Append as much of `s` as fits, cutting at a character boundary.
+    /// Returns false if anything was left out.
+    pub fn push_str(&mut self, s: &str) -> bool {
+        let room = N - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        if take < s.len() {
+            self.truncated = true;
+        }
+        !self.truncated
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for ArrayString<N> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for ArrayString<N> {
+    /// Never fails: a full buffer truncates instead, so a long message still
+    /// gets its beginning out (check is_truncated() if that matters)
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Display for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
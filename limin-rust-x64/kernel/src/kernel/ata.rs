@@ -0,0 +1,134 @@
+// src/kernel/ata.rs - ATA PIO disk access (primary bus, master drive, 28-bit LBA)
+use crate::kernel::io::{inb, inw, outb};
+
+// Primary bus I/O ports
+const ATA_DATA: u16 = 0x1F0;
+const ATA_ERROR: u16 = 0x1F1;
+const ATA_SECTOR_COUNT: u16 = 0x1F2;
+const ATA_LBA_LOW: u16 = 0x1F3;
+const ATA_LBA_MID: u16 = 0x1F4;
+const ATA_LBA_HIGH: u16 = 0x1F5;
+const ATA_DRIVE_HEAD: u16 = 0x1F6;
+const ATA_STATUS: u16 = 0x1F7;   // read
+const ATA_COMMAND: u16 = 0x1F7;  // write
+const ATA_ALT_STATUS: u16 = 0x3F6; // read
+const ATA_DEVICE_CONTROL: u16 = 0x3F6; // write
+
+// Status register bits
+const STATUS_ERR: u8 = 1 << 0;
+const STATUS_DRQ: u8 = 1 << 3;
+const STATUS_DF: u8 = 1 << 5;
+const STATUS_BSY: u8 = 1 << 7;
+
+// Commands
+const CMD_READ_SECTORS: u8 = 0x20;
+
+/// Master drive, LBA addressing; LBA bits 24-27 go in the low nibble
+const DRIVE_MASTER_LBA: u8 = 0xE0;
+/// Device control: nIEN, we poll instead of taking IRQ14
+const CONTROL_NO_INTERRUPTS: u8 = 0x02;
+
+pub const SECTOR_SIZE: usize = 512;
+
+/// Highest sector reachable with 28-bit LBA, plus one
+const LBA28_LIMIT: u64 = 1 << 28;
+
+/// Status polls before giving up
+const ATA_TIMEOUT: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtaError {
+    /// count was 0 or the buffer is smaller than count sectors
+    InvalidArgument,
+    /// lba + count does not fit in 28 bits
+    LbaOutOfRange,
+    /// Status reads 0xFF: nothing attached to the bus
+    NoDevice,
+    /// BSY/DRQ never reached the expected state
+    Timeout,
+    /// ERR set; contains the error register
+    DeviceError(u8),
+    /// Drive fault (DF) set
+    DriveFault,
+}
+
+/// ~400ns delay: each alternate status read takes ~100ns
+unsafe fn delay_400ns() {
+    for _ in 0..4 {
+        inb(ATA_ALT_STATUS);
+    }
+}
+
+/// Wait for BSY to clear
+unsafe fn wait_not_busy() -> Result<u8, AtaError> {
+    for _ in 0..ATA_TIMEOUT {
+        let status = inb(ATA_STATUS);
+        if status == 0xFF {
+            return Err(AtaError::NoDevice);
+        }
+        if status & STATUS_BSY == 0 {
+            return Ok(status);
+        }
+    }
+    Err(AtaError::Timeout)
+}
+
+/// Wait until the drive has a sector ready (BSY clear, DRQ set), reporting ERR/DF
+unsafe fn wait_data_ready() -> Result<(), AtaError> {
+    delay_400ns();
+    for _ in 0..ATA_TIMEOUT {
+        let status = inb(ATA_STATUS);
+        if status == 0xFF {
+            return Err(AtaError::NoDevice);
+        }
+        if status & STATUS_BSY != 0 {
+            continue;
+        }
+        if status & STATUS_ERR != 0 {
+            return Err(AtaError::DeviceError(inb(ATA_ERROR)));
+        }
+        if status & STATUS_DF != 0 {
+            return Err(AtaError::DriveFault);
+        }
+        if status & STATUS_DRQ != 0 {
+            return Ok(());
+        }
+    }
+    Err(AtaError::Timeout)
+}
+
+/// Read `count` sectors starting at `lba` into `buf` (at least count * 512 bytes)
+pub unsafe fn read_sectors(lba: u64, count: u8, buf: &mut [u8]) -> Result<(), AtaError> {
+    if count == 0 || buf.len() < count as usize * SECTOR_SIZE {
+        return Err(AtaError::InvalidArgument);
+    }
+    if lba.checked_add(count as u64).is_none_or(|end| end > LBA28_LIMIT) {
+        return Err(AtaError::LbaOutOfRange);
+    }
+
+    outb(ATA_DEVICE_CONTROL, CONTROL_NO_INTERRUPTS);
+    wait_not_busy()?;
+
+    // Select drive + LBA bits 24-27, then let the drive settle
+    outb(ATA_DRIVE_HEAD, DRIVE_MASTER_LBA | ((lba >> 24) & 0x0F) as u8);
+    delay_400ns();
+    wait_not_busy()?;
+
+    outb(ATA_SECTOR_COUNT, count);
+    outb(ATA_LBA_LOW, lba as u8);
+    outb(ATA_LBA_MID, (lba >> 8) as u8);
+    outb(ATA_LBA_HIGH, (lba >> 16) as u8);
+    outb(ATA_COMMAND, CMD_READ_SECTORS);
+
+    for sector in buf.chunks_exact_mut(SECTOR_SIZE).take(count as usize) {
+        wait_data_ready()?;
+        // 256 little-endian words per sector
+        for word in sector.chunks_exact_mut(2) {
+            let value = inw(ATA_DATA);
+            word[0] = value as u8;
+            word[1] = (value >> 8) as u8;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// src/kernel/console.rs - framebuffer text console and console_print!/console_println!
+use core::fmt::{self, Write};
+use graphics::{Console, Framebuffer};
+use crate::kernel::sync::SpinLock;
+
+/// Widest grid we keep cells for (1920 px / 8)
+const CONSOLE_COLS: usize = 240;
+/// A 1080p screen is 67 rows; keep about four screens, the rest is scrollback
+const CONSOLE_LINES: usize = 4 * 68;
+
+/// Colors of the panic screen: white on dark red
+const PANIC_FG: u32 = 0xFF_FF_FF_FF;
+const PANIC_BG: u32 = 0xFF_60_00_00;
+
+// Shared by everything that prints, so it is locked with interrupts off
+static CONSOLE: SpinLock<Console<CONSOLE_COLS, CONSOLE_LINES>> = SpinLock::new(Console::new());
+
+/// Start showing console output on `fb`, drawn over whatever is already there.
+/// Until this runs, console_print! output is dropped.
+pub unsafe fn init(fb: Framebuffer) {
+    CONSOLE.lock_irqsave().attach(fb);
+}
+
+/// Blank the screen and move the cursor to the top left
+pub unsafe fn clear() {
+    CONSOLE.lock_irqsave().clear();
+}
+
+/// Scroll only rows top_row..=bottom_row, keeping the rest of the screen (e.g. a
+/// banner) in place. Returns false if the rows don't fit on the screen.
+pub unsafe fn set_scroll_region(top_row: usize, bottom_row: usize) -> bool {
+    CONSOLE.lock_irqsave().set_scroll_region(top_row, bottom_row)
+}
+
+/// Scroll the view `lines_back` lines into the scrollback (0 = live output)
+pub unsafe fn scroll_view(lines_back: usize) {
+    CONSOLE.lock_irqsave().scroll_view(lines_back);
+}
+
+/// Clear the screen to dark red and let `report` write the panic report on it.
+/// Returns false without drawing if no framebuffer was registered yet, or if the
+/// console lock is held: then the panic came from inside the console itself and
+/// only serial output is safe.
+pub unsafe fn panic_screen(report: impl FnOnce(&mut dyn fmt::Write)) -> bool {
+    let Some(mut console) = CONSOLE.try_lock() else {
+        return false;
+    };
+    if !console.is_attached() {
+        return false;
+    }
+    console.set_colors(PANIC_FG, PANIC_BG);
+    console.clear();
+    report(&mut *console);
+    true
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    let _ = CONSOLE.lock_irqsave().write_fmt(args);
+}
+
+#[macro_export]
+macro_rules! console_print {
+    ($($arg:tt)*) => {
+        $crate::kernel::console::_print(format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! console_println {
+    () => {
+        $crate::console_print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::kernel::console::_print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
@@ -0,0 +1,177 @@
+// src/kernel/cpuid.rs - CPU feature detection with the CPUID instruction
+use core::arch::asm;
+use crate::kernel::serial::SERIAL_PORT;
+
+/// First extended leaf; its EAX reports the highest extended leaf
+const EXTENDED_BASE: u32 = 0x8000_0000;
+/// Extended leaves 0x8000_0002..=0x8000_0004 each return 16 bytes of the brand string
+const BRAND_FIRST_LEAF: u32 = 0x8000_0002;
+const BRAND_LAST_LEAF: u32 = 0x8000_0004;
+
+/// Features other subsystems check before relying on them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuFeature {
+    /// Time-stamp counter (RDTSC)
+    Tsc,
+    /// TSC ticks at a constant rate regardless of P/C-states
+    InvariantTsc,
+    /// Physical address extension
+    Pae,
+    /// On-chip local APIC
+    Apic,
+    /// Page attribute table (memory types selected per page)
+    Pat,
+    /// Local APIC timer supports TSC-deadline mode
+    TscDeadline,
+    Sse,
+    Sse2,
+    /// 1 GiB pages in the page tables
+    Pages1G,
+}
+
+#[derive(Clone, Copy)]
+enum Reg {
+    Ecx,
+    Edx,
+}
+
+impl CpuFeature {
+    /// (leaf, register, bit) reporting this feature
+    fn location(self) -> (u32, Reg, u32) {
+        match self {
+            CpuFeature::Tsc => (1, Reg::Edx, 4),
+            CpuFeature::Pae => (1, Reg::Edx, 6),
+            CpuFeature::Apic => (1, Reg::Edx, 9),
+            CpuFeature::Pat => (1, Reg::Edx, 16),
+            CpuFeature::Sse => (1, Reg::Edx, 25),
+            CpuFeature::Sse2 => (1, Reg::Edx, 26),
+            CpuFeature::TscDeadline => (1, Reg::Ecx, 24),
+            CpuFeature::Pages1G => (0x8000_0001, Reg::Edx, 26),
+            CpuFeature::InvariantTsc => (0x8000_0007, Reg::Edx, 8),
+        }
+    }
+}
+
+/// Raw CPUID result
+#[derive(Debug, Clone, Copy)]
+pub struct CpuidResult {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+/// Execute CPUID. Leaves past max_leaf()/max_extended_leaf() return
+/// unrelated data on some CPUs, so prefer has_feature().
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    let eax: u32;
+    let ebx: u64;
+    let ecx: u32;
+    let edx: u32;
+    // LLVM reserves rbx, so shuffle it through a scratch register
+    unsafe {
+        asm!(
+            "mov {tmp}, rbx",
+            "cpuid",
+            "xchg {tmp}, rbx",
+            tmp = out(reg) ebx,
+            inout("eax") leaf => eax,
+            inout("ecx") subleaf => ecx,
+            out("edx") edx,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    CpuidResult { eax, ebx: ebx as u32, ecx, edx }
+}
+
+/// Highest basic leaf
+pub fn max_leaf() -> u32 {
+    cpuid(0, 0).eax
+}
+
+/// Highest extended (0x8000_0000+) leaf, or 0 if there are none
+pub fn max_extended_leaf() -> u32 {
+    let max = cpuid(EXTENDED_BASE, 0).eax;
+    if max >= EXTENDED_BASE { max } else { 0 }
+}
+
+/// Vendor string, e.g. "GenuineIntel" or "AuthenticAMD"
+pub fn vendor() -> [u8; 12] {
+    let r = cpuid(0, 0);
+    let mut vendor = [0u8; 12];
+    vendor[0..4].copy_from_slice(&r.ebx.to_le_bytes());
+    vendor[4..8].copy_from_slice(&r.edx.to_le_bytes());
+    vendor[8..12].copy_from_slice(&r.ecx.to_le_bytes());
+    vendor
+}
+
+/// Processor brand string, e.g. "Intel(R) Core(TM) i7-8700 CPU @ 3.20GHz",
+/// NUL-padded. CPUs without the brand leaves get the vendor string instead.
+pub fn brand_string() -> [u8; 48] {
+    let mut brand = [0u8; 48];
+    if max_extended_leaf() < BRAND_LAST_LEAF {
+        brand[..12].copy_from_slice(&vendor());
+        return brand;
+    }
+    for (i, leaf) in (BRAND_FIRST_LEAF..=BRAND_LAST_LEAF).enumerate() {
+        let r = cpuid(leaf, 0);
+        for (j, reg) in [r.eax, r.ebx, r.ecx, r.edx].iter().enumerate() {
+            let at = i * 16 + j * 4;
+            brand[at..at + 4].copy_from_slice(&reg.to_le_bytes());
+        }
+    }
+    brand
+}
+
+/// `bytes` up to the first NUL, without the padding spaces some CPUs put
+/// around the brand string
+fn trim_cpu_string(bytes: &[u8]) -> &str {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..end]).map(str::trim).unwrap_or("<unknown>")
+}
+
+/// Whether the CPU reports `feature`. False when its leaf is not implemented.
+pub fn has_feature(feature: CpuFeature) -> bool {
+    let (leaf, reg, bit) = feature.location();
+    let max = if leaf >= EXTENDED_BASE { max_extended_leaf() } else { max_leaf() };
+    if leaf > max {
+        return false;
+    }
+    let r = cpuid(leaf, 0);
+    let value = match reg {
+        Reg::Ecx => r.ecx,
+        Reg::Edx => r.edx,
+    };
+    value & (1 << bit) != 0
+}
+
+/// Log the vendor and the features we care about
+pub unsafe fn log_features() {
+    const FEATURES: &[(CpuFeature, &str)] = &[
+        (CpuFeature::Tsc, "tsc"),
+        (CpuFeature::InvariantTsc, "invariant-tsc"),
+        (CpuFeature::Pae, "pae"),
+        (CpuFeature::Apic, "apic"),
+        (CpuFeature::Pat, "pat"),
+        (CpuFeature::TscDeadline, "tsc-deadline"),
+        (CpuFeature::Sse, "sse"),
+        (CpuFeature::Sse2, "sse2"),
+        (CpuFeature::Pages1G, "1g-pages"),
+    ];
+
+    let vendor = vendor();
+    let brand = brand_string();
+    SERIAL_PORT.write_fmt(format_args!("CPU: {}\n", trim_cpu_string(&brand)));
+    SERIAL_PORT.write_fmt(format_args!(
+        "CPU vendor: {}, max leaf {:#x}, max extended leaf {:#x}\n",
+        trim_cpu_string(&vendor), max_leaf(), max_extended_leaf()
+    ));
+    SERIAL_PORT.write_str("CPU features:");
+    for &(feature, name) in FEATURES {
+        if has_feature(feature) {
+            SERIAL_PORT.write_str(" ");
+            SERIAL_PORT.write_str(name);
+        }
+    }
+    SERIAL_PORT.write_str("\n");
+}
@@ -0,0 +1,124 @@
+// src/kernel/fault_tests.rs - deliberate CPU exceptions as boot-time regression tests
+//
+// Built only with `--features test-faults` (make KFEATURES=test-faults run). Each
+// test arms an exception hook, raises the fault, and checks the hook saw it.
+// The double fault test goes last: the faulting context has no usable stack, so
+// its hook ends the run through the isa-debug-exit device. If the #DF path is
+// broken the CPU triple faults and QEMU resets instead, so no success code.
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::kernel::interrupts::{self, ExceptionAction, InterruptFrame};
+use crate::kernel::qemu::{qemu_exit, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::paging;
+
+const VECTOR_INVALID_OPCODE: u8 = 6;
+const VECTOR_DOUBLE_FAULT: u8 = 8;
+const VECTOR_PAGE_FAULT: u8 = 14;
+
+/// Canonical lower-half address the kernel never maps (Limine only maps the
+/// higher half), used as the bad pointer and the overflowed stack
+const UNMAPPED_ADDR: u64 = 0x0000_4000_0000_0000;
+
+/// Set while a test expects its fault; a hook that fires unarmed halts as usual
+static ARMED: AtomicBool = AtomicBool::new(false);
+/// Fault address (CR2) or RIP recorded by the last hook
+static RECORDED: AtomicU64 = AtomicU64::new(0);
+
+fn invalid_opcode_hook(frame: &mut InterruptFrame) -> ExceptionAction {
+    if !ARMED.swap(false, Ordering::Relaxed) {
+        return ExceptionAction::Halt;
+    }
+    RECORDED.store(frame.rip, Ordering::Relaxed);
+    // skip the ud2 (0F 0B)
+    frame.rip += 2;
+    ExceptionAction::Resume
+}
+
+fn page_fault_hook(frame: &mut InterruptFrame) -> ExceptionAction {
+    if !ARMED.swap(false, Ordering::Relaxed) {
+        return ExceptionAction::Halt;
+    }
+    let cr2: u64;
+    unsafe { asm!("mov {}, cr2", out(reg) cr2, options(nomem, nostack, preserves_flags)) };
+    RECORDED.store(cr2, Ordering::Relaxed);
+    // skip the faulting `mov al, [rdi]` (8A 07)
+    frame.rip += 2;
+    ExceptionAction::Resume
+}
+
+fn double_fault_hook(frame: &mut InterruptFrame) -> ExceptionAction {
+    if !ARMED.swap(false, Ordering::Relaxed) {
+        return ExceptionAction::Halt;
+    }
+    // Getting here at all means the CPU could deliver #DF: the interrupted
+    // stack is the dead one, so the IST switch worked. Nothing to return to.
+    unsafe {
+        SERIAL_PORT.write_fmt(format_args!(
+            "fault test #DF: handler ran, faulting rsp {:#x}, error code {:#x}\n",
+            frame.rsp, frame.err_code
+        ));
+    }
+    // #DF always pushes an error code of zero
+    let ok = frame.err_code == 0;
+    unsafe { report("#DF", ok) };
+    qemu_exit(if ok { EXIT_SUCCESS } else { EXIT_FAILURE });
+}
+
+unsafe fn report(name: &str, ok: bool) -> bool {
+    SERIAL_PORT.write_fmt(format_args!("fault test {}: {}\n", name, if ok { "ok" } else { "FAILED" }));
+    ok
+}
+
+/// ud2 must raise #6 and come back through the hook
+pub unsafe fn test_invalid_opcode() -> bool {
+    interrupts::set_exception_handler(VECTOR_INVALID_OPCODE, invalid_opcode_hook);
+    RECORDED.store(0, Ordering::Relaxed);
+    ARMED.store(true, Ordering::Relaxed);
+    asm!("ud2", options(nostack));
+    let disarmed = !ARMED.swap(false, Ordering::Relaxed);
+    report("#UD", disarmed && RECORDED.load(Ordering::Relaxed) != 0)
+}
+
+/// A read from an unmapped page must raise #14 with the address in CR2
+pub unsafe fn test_page_fault() -> bool {
+    if paging::translate(UNMAPPED_ADDR).is_some() {
+        SERIAL_PORT.write_fmt(format_args!("fault test #PF: {:#x} is mapped, skipped\n", UNMAPPED_ADDR));
+        return false;
+    }
+    interrupts::set_exception_handler(VECTOR_PAGE_FAULT, page_fault_hook);
+    RECORDED.store(0, Ordering::Relaxed);
+    ARMED.store(true, Ordering::Relaxed);
+    // rdi and al pinned so the encoding is the 2 bytes the hook skips
+    asm!("mov al, [rdi]", in("rdi") UNMAPPED_ADDR, out("al") _, options(nostack));
+    let disarmed = !ARMED.swap(false, Ordering::Relaxed);
+    report("#PF", disarmed && RECORDED.load(Ordering::Relaxed) == UNMAPPED_ADDR)
+}
+
+/// Overflow the stack into unmapped memory. The push faults (#PF), delivering
+/// the #PF needs that same stack and faults again, and the CPU escalates to #8,
+/// which only works because the #DF gate switches to IST1. Never returns.
+pub unsafe fn test_double_fault() -> ! {
+    interrupts::set_exception_handler(VECTOR_DOUBLE_FAULT, double_fault_hook);
+    ARMED.store(true, Ordering::Relaxed);
+    SERIAL_PORT.write_str("fault test #DF: overflowing the stack...\n");
+    asm!(
+        "cli",
+        "mov rsp, {stack}",
+        "push rax",
+        stack = in(reg) UNMAPPED_ADDR,
+        options(noreturn)
+    );
+}
+
+/// Run every fault test, then exit QEMU with EXIT_SUCCESS or EXIT_FAILURE
+pub unsafe fn run_all() -> ! {
+    SERIAL_PORT.write_str("\n=== FAULT TESTS ===\n");
+    let ud_ok = test_invalid_opcode();
+    let pf_ok = test_page_fault();
+    if !(ud_ok && pf_ok) {
+        SERIAL_PORT.write_str("fault tests: FAILED\n");
+        qemu_exit(EXIT_FAILURE);
+    }
+    test_double_fault();
+}
@@ -0,0 +1,73 @@
+// src/kernel/fpu.rs - opt-in x87/SSE state saving around interrupt handlers
+//
+// The kernel is built soft-float, so by default isr_common_stub only saves the
+// general-purpose registers. Once enable_interrupt_save() has run, the stub also
+// FXSAVEs the interrupted code's x87/SSE state into a 512-byte area on the
+// interrupt stack and FXRSTORs it on the way out, and the scheduler keeps one
+// area per task so a task switch carries the FPU state along with the registers.
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::kernel::cpuid::{self, CpuFeature};
+use crate::kernel::serial::SERIAL_PORT;
+
+// Control register bits
+const CR0_MP: u64 = 1 << 1;         // monitor coprocessor
+const CR0_EM: u64 = 1 << 2;         // x87 emulation (must be clear)
+const CR4_OSFXSR: u64 = 1 << 9;     // FXSAVE/FXRSTOR and SSE enabled
+const CR4_OSXMMEXCPT: u64 = 1 << 10; // SIMD exceptions delivered as #XM
+
+// Reset values loaded by FNINIT / expected by SSE code
+const DEFAULT_FCW: u16 = 0x037F;
+const DEFAULT_MXCSR: u32 = 0x1F80;
+
+/// Read by isr_common_stub on every interrupt; false keeps the hot path GPR-only
+#[unsafe(no_mangle)]
+static FPU_SAVE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// FXSAVE/FXRSTOR memory image
+#[derive(Clone, Copy)]
+#[repr(C, align(16))]
+pub struct FxsaveArea(pub [u8; 512]);
+
+impl FxsaveArea {
+    /// The state FNINIT leaves behind: x87 and SSE exceptions masked, round to nearest
+    pub const fn initial() -> Self {
+        let mut bytes = [0u8; 512];
+        let fcw = DEFAULT_FCW.to_le_bytes();
+        bytes[0] = fcw[0];
+        bytes[1] = fcw[1];
+        let mxcsr = DEFAULT_MXCSR.to_le_bytes();
+        bytes[24] = mxcsr[0];
+        bytes[25] = mxcsr[1];
+        bytes[26] = mxcsr[2];
+        bytes[27] = mxcsr[3];
+        FxsaveArea(bytes)
+    }
+}
+
+/// Turn on the FPU/SSE units and start saving their state across interrupts.
+/// Call once, before any code that uses floating point or SIMD registers.
+pub unsafe fn enable_interrupt_save() {
+    if !cpuid::has_feature(CpuFeature::Sse) {
+        SERIAL_PORT.write_str("FPU: no SSE/FXSAVE support, not saving FPU state\n");
+        return;
+    }
+
+    let mut cr0: u64;
+    let mut cr4: u64;
+    asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack, preserves_flags));
+    asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack, preserves_flags));
+    cr0 = (cr0 & !CR0_EM) | CR0_MP;
+    cr4 |= CR4_OSFXSR | CR4_OSXMMEXCPT;
+    asm!("mov cr0, {}", in(reg) cr0, options(nostack, preserves_flags));
+    asm!("mov cr4, {}", in(reg) cr4, options(nostack, preserves_flags));
+    asm!("fninit", options(nomem, nostack));
+
+    FPU_SAVE_ENABLED.store(true, Ordering::Release);
+    SERIAL_PORT.write_str("FPU: saving x87/SSE state across interrupts\n");
+}
+
+/// Whether isr_common_stub is saving FPU/SSE state
+pub fn interrupt_save_enabled() -> bool {
+    FPU_SAVE_ENABLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,158 @@
+// src/kernel/gdt.rs - our own GDT: kernel and user segments plus a TSS for IST stacks
+//
+// Layout (selectors include the RPL in the low two bits):
+//
+//   0x00  null
+//   0x08  kernel code (ring 0, 64-bit)
+//   0x10  kernel data (ring 0)
+//   0x1B  user code   (ring 3, 64-bit)
+//   0x23  user data   (ring 3)
+//   0x28  TSS         (16-byte system descriptor, two slots)
+//
+// Limine's GDT has no TSS, so without this the CPU has nowhere to take a known
+// good stack from. Interrupt Stack Table assignments (IDT entry ist field):
+//
+//   IST1  #DF  double fault      (kernel stack may have overflowed)
+//   IST2  #NMI non-maskable int  (can arrive anywhere, even inside another handler)
+//   IST3  #MC  machine check     (same)
+//
+// IST4-7 are free. Give every new IST user its own index: two vectors sharing
+// one would overwrite each other's frames if one interrupted the other.
+use core::arch::asm;
+use core::mem::size_of;
+use core::ptr::addr_of;
+use crate::kernel::serial::SERIAL_PORT;
+
+pub const KERNEL_CODE_SELECTOR: u16 = 0x08;
+pub const KERNEL_DATA_SELECTOR: u16 = 0x10;
+pub const USER_CODE_SELECTOR: u16 = 0x18 | 3;
+pub const USER_DATA_SELECTOR: u16 = 0x20 | 3;
+pub const TSS_SELECTOR: u16 = 0x28;
+
+pub const IST_DOUBLE_FAULT: u8 = 1;
+pub const IST_NMI: u8 = 2;
+pub const IST_MACHINE_CHECK: u8 = 3;
+
+/// Number of IST stacks we allocate (IST1..=IST_COUNT)
+const IST_COUNT: usize = 3;
+const IST_STACK_SIZE: usize = 16 * 1024;
+
+// Flat 64-bit segments: present, code execute/read + long mode / data read/write.
+// The user ones only differ in DPL (bits 45-46 = 3).
+const KERNEL_CODE_DESCRIPTOR: u64 = 0x00AF_9A00_0000_FFFF;
+const KERNEL_DATA_DESCRIPTOR: u64 = 0x00CF_9200_0000_FFFF;
+const USER_CODE_DESCRIPTOR: u64 = 0x00AF_FA00_0000_FFFF;
+const USER_DATA_DESCRIPTOR: u64 = 0x00CF_F200_0000_FFFF;
+
+const GDT_ENTRIES: usize = 7;
+
+/// Available 64-bit TSS, present, ring 0
+const TSS_TYPE_AVAILABLE: u64 = 0x89;
+
+/// 64-bit Task State Segment (only the stack pointers matter in long mode)
+#[repr(C, packed)]
+struct TaskStateSegment {
+    reserved0: u32,
+    rsp: [u64; 3],
+    reserved1: u64,
+    ist: [u64; 7],
+    reserved2: u64,
+    reserved3: u16,
+    iomap_base: u16,
+}
+
+#[repr(C, packed)]
+struct GdtDescriptor {
+    limit: u16,
+    base: u64,
+}
+
+#[repr(C, align(16))]
+struct IstStack([u8; IST_STACK_SIZE]);
+
+static mut IST_STACKS: [IstStack; IST_COUNT] = [const { IstStack([0; IST_STACK_SIZE]) }; IST_COUNT];
+
+static mut TSS: TaskStateSegment = TaskStateSegment {
+    reserved0: 0,
+    rsp: [0; 3],
+    reserved1: 0,
+    ist: [0; 7],
+    reserved2: 0,
+    reserved3: 0,
+    // No I/O permission bitmap: point past the end of the TSS
+    iomap_base: size_of::<TaskStateSegment>() as u16,
+};
+
+// TSS slots are filled in by init(), its address isn't a compile-time constant
+static mut GDT: [u64; GDT_ENTRIES] = [
+    0,
+    KERNEL_CODE_DESCRIPTOR,
+    KERNEL_DATA_DESCRIPTOR,
+    USER_CODE_DESCRIPTOR,
+    USER_DATA_DESCRIPTOR,
+    0,
+    0,
+];
+
+static mut GDT_DESCRIPTOR: GdtDescriptor = GdtDescriptor { limit: 0, base: 0 };
+
+/// Build the two-slot system descriptor for a TSS at `base`
+fn tss_descriptor(base: u64, limit: u64) -> (u64, u64) {
+    let low = (limit & 0xFFFF)
+        | ((base & 0xFF_FFFF) << 16)
+        | (TSS_TYPE_AVAILABLE << 40)
+        | (((limit >> 16) & 0xF) << 48)
+        | (((base >> 24) & 0xFF) << 56);
+    (low, base >> 32)
+}
+
+/// Load our GDT, reload CS with a far return and the data segments with the
+/// kernel data selector, then load the TSS.
+/// Call with interrupts disabled, before idt::init.
+pub unsafe fn init() {
+    for (i, stack) in (*core::ptr::addr_of_mut!(IST_STACKS)).iter_mut().enumerate() {
+        // stacks grow down: the IST entry is the (16-byte aligned) top
+        TSS.ist[i] = stack.0.as_mut_ptr().add(IST_STACK_SIZE) as u64;
+    }
+
+    let (tss_low, tss_high) = tss_descriptor(addr_of!(TSS) as u64, (size_of::<TaskStateSegment>() - 1) as u64);
+    let tss_index = (TSS_SELECTOR / 8) as usize;
+    GDT[tss_index] = tss_low;
+    GDT[tss_index + 1] = tss_high;
+
+    GDT_DESCRIPTOR.limit = (size_of::<[u64; GDT_ENTRIES]>() - 1) as u16;
+    GDT_DESCRIPTOR.base = addr_of!(GDT) as u64;
+    asm!("lgdt [{}]", sym GDT_DESCRIPTOR, options(nostack, preserves_flags));
+
+    // CS can only be changed by a far transfer: fake a far call frame and retfq
+    asm!(
+        "push {sel}",
+        "lea {tmp}, [rip + 2f]",
+        "push {tmp}",
+        "retfq",
+        "2:",
+        sel = in(reg) KERNEL_CODE_SELECTOR as u64,
+        tmp = lateout(reg) _,
+        options(preserves_flags)
+    );
+    asm!(
+        "mov ds, {0:x}",
+        "mov es, {0:x}",
+        "mov ss, {0:x}",
+        "mov fs, {0:x}",
+        "mov gs, {0:x}",
+        in(reg) KERNEL_DATA_SELECTOR,
+        options(nostack, preserves_flags)
+    );
+    asm!("ltr {0:x}", in(reg) TSS_SELECTOR, options(nostack, preserves_flags));
+
+    SERIAL_PORT.write_fmt(format_args!(
+        "GDT: loaded, CS={:#x} DS={:#x} TR={:#x}, {} IST stacks of {} KiB\n",
+        KERNEL_CODE_SELECTOR, KERNEL_DATA_SELECTOR, TSS_SELECTOR, IST_COUNT, IST_STACK_SIZE / 1024
+    ));
+}
+
+/// Stack the CPU switches to when an interrupt or int 0x80 arrives from ring 3 (TSS.rsp0)
+pub unsafe fn set_kernel_stack(top: u64) {
+    TSS.rsp[0] = top;
+}
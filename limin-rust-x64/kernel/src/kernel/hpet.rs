@@ -0,0 +1,136 @@
+// src/kernel/hpet.rs - High Precision Event Timer
+//
+// The HPET is a free-running up-counter at a fixed rate (usually 10-25 MHz,
+// femtosecond period in the capabilities register) plus a few comparators.
+// init() finds it through the ACPI "HPET" table and starts the main counter;
+// without one, hpet_now_ns() falls back to the PIT tick count.
+use core::ptr::{read_unaligned, read_volatile, write_volatile};
+use core::sync::atomic::{AtomicU64, Ordering};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::{acpi, paging, timer};
+
+// Register offsets from the MMIO base
+const REG_CAPABILITIES: u64 = 0x000;
+const REG_CONFIG: u64 = 0x010;
+const REG_MAIN_COUNTER: u64 = 0x0F0;
+const REG_TIMER0_CONFIG: u64 = 0x100;
+const REG_TIMER0_COMPARATOR: u64 = 0x108;
+
+// General capabilities
+const CAP_LEGACY_ROUTE: u64 = 1 << 15;
+const CAP_PERIOD_SHIFT: u64 = 32;
+
+// General configuration
+const CONFIG_ENABLE: u64 = 1 << 0;
+const CONFIG_LEGACY_ROUTE: u64 = 1 << 1;
+
+// Timer N configuration
+const TIMER_INT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_PERIODIC_CAP: u64 = 1 << 4;
+const TIMER_VALUE_SET: u64 = 1 << 6;
+
+/// The spec caps the period at 100 ns (a 10 MHz minimum rate)
+const MAX_PERIOD_FS: u64 = 100_000_000;
+const FS_PER_NS: u64 = 1_000_000;
+const FS_PER_SEC: u64 = 1_000_000_000_000_000;
+
+/// Offset of the MMIO base address in the ACPI HPET table (a Generic Address
+/// Structure whose 64-bit address starts 4 bytes in)
+const HPET_TABLE_ADDRESS_OFFSET: usize = 36 + 4 + 4;
+
+/// Mapped register base; 0 until init() found an HPET
+static HPET_BASE: AtomicU64 = AtomicU64::new(0);
+/// Counter period in femtoseconds
+static PERIOD_FS: AtomicU64 = AtomicU64::new(0);
+
+unsafe fn read_reg(base: u64, reg: u64) -> u64 {
+    read_volatile((base + reg) as *const u64)
+}
+
+unsafe fn write_reg(base: u64, reg: u64, value: u64) {
+    write_volatile((base + reg) as *mut u64, value);
+}
+
+/// Find the HPET, map its registers and start the main counter.
+/// Returns false (and leaves the PIT as the only time source) if there is none.
+pub unsafe fn init() -> bool {
+    let Some(table) = acpi::find_table(b"HPET") else {
+        SERIAL_PORT.write_str("HPET: no ACPI HPET table, using the PIT\n");
+        return false;
+    };
+    let phys = read_unaligned((table as *const u8).add(HPET_TABLE_ADDRESS_OFFSET) as *const u64);
+    let Some(base) = paging::map_mmio(phys) else {
+        SERIAL_PORT.write_fmt(format_args!("HPET: could not map registers at {:#x}\n", phys));
+        return false;
+    };
+
+    let caps = read_reg(base, REG_CAPABILITIES);
+    let period_fs = caps >> CAP_PERIOD_SHIFT;
+    if period_fs == 0 || period_fs > MAX_PERIOD_FS {
+        SERIAL_PORT.write_fmt(format_args!("HPET: bad counter period {} fs, ignoring it\n", period_fs));
+        return false;
+    }
+
+    // Stop, zero and restart the main counter
+    let config = read_reg(base, REG_CONFIG);
+    write_reg(base, REG_CONFIG, config & !CONFIG_ENABLE);
+    write_reg(base, REG_MAIN_COUNTER, 0);
+    write_reg(base, REG_CONFIG, config | CONFIG_ENABLE);
+
+    PERIOD_FS.store(period_fs, Ordering::Relaxed);
+    HPET_BASE.store(base, Ordering::Release);
+    SERIAL_PORT.write_fmt(format_args!(
+        "HPET: at {:#x}, {} Hz counter ({} fs period)\n",
+        phys, FS_PER_SEC / period_fs, period_fs
+    ));
+    true
+}
+
+/// Whether init() found and started an HPET
+pub fn is_available() -> bool {
+    HPET_BASE.load(Ordering::Acquire) != 0
+}
+
+/// Nanoseconds since init() started the counter. Without an HPET this is the
+/// PIT uptime, which only moves in whole ticks.
+pub fn hpet_now_ns() -> u64 {
+    let base = HPET_BASE.load(Ordering::Acquire);
+    if base == 0 {
+        return unsafe { timer::get_uptime_ms() } * 1_000_000;
+    }
+    let count = unsafe { read_reg(base, REG_MAIN_COUNTER) };
+    // 128-bit product: a 64-bit one overflows after a few minutes
+    (count as u128 * PERIOD_FS.load(Ordering::Relaxed) as u128 / FS_PER_NS as u128) as u64
+}
+
+/// Make timer 0 fire `freq_hz` times a second on IRQ0 in place of the PIT,
+/// using legacy replacement routing (which also takes over IRQ8 from the RTC).
+/// Returns false if the HPET is missing or can't do periodic legacy interrupts.
+/// Pass the rate timer::init was given so tick-based uptime stays correct.
+pub unsafe fn start_periodic(freq_hz: u32) -> bool {
+    let base = HPET_BASE.load(Ordering::Acquire);
+    if base == 0 || freq_hz == 0 {
+        return false;
+    }
+    let timer_config = read_reg(base, REG_TIMER0_CONFIG);
+    if read_reg(base, REG_CAPABILITIES) & CAP_LEGACY_ROUTE == 0 || timer_config & TIMER_PERIODIC_CAP == 0 {
+        SERIAL_PORT.write_str("HPET: timer 0 can't do periodic legacy interrupts\n");
+        return false;
+    }
+
+    let interval = FS_PER_SEC / freq_hz as u64 / PERIOD_FS.load(Ordering::Relaxed);
+
+    // Reprogram with the counter stopped so the first deadline isn't already behind it
+    let config = read_reg(base, REG_CONFIG);
+    write_reg(base, REG_CONFIG, config & !CONFIG_ENABLE);
+    write_reg(base, REG_TIMER0_CONFIG, timer_config | TIMER_INT_ENABLE | TIMER_PERIODIC | TIMER_VALUE_SET);
+    // With VALUE_SET, the first write sets the comparator and the second the period
+    let now = read_reg(base, REG_MAIN_COUNTER);
+    write_reg(base, REG_TIMER0_COMPARATOR, now + interval);
+    write_reg(base, REG_TIMER0_COMPARATOR, interval);
+    write_reg(base, REG_CONFIG, config | CONFIG_ENABLE | CONFIG_LEGACY_ROUTE);
+
+    SERIAL_PORT.write_fmt(format_args!("HPET: timer 0 periodic at {} Hz on IRQ0\n", freq_hz));
+    true
+}
@@ -2,7 +2,7 @@
 #![no_std]
 
 use core::mem::size_of;
-use core::arch::asm;
+use crate::kernel::gdt;
 use crate::kernel::serial::SERIAL_PORT;
 
 #[repr(C, packed)]
@@ -14,6 +14,31 @@ struct IdtDescriptor {
 #[unsafe(no_mangle)]
 static mut IDT_DESCRIPTOR: IdtDescriptor = IdtDescriptor { limit: 0, base: 0 };
 
+/// Present bit of an IDT entry's flags byte
+const GATE_PRESENT: u8 = 0x80;
+
+/// 64-bit gate types (low nibble of the flags byte). Which to use per vector:
+///   Interrupt  - hardware IRQs, and every exception that reports a fault or
+///                abort (#DF, #GP, #PF, #MC, ...): IF is cleared on entry, so
+///                the handler can't be interrupted before it has saved state
+///   Trap       - #1 debug and #3 breakpoint: IF is left alone, so a debug
+///                stop doesn't shut off the timer and keyboard
+/// int 0x80 stays an interrupt gate (the handler may touch the scheduler) but
+/// needs DPL 3 so ring 3 can invoke it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateType {
+    Interrupt = 0xE,
+    Trap = 0xF,
+}
+
+impl GateType {
+    /// Flags byte for a present gate of this type callable from ring `dpl`
+    /// and more privileged: 0x8E/0x8F for DPL 0, 0xEE/0xEF for DPL 3
+    pub const fn flags(self, dpl: u8) -> u8 {
+        GATE_PRESENT | ((dpl & 0x3) << 5) | self as u8
+    }
+}
+
 // 64-bit IDT entry structure
 #[repr(C, packed)]
 #[derive(Copy, Clone)]
@@ -39,6 +64,23 @@ impl IdtEntry {
         self.reserved = 0;
     }
 
+    /// Install `handler` as an interrupt gate (IF cleared on entry). `dpl` is
+    /// the least privileged ring allowed to raise the vector with `int n`.
+    pub fn set_interrupt_handler(&mut self, handler: unsafe extern "C" fn(), selector: u16, dpl: u8) {
+        self.set_handler(handler, selector, GateType::Interrupt.flags(dpl));
+    }
+
+    /// Install `handler` as a trap gate (IF unchanged on entry)
+    pub fn set_trap_handler(&mut self, handler: unsafe extern "C" fn(), selector: u16, dpl: u8) {
+        self.set_handler(handler, selector, GateType::Trap.flags(dpl));
+    }
+
+    /// Run this vector on stack `index` (1-7) of the TSS Interrupt Stack Table; 0 = none.
+    /// Call after set_handler, which clears it. Index assignments live in gdt.rs.
+    pub fn set_ist(&mut self, index: u8) {
+        self.ist = index & 0x7;
+    }
+
     pub fn set_empty(&mut self) {
         *self = IdtEntry {
             offset_low: 0,
@@ -116,80 +158,94 @@ unsafe extern "C" {
     unsafe fn isr45();  // IRQ13
     unsafe fn isr46();  // IRQ14
     unsafe fn isr47();  // IRQ15
+
+    // System call
+    unsafe fn isr128(); // int 0x80
+
+    // Scheduler
+    unsafe fn isr129(); // sched::yield_now
 }
 
 pub fn init() {
     unsafe {
-        // Get current code segment selector (should be different in 64-bit)
-        let kernel_selector: u16;
-        asm!("mov {0:x}, cs", out(reg) kernel_selector, options(nomem, nostack, preserves_flags));
+        // Our GDT's kernel code segment (gdt::init must have run)
+        let kernel_selector = gdt::KERNEL_CODE_SELECTOR;
 
         SERIAL_PORT.write_str("64-bit IDT: Using kernel selector: 0x");
         SERIAL_PORT.write_hex(kernel_selector as u32);
         SERIAL_PORT.write_str("\n");
 
-        // Set up exception handlers (0-31)
-        IDT[0].set_handler(isr0, kernel_selector, 0x8E);    // Interrupt gate
-        IDT[1].set_handler(isr1, kernel_selector, 0x8E);
-        IDT[2].set_handler(isr2, kernel_selector, 0x8E);
-        IDT[3].set_handler(isr3, kernel_selector, 0x8E);
-        IDT[4].set_handler(isr4, kernel_selector, 0x8E);
-        IDT[5].set_handler(isr5, kernel_selector, 0x8E);
-        IDT[6].set_handler(isr6, kernel_selector, 0x8E);
-        IDT[7].set_handler(isr7, kernel_selector, 0x8E);
-        IDT[8].set_handler(isr8, kernel_selector, 0x8E);    // Double fault - consider IST
-        IDT[9].set_handler(isr9, kernel_selector, 0x8E);
-        IDT[10].set_handler(isr10, kernel_selector, 0x8E);
-        IDT[11].set_handler(isr11, kernel_selector, 0x8E);
-        IDT[12].set_handler(isr12, kernel_selector, 0x8E);
-        IDT[13].set_handler(isr13, kernel_selector, 0x8E);  // GPF
-        IDT[14].set_handler(isr14, kernel_selector, 0x8E);  // Page fault
-        IDT[15].set_handler(isr15, kernel_selector, 0x8E);
-        IDT[16].set_handler(isr16, kernel_selector, 0x8E);
-        IDT[17].set_handler(isr17, kernel_selector, 0x8E);
-        IDT[18].set_handler(isr18, kernel_selector, 0x8E);  // Machine check - consider IST
-        IDT[19].set_handler(isr19, kernel_selector, 0x8E);
-        IDT[20].set_handler(isr20, kernel_selector, 0x8E);
-        IDT[21].set_handler(isr21, kernel_selector, 0x8E);
-        IDT[22].set_handler(isr22, kernel_selector, 0x8E);
-        IDT[23].set_handler(isr23, kernel_selector, 0x8E);
-        IDT[24].set_handler(isr24, kernel_selector, 0x8E);
-        IDT[25].set_handler(isr25, kernel_selector, 0x8E);
-        IDT[26].set_handler(isr26, kernel_selector, 0x8E);
-        IDT[27].set_handler(isr27, kernel_selector, 0x8E);
-        IDT[28].set_handler(isr28, kernel_selector, 0x8E);
-        IDT[29].set_handler(isr29, kernel_selector, 0x8E);
-        IDT[30].set_handler(isr30, kernel_selector, 0x8E);
-        IDT[31].set_handler(isr31, kernel_selector, 0x8E);
-
-        // Set up hardware IRQ handlers (32-47)
-        IDT[32].set_handler(isr32, kernel_selector, 0x8E);  // Timer
-        IDT[33].set_handler(isr33, kernel_selector, 0x8E);  // Keyboard
-        IDT[34].set_handler(isr34, kernel_selector, 0x8E);
-        IDT[35].set_handler(isr35, kernel_selector, 0x8E);
-        IDT[36].set_handler(isr36, kernel_selector, 0x8E);
-        IDT[37].set_handler(isr37, kernel_selector, 0x8E);
-        IDT[38].set_handler(isr38, kernel_selector, 0x8E);
-        IDT[39].set_handler(isr39, kernel_selector, 0x8E);
-        IDT[40].set_handler(isr40, kernel_selector, 0x8E);
-        IDT[41].set_handler(isr41, kernel_selector, 0x8E);
-        IDT[42].set_handler(isr42, kernel_selector, 0x8E);
-        IDT[43].set_handler(isr43, kernel_selector, 0x8E);
-        IDT[44].set_handler(isr44, kernel_selector, 0x8E);
-        IDT[45].set_handler(isr45, kernel_selector, 0x8E);
-        IDT[46].set_handler(isr46, kernel_selector, 0x8E);
-        IDT[47].set_handler(isr47, kernel_selector, 0x8E);
+        // Set up exception handlers (0-31): interrupt gates, except trap gates for #1/#3
+        IDT[0].set_interrupt_handler(isr0, kernel_selector, 0);
+        IDT[1].set_trap_handler(isr1, kernel_selector, 0);   // Debug: keeps IF
+        IDT[2].set_interrupt_handler(isr2, kernel_selector, 0);
+        IDT[2].set_ist(gdt::IST_NMI);
+        IDT[3].set_trap_handler(isr3, kernel_selector, 0);   // Breakpoint: keeps IF
+        IDT[4].set_interrupt_handler(isr4, kernel_selector, 0);
+        IDT[5].set_interrupt_handler(isr5, kernel_selector, 0);
+        IDT[6].set_interrupt_handler(isr6, kernel_selector, 0);
+        IDT[7].set_interrupt_handler(isr7, kernel_selector, 0);
+        IDT[8].set_interrupt_handler(isr8, kernel_selector, 0);    // Double fault
+        IDT[8].set_ist(gdt::IST_DOUBLE_FAULT);
+        IDT[9].set_interrupt_handler(isr9, kernel_selector, 0);
+        IDT[10].set_interrupt_handler(isr10, kernel_selector, 0);
+        IDT[11].set_interrupt_handler(isr11, kernel_selector, 0);
+        IDT[12].set_interrupt_handler(isr12, kernel_selector, 0);
+        IDT[13].set_interrupt_handler(isr13, kernel_selector, 0);  // GPF
+        IDT[14].set_interrupt_handler(isr14, kernel_selector, 0);  // Page fault
+        IDT[15].set_interrupt_handler(isr15, kernel_selector, 0);
+        IDT[16].set_interrupt_handler(isr16, kernel_selector, 0);
+        IDT[17].set_interrupt_handler(isr17, kernel_selector, 0);
+        IDT[18].set_interrupt_handler(isr18, kernel_selector, 0);  // Machine check
+        IDT[18].set_ist(gdt::IST_MACHINE_CHECK);
+        IDT[19].set_interrupt_handler(isr19, kernel_selector, 0);
+        IDT[20].set_interrupt_handler(isr20, kernel_selector, 0);
+        IDT[21].set_interrupt_handler(isr21, kernel_selector, 0);
+        IDT[22].set_interrupt_handler(isr22, kernel_selector, 0);
+        IDT[23].set_interrupt_handler(isr23, kernel_selector, 0);
+        IDT[24].set_interrupt_handler(isr24, kernel_selector, 0);
+        IDT[25].set_interrupt_handler(isr25, kernel_selector, 0);
+        IDT[26].set_interrupt_handler(isr26, kernel_selector, 0);
+        IDT[27].set_interrupt_handler(isr27, kernel_selector, 0);
+        IDT[28].set_interrupt_handler(isr28, kernel_selector, 0);
+        IDT[29].set_interrupt_handler(isr29, kernel_selector, 0);
+        IDT[30].set_interrupt_handler(isr30, kernel_selector, 0);
+        IDT[31].set_interrupt_handler(isr31, kernel_selector, 0);
+
+        // Set up hardware IRQ handlers (32-47): interrupt gates
+        IDT[32].set_interrupt_handler(isr32, kernel_selector, 0);  // Timer
+        IDT[33].set_interrupt_handler(isr33, kernel_selector, 0);  // Keyboard
+        IDT[34].set_interrupt_handler(isr34, kernel_selector, 0);
+        IDT[35].set_interrupt_handler(isr35, kernel_selector, 0);
+        IDT[36].set_interrupt_handler(isr36, kernel_selector, 0);
+        IDT[37].set_interrupt_handler(isr37, kernel_selector, 0);
+        IDT[38].set_interrupt_handler(isr38, kernel_selector, 0);
+        IDT[39].set_interrupt_handler(isr39, kernel_selector, 0);
+        IDT[40].set_interrupt_handler(isr40, kernel_selector, 0);
+        IDT[41].set_interrupt_handler(isr41, kernel_selector, 0);
+        IDT[42].set_interrupt_handler(isr42, kernel_selector, 0);
+        IDT[43].set_interrupt_handler(isr43, kernel_selector, 0);
+        IDT[44].set_interrupt_handler(isr44, kernel_selector, 0);
+        IDT[45].set_interrupt_handler(isr45, kernel_selector, 0);
+        IDT[46].set_interrupt_handler(isr46, kernel_selector, 0);
+        IDT[47].set_interrupt_handler(isr47, kernel_selector, 0);
 
         // Default handler for unused entries
         unsafe extern "C" fn default_isr() {
             SERIAL_PORT.write_str("[DEFAULT_64BIT_ISR]\n");
         }
 
-        // Set default handlers for remaining entries
+        // Set default handlers for remaining entries: interrupt gates
         for i in 48..256 {
-            IDT[i].set_handler(default_isr, kernel_selector, 0x8E);
+            IDT[i].set_interrupt_handler(default_isr, kernel_selector, 0);
         }
 
+        // System calls: interrupt gate, DPL 3 so `int 0x80` from ring 3 isn't a #GP
+        IDT[128].set_interrupt_handler(isr128, kernel_selector, 3);
+
+        // Scheduler yield (sched::SCHED_YIELD_VECTOR): interrupt gate, kernel only
+        IDT[129].set_interrupt_handler(isr129, kernel_selector, 0);
+
         // Set up IDT descriptor
         let idt_limit = (size_of::<[IdtEntry; 256]>() - 1) as u16;
         let idt_base = core::ptr::addr_of_mut!(IDT) as *const _ as usize as u64;
@@ -1,21 +1,144 @@
 // src/kernel/interrupts.rs - Complete 64-bit interrupt handling
 use core::arch::asm;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use crate::kernel::io::inb;
 use crate::kernel::serial::SERIAL_PORT;
 use crate::kernel::pic;
+use crate::kernel::syscall;
+use crate::kernel::sched;
+use crate::kernel::timer;
+use crate::kernel::keyboard;
+use crate::kernel::mouse;
+use crate::kernel::fpu::FxsaveArea;
 use super::interrupts_asm;
 
 // ============================================================================
 // GLOBAL STATE
 // ============================================================================
 
-pub static mut TIMER_TICKS: u64 = 0;
+/// Timer ticks since boot (relaxed: only ever incremented by the timer ISR)
+static TIMER_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// How many interrupts are currently being handled (>1 means nested). The kernel
+/// runs on a single CPU, so one counter serves as the per-CPU depth.
+static NESTING_DEPTH: AtomicU32 = AtomicU32::new(0);
+/// Deepest nesting seen since boot
+static MAX_NESTING_DEPTH: AtomicU32 = AtomicU32::new(0);
+
+/// Number of times each vector has fired (relaxed: counters need no ordering)
+static INT_COUNTS: [AtomicU64; 256] = [const { AtomicU64::new(0) }; 256];
+
+// ============================================================================
+// EXCEPTION HOOKS
+// ============================================================================
+
+/// What the common handler does after an exception hook has run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionAction {
+    /// iretq back using the (possibly modified) frame
+    Resume,
+    /// Dump state and halt, as for an unhandled exception
+    Halt,
+}
+
+/// Exception hook; may edit the frame (e.g. advance rip past a faulting instruction)
+pub type ExceptionHandler = fn(&mut InterruptFrame) -> ExceptionAction;
+
+/// Per-vector hooks for exceptions 0-31. Debug (#1) and breakpoint (#3) resume by default.
+static mut EXCEPTION_HANDLERS: [Option<ExceptionHandler>; 32] = {
+    let mut handlers: [Option<ExceptionHandler>; 32] = [None; 32];
+    handlers[1] = Some(resume_trap);
+    handlers[3] = Some(resume_trap);
+    handlers
+};
+
+/// Install a hook for exception `vector`. Returns false if `vector` is not 0-31.
+/// Faults (unlike traps such as #1/#3) report the RIP of the faulting instruction,
+/// so a hook that returns Resume must fix the cause or advance rip itself.
+pub unsafe fn set_exception_handler(vector: u8, f: ExceptionHandler) -> bool {
+    if vector >= 32 {
+        return false;
+    }
+    EXCEPTION_HANDLERS[vector as usize] = Some(f);
+    true
+}
+
+/// Default for debug/breakpoint traps: log and carry on (rip is already past int3)
+fn resume_trap(frame: &mut InterruptFrame) -> ExceptionAction {
+    unsafe {
+        SERIAL_PORT.write_fmt(format_args!("TRAP #{} at RIP {:#x}, resuming\n", frame.int_no, frame.rip));
+    }
+    ExceptionAction::Resume
+}
+
+/// Selector test_gpf_error_code loads: index 8191, far past the end of our GDT
+const GPF_TEST_SELECTOR: u16 = 0xFFF8;
+/// Error code the #GP hook saw during test_gpf_error_code
+static GPF_TEST_ERR_CODE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+fn gpf_test_hook(frame: &mut InterruptFrame) -> ExceptionAction {
+    GPF_TEST_ERR_CODE.store(frame.err_code, Ordering::Relaxed);
+    // skip the faulting `mov ds, ax` (8E D8)
+    frame.rip += 2;
+    ExceptionAction::Resume
+}
+
+/// Check the error-code stubs end to end: load a selector past the end of the
+/// GDT, which raises #GP with that selector as its error code, and make sure the
+/// handler found it in err_code. Any #GP hook already installed is put back.
+pub unsafe fn test_gpf_error_code() -> bool {
+    let previous = EXCEPTION_HANDLERS[13];
+    EXCEPTION_HANDLERS[13] = Some(gpf_test_hook);
+    GPF_TEST_ERR_CODE.store(u64::MAX, Ordering::Relaxed);
+    // ax, not any register: r8-r15 would need a REX prefix and make it 3 bytes
+    asm!("mov ds, ax", in("ax") GPF_TEST_SELECTOR, options(nostack, preserves_flags));
+    EXCEPTION_HANDLERS[13] = previous;
+
+    let err_code = GPF_TEST_ERR_CODE.load(Ordering::Relaxed);
+    let ok = err_code == GPF_TEST_SELECTOR as u64;
+    SERIAL_PORT.write_fmt(format_args!(
+        "#GP error code test: got {:#x}, expected {:#x}: {}\n",
+        err_code, GPF_TEST_SELECTOR, if ok { "ok" } else { "FAILED" }
+    ));
+    ok
+}
+
+// ============================================================================
+// IRQ HANDLERS
+// ============================================================================
+
+/// Number of legacy PIC lines (IRQ0-15, vectors 32-47)
+pub const IRQ_COUNT: usize = 16;
+
+/// Device handler for a hardware IRQ. Runs in interrupt context with the EOI
+/// still pending, so it must not block or wait for another interrupt.
+pub type IrqHandler = unsafe fn();
+
+/// Per-IRQ driver handlers. The timer (IRQ0) and keyboard (IRQ1) are built in;
+/// other drivers register themselves when they enable their IRQ.
+static mut IRQ_HANDLERS: [Option<IrqHandler>; IRQ_COUNT] = {
+    let mut handlers: [Option<IrqHandler>; IRQ_COUNT] = [None; IRQ_COUNT];
+    handlers[0] = Some(handle_timer_interrupt);
+    handlers[1] = Some(handle_keyboard_interrupt);
+    handlers
+};
+
+/// Install the handler for `irq`, replacing any previous one. Returns false if
+/// `irq` is not 0-15. Unmasking the line on the PIC is still up to the caller.
+pub unsafe fn register_irq_handler(irq: u8, f: IrqHandler) -> bool {
+    if irq as usize >= IRQ_COUNT {
+        return false;
+    }
+    IRQ_HANDLERS[irq as usize] = Some(f);
+    true
+}
 
 // ============================================================================
 // 64-BIT INTERRUPT FRAME STRUCTURE
 // ============================================================================
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct InterruptFrame {
     // Saved by our assembly stub (pushed in reverse order, so r15 is first)
     pub r15: u64,
@@ -46,14 +169,64 @@ pub struct InterruptFrame {
     pub ss: u64,     // User stack segment (if privilege change)
 }
 
+// The frame is read straight off the stack, so its layout must match the pushes.
+// The stack grows down: the last value pushed is the first field.
+//   CPU on entry:      ss, rsp, rflags, cs, rip         -> slots 21..17
+//   isrN stub:         error code (CPU's or a dummy 0), -> slot 16 (err_code)
+//                      then the vector number            -> slot 15 (int_no)
+//   isr_common_stub:   push rax ... push r15             -> slots 14..0
+const _: () = {
+    use core::mem::offset_of;
+    /// Byte offset of stack slot `i`
+    const fn slot(i: usize) -> usize {
+        i * 8
+    }
+    assert!(offset_of!(InterruptFrame, r15) == slot(0), "InterruptFrame.r15 out of place");
+    assert!(offset_of!(InterruptFrame, r14) == slot(1), "InterruptFrame.r14 out of place");
+    assert!(offset_of!(InterruptFrame, r13) == slot(2), "InterruptFrame.r13 out of place");
+    assert!(offset_of!(InterruptFrame, r12) == slot(3), "InterruptFrame.r12 out of place");
+    assert!(offset_of!(InterruptFrame, r11) == slot(4), "InterruptFrame.r11 out of place");
+    assert!(offset_of!(InterruptFrame, r10) == slot(5), "InterruptFrame.r10 out of place");
+    assert!(offset_of!(InterruptFrame, r9) == slot(6), "InterruptFrame.r9 out of place");
+    assert!(offset_of!(InterruptFrame, r8) == slot(7), "InterruptFrame.r8 out of place");
+    assert!(offset_of!(InterruptFrame, rdi) == slot(8), "InterruptFrame.rdi out of place");
+    assert!(offset_of!(InterruptFrame, rsi) == slot(9), "InterruptFrame.rsi out of place");
+    assert!(offset_of!(InterruptFrame, rbp) == slot(10), "InterruptFrame.rbp out of place");
+    assert!(offset_of!(InterruptFrame, rdx) == slot(11), "InterruptFrame.rdx out of place");
+    assert!(offset_of!(InterruptFrame, rcx) == slot(12), "InterruptFrame.rcx out of place");
+    assert!(offset_of!(InterruptFrame, rbx) == slot(13), "InterruptFrame.rbx out of place");
+    assert!(offset_of!(InterruptFrame, rax) == slot(14), "InterruptFrame.rax out of place");
+    assert!(offset_of!(InterruptFrame, int_no) == slot(15), "InterruptFrame.int_no out of place");
+    assert!(offset_of!(InterruptFrame, err_code) == slot(16), "InterruptFrame.err_code out of place");
+    assert!(offset_of!(InterruptFrame, rip) == slot(17), "InterruptFrame.rip out of place");
+    assert!(offset_of!(InterruptFrame, cs) == slot(18), "InterruptFrame.cs out of place");
+    assert!(offset_of!(InterruptFrame, rflags) == slot(19), "InterruptFrame.rflags out of place");
+    assert!(offset_of!(InterruptFrame, rsp) == slot(20), "InterruptFrame.rsp out of place");
+    assert!(offset_of!(InterruptFrame, ss) == slot(21), "InterruptFrame.ss out of place");
+    assert!(size_of::<InterruptFrame>() == slot(22));
+};
+
+impl InterruptFrame {
+    /// All-zero frame, used as a placeholder before a real context is saved
+    pub const fn zeroed() -> Self {
+        InterruptFrame {
+            r15: 0, r14: 0, r13: 0, r12: 0, r11: 0, r10: 0, r9: 0, r8: 0,
+            rdi: 0, rsi: 0, rbp: 0, rdx: 0, rcx: 0, rbx: 0, rax: 0,
+            int_no: 0, err_code: 0,
+            rip: 0, cs: 0, rflags: 0, rsp: 0, ss: 0,
+        }
+    }
+}
+
 // ============================================================================
 // MAIN INTERRUPT HANDLER - 64-bit version
 // ============================================================================
 
 /// Main interrupt dispatcher for 64-bit mode
-/// Called from assembly stub with pointer to interrupt frame
+/// Called from assembly stub with pointer to interrupt frame, and the saved
+/// FPU/SSE state of the interrupted code (null unless fpu saving is enabled)
 #[unsafe(no_mangle)]
-pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
+pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame, fpu: *mut FxsaveArea) {
     unsafe {
         if frame.is_null() {
             SERIAL_PORT.write_str("ERROR: Null interrupt frame!\n");
@@ -71,8 +244,14 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
             halt_system();
         }
 
+        INT_COUNTS[int_no as usize].fetch_add(1, Ordering::Relaxed);
+
+        let depth = NESTING_DEPTH.fetch_add(1, Ordering::Relaxed) + 1;
+        MAX_NESTING_DEPTH.fetch_max(depth, Ordering::Relaxed);
+
         // Debug output for early interrupts (reduced spam)
-        if (int_no != 32 && TIMER_TICKS < 5) || (int_no == 32 && TIMER_TICKS < 3) {
+        let ticks = TIMER_TICKS.load(Ordering::Relaxed);
+        if (int_no != 32 && ticks < 5) || (int_no == 32 && ticks < 3) {
             SERIAL_PORT.write_str("[64-INT:");
             SERIAL_PORT.write_decimal(int_no as u32);
             if err_code != 0 {
@@ -82,49 +261,58 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
             SERIAL_PORT.write_str("] ");
         }
 
-        // Dispatch to specific handlers
-        match int_no {
-            0..=31 => {
-                // CPU exceptions
-                handle_cpu_exception_64(int_no, err_code, frame);
-            },
-            32 => {
-                // Timer interrupt (IRQ0)
-                handle_timer_interrupt();
-                pic::send_eoi(0);
+        // Dispatch to specific handlers. PIC IRQs go by the offsets pic.rs programmed.
+        match (int_no, pic::irq_for_vector(int_no)) {
+            (2 | 18, _) => {
+                // NMI / machine check: running on their own IST stack (see gdt.rs)
+                handle_ist_exception(int_no, frame);
             },
-            33 => {
-                // Keyboard interrupt (IRQ1)
-                handle_keyboard_interrupt();
-                pic::send_eoi(1);
+            (0..=31, _) => {
+                // CPU exceptions: a registered hook may handle it and resume
+                let action = match EXCEPTION_HANDLERS[int_no as usize] {
+                    Some(hook) => hook(&mut *frame),
+                    None => ExceptionAction::Halt,
+                };
+                if action == ExceptionAction::Halt {
+                    handle_cpu_exception_64(int_no, err_code, frame);
+                }
             },
-            34..=47 => {
-                // Other hardware IRQs
-                handle_hardware_irq(int_no);
-                // Send EOI to appropriate PIC
-                if int_no >= 40 {
-                    pic::send_eoi((int_no - 32) as u8); // Slave PIC
-                } else {
-                    pic::send_eoi(0); // Master PIC only
+            (_, Some(irq)) => {
+                // Hardware IRQs: registered driver handler (or a log line), then EOI.
+                // Spurious IRQ7/IRQ15 handle their own EOI.
+                if handle_hardware_irq(irq) {
+                    pic::send_eoi(irq);
+                }
+                // After the EOI (sent exactly once), so the next tick can arrive in
+                // whichever task the rewritten frame returns into. Never switch from a
+                // nested interrupt: the outer handler's frame would be left half-done.
+                if irq == 0 && depth == 1 {
+                    sched::preempt(frame, fpu);
                 }
             },
-            48..=127 => {
+            (32..=127, _) => {
                 // Reserved/unused
                 SERIAL_PORT.write_str("WARN: Reserved interrupt ");
                 SERIAL_PORT.write_decimal(int_no as u32);
                 SERIAL_PORT.write_str("\n");
             },
-            128 => {
+            (128, _) => {
                 // System call interrupt (int 0x80)
                 handle_system_call(frame);
             },
-            129..=255 => {
+            (129, _) => {
+                // sched::yield_now (SCHED_YIELD_VECTOR) - no EOI, not a hardware IRQ
+                if depth == 1 {
+                    sched::switch_from_interrupt(frame, fpu);
+                }
+            },
+            (130..=255, _) => {
                 // Software interrupts or spurious
                 SERIAL_PORT.write_str("SW-INT:");
                 SERIAL_PORT.write_decimal(int_no as u32);
                 SERIAL_PORT.write_str(" ");
             },
-            256..=u64::MAX => {
+            (256..=u64::MAX, _) => {
                 // Invalid interrupt numbers - should never happen due to earlier validation
                 SERIAL_PORT.write_str("FATAL: Invalid interrupt number beyond 255: ");
                 SERIAL_PORT.write_decimal(int_no as u32);
@@ -132,6 +320,8 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
                 halt_system();
             }
         }
+
+        NESTING_DEPTH.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
@@ -141,17 +331,18 @@ pub extern "C" fn isr_common_handler(frame: *mut InterruptFrame) {
 
 /// Handle timer interrupt (IRQ0)
 unsafe fn handle_timer_interrupt() {
-    TIMER_TICKS += 1;
+    let ticks = TIMER_TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    timer::run_expired_timeouts(ticks);
     
     // Periodic output to show system is alive
-    if TIMER_TICKS <= 10 || TIMER_TICKS % 100 == 0 {
+    if ticks <= 10 || ticks.is_multiple_of(100) {
         SERIAL_PORT.write_str("T64:");
-        SERIAL_PORT.write_decimal(TIMER_TICKS as u32);
+        SERIAL_PORT.write_decimal(ticks as u32);
         SERIAL_PORT.write_str(" ");
     }
     
     // Detailed debug for first few ticks
-    if TIMER_TICKS <= 3 {
+    if ticks <= 3 {
         SERIAL_PORT.write_str("(RSP in timer: ");
         let rsp: u64;
         asm!("mov {}, rsp", out(reg) rsp, options(nomem, nostack, preserves_flags));
@@ -161,53 +352,71 @@ unsafe fn handle_timer_interrupt() {
     }
 }
 
+// 8042 ports and the status bits the IRQ1 handler checks
+const PS2_DATA: u16 = 0x60;
+const PS2_STATUS: u16 = 0x64;
+const PS2_STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const PS2_STATUS_AUX_DATA: u8 = 1 << 5;
+
 /// Handle keyboard interrupt (IRQ1)
 unsafe fn handle_keyboard_interrupt() {
-    // Read scancode from keyboard controller
-    let scancode: u8;
-    asm!("in al, 0x60", out("al") scancode, options(nostack, nomem));
-    
+    // Reading 0x60 with the output buffer empty returns a stale byte, and the
+    // keyboard and mouse share it, so check what (if anything) is waiting
+    let status = inb(PS2_STATUS);
+    if status & PS2_STATUS_OUTPUT_FULL == 0 {
+        return;
+    }
+
+    let scancode = inb(PS2_DATA);
+
+    if status & PS2_STATUS_AUX_DATA != 0 {
+        // A mouse byte that arrived ahead of IRQ12 belongs to the mouse packet
+        mouse::handle_byte(scancode);
+        return;
+    }
+    handle_keyboard_byte(scancode);
+}
+
+/// Log and decode one byte the keyboard sent. Also used by keyboard.rs for key
+/// bytes that turn up while it polls port 0x60 for an ACK with interrupts off.
+pub unsafe fn handle_keyboard_byte(scancode: u8) {
     SERIAL_PORT.write_str("K64:0x");
     SERIAL_PORT.write_hex(scancode as u32);
     SERIAL_PORT.write_str(" ");
     
-    // Basic key processing (just for demonstration)
-    match scancode {
-        0x1E => SERIAL_PORT.write_str("(A) "), // A key
-        0x30 => SERIAL_PORT.write_str("(B) "), // B key
-        0x2E => SERIAL_PORT.write_str("(C) "), // C key
-        0x01 => SERIAL_PORT.write_str("(ESC) "), // Escape
-        0x1C => SERIAL_PORT.write_str("(ENTER) "), // Enter
-        _ => {} // Other keys
+    // Decode with whichever scancode set keyboard::set_scancode_set selected
+    if let Some(event) = keyboard::decode(scancode) {
+        let action = if event.pressed { "down" } else { "up" };
+        SERIAL_PORT.write_fmt(format_args!("({:?} {}) ", event.code, action));
     }
 }
 
-/// Handle other hardware IRQs
-unsafe fn handle_hardware_irq(int_no: u64) {
-    let irq_num = int_no - 32;
-    SERIAL_PORT.write_str("HW-IRQ:");
-    SERIAL_PORT.write_decimal(irq_num as u32);
-    SERIAL_PORT.write_str(" ");
-    
-    // Handle specific IRQs if needed
-    match irq_num {
-        2 => { /* Cascade - should never happen */ }
-        3 => { /* COM2 */ }
-        4 => { /* COM1 */ }
-        5 => { /* LPT2 */ }
-        6 => { /* Floppy */ }
-        7 => { /* LPT1 */ }
-        8 => { /* RTC */ }
-        12 => { /* PS/2 Mouse */ }
-        14 => { /* Primary ATA */ }
-        15 => { /* Secondary ATA */ }
-        _ => { /* Other IRQ */ }
+/// Run the registered handler for `irq`, or log it if there is none.
+/// Returns false for spurious IRQ7/IRQ15, which must not receive a normal EOI
+unsafe fn handle_hardware_irq(irq: u8) -> bool {
+    // Spurious interrupts: no ISR bit set, so don't acknowledge a real IRQ
+    if (irq == 7 || irq == 15) && pic::is_spurious(irq) {
+        if irq == 15 {
+            // A spurious IRQ15 still came through the master's cascade line (IRQ2)
+            pic::send_eoi(0);
+        }
+        return false;
     }
+
+    match IRQ_HANDLERS[irq as usize] {
+        Some(handler) => handler(),
+        None => {
+            SERIAL_PORT.write_str("HW-IRQ:");
+            SERIAL_PORT.write_decimal(irq as u32);
+            SERIAL_PORT.write_str(" ");
+        }
+    }
+    true
 }
 
-/// Handle system call (int 0x80) - basic implementation
+/// Handle system call (int 0x80) - dispatched through the syscall table
 unsafe fn handle_system_call(frame: *mut InterruptFrame) {
-    // In 64-bit, system call number typically in RAX
+    // In 64-bit, system call number in RAX, arguments in RDI, RSI, RDX
     let syscall_num = (*frame).rax;
     let arg1 = (*frame).rdi;
     let arg2 = (*frame).rsi;
@@ -217,20 +426,8 @@ unsafe fn handle_system_call(frame: *mut InterruptFrame) {
     SERIAL_PORT.write_decimal(syscall_num as u32);
     SERIAL_PORT.write_str(" ");
     
-    match syscall_num {
-        0 => {
-            // Example: sys_write
-            SERIAL_PORT.write_str("(write) ");
-        },
-        1 => {
-            // Example: sys_exit
-            SERIAL_PORT.write_str("(exit) ");
-        },
-        _ => {
-            SERIAL_PORT.write_str("(unknown) ");
-            (*frame).rax = u64::MAX; // Return error
-        }
-    }
+    // Result goes back to the caller in RAX (u64::MAX for unknown numbers)
+    (*frame).rax = syscall::dispatch(syscall_num, arg1, arg2, arg3);
 }
 
 // ============================================================================
@@ -238,6 +435,22 @@ unsafe fn handle_system_call(frame: *mut InterruptFrame) {
 // ============================================================================
 
 /// Handle CPU exceptions with detailed 64-bit information
+/// NMI and machine-check handler. These arrive on an IST stack and may have
+/// interrupted anything, including another handler, so only the saved frame is
+/// read (never the interrupted stack) before halting.
+fn handle_ist_exception(int_no: u64, frame: *mut InterruptFrame) -> ! {
+    unsafe {
+        let name = if int_no == 2 { "NMI" } else { "MACHINE CHECK" };
+        let f = &*frame;
+        SERIAL_PORT.write_fmt(format_args!(
+            "\n=== {} (vector {}) ===\nRIP={:#018x} CS={:#x} RFLAGS={:#x}\nRSP={:#018x} SS={:#x}\n",
+            name, int_no, f.rip, f.cs, f.rflags, f.rsp, f.ss
+        ));
+        SERIAL_PORT.write_str("System halted.\n");
+    }
+    halt_system();
+}
+
 fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFrame) -> ! {
     unsafe {
         SERIAL_PORT.write_str("\n=== 64-BIT CPU EXCEPTION ===\n");
@@ -306,6 +519,9 @@ fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFram
         SERIAL_PORT.write_hex(((*frame).rflags >> 32) as u32);
         SERIAL_PORT.write_hex((*frame).rflags as u32);
         SERIAL_PORT.write_str("\n");
+
+        dump_control_registers((*frame).rflags);
+        dump_instruction_bytes(int_no, (*frame).rip);
         
         // Special handling for specific exceptions
         match int_no {
@@ -342,13 +558,129 @@ fn handle_cpu_exception_64(int_no: u64, err_code: u64, frame: *mut InterruptFram
     halt_system();
 }
 
+// Control register / RFLAGS bits worth naming in a fault dump
+const CR0_FLAGS: &[(u64, &str)] = &[(1 << 0, "PE"), (1 << 16, "WP"), (1 << 31, "PG")];
+const CR4_FLAGS: &[(u64, &str)] = &[(1 << 5, "PAE"), (1 << 9, "OSFXSR")];
+const RFLAGS_FLAGS: &[(u64, &str)] = &[(1 << 6, "ZF"), (1 << 9, "IF"), (1 << 10, "DF")];
+
+/// Print `name: 0x<value> [FLAG ...]` listing each named bit that is set
+unsafe fn write_decoded(name: &str, value: u64, flags: &[(u64, &str)]) {
+    SERIAL_PORT.write_fmt(format_args!("{}: {:#018x} [", name, value));
+    let mut first = true;
+    for &(bit, flag) in flags {
+        if value & bit != 0 {
+            if !first { SERIAL_PORT.write_str(" "); }
+            SERIAL_PORT.write_str(flag);
+            first = false;
+        }
+    }
+    SERIAL_PORT.write_str("]\n");
+}
+
+/// CR0/CR2/CR3/CR4 and RFLAGS with the paging/SSE/interrupt bits decoded
+unsafe fn dump_control_registers(rflags: u64) {
+    write_decoded("CR0", read_cr0(), CR0_FLAGS);
+    SERIAL_PORT.write_fmt(format_args!("CR2: {:#018x}\n", read_cr2()));
+    SERIAL_PORT.write_fmt(format_args!("CR3: {:#018x}\n", read_cr3()));
+    write_decoded("CR4", read_cr4(), CR4_FLAGS);
+    write_decoded("RFLAGS", rflags, RFLAGS_FLAGS);
+}
+
+/// Max bytes shown at the faulting RIP (longest x86 instruction is 15 bytes)
+const FAULT_BYTES: u64 = 16;
+
+/// Print the bytes at `rip` as hex for pasting into a disassembler. Only reads
+/// inside the kernel's .text (linker symbols), so a bogus RIP can't fault again.
+unsafe fn dump_instruction_bytes(int_no: u64, rip: u64) {
+    let text_start = &raw const __text_start as u64;
+    let text_end = &raw const __text_end as u64;
+
+    let label = if int_no == 6 { "Opcode bytes:" } else { "Bytes at RIP:" };
+    SERIAL_PORT.write_str(label);
+
+    if rip < 0x1000 || rip < text_start || rip >= text_end {
+        SERIAL_PORT.write_str(" (RIP outside kernel text, not read)\n");
+        return;
+    }
+
+    let count = FAULT_BYTES.min(text_end - rip);
+    for i in 0..count {
+        let byte = core::ptr::read_volatile((rip + i) as *const u8);
+        SERIAL_PORT.write_fmt(format_args!(" {:02x}", byte));
+    }
+    SERIAL_PORT.write_str("\n");
+}
+
 // ============================================================================
 // UTILITY FUNCTIONS
 // ============================================================================
 
 /// Get current timer ticks (thread-safe read)
 pub unsafe fn get_timer_ticks() -> u64 {
-    TIMER_TICKS
+    TIMER_TICKS.load(Ordering::Relaxed)
+}
+
+/// RFLAGS interrupt-enable bit
+pub const RFLAGS_IF: u64 = 1 << 9;
+
+/// Disable interrupts and report whether they were enabled, for restore().
+/// Neither asm block is `nomem`: they double as compiler barriers, so loads and
+/// stores between the two can't be moved outside the interrupts-off region.
+#[inline]
+pub fn save_and_disable() -> bool {
+    let rflags: u64;
+    unsafe { asm!("pushfq", "pop {}", "cli", out(reg) rflags) };
+    rflags & RFLAGS_IF != 0
+}
+
+/// Undo save_and_disable: re-enable interrupts only if they were enabled then
+#[inline]
+pub fn restore(were_enabled: bool) {
+    if were_enabled {
+        unsafe { asm!("sti", options(nostack)) };
+    }
+}
+
+/// Run `f` with interrupts disabled, then put IF back the way it was. Unlike a
+/// bare cli/sti pair this nests: an inner critical section doesn't turn
+/// interrupts back on under an outer one.
+#[inline]
+pub fn without_interrupts<F: FnOnce() -> R, R>(f: F) -> R {
+    let were_enabled = save_and_disable();
+    let result = f();
+    restore(were_enabled);
+    result
+}
+
+/// Interrupts currently being handled (0 outside interrupt context)
+pub fn nesting_depth() -> u32 {
+    NESTING_DEPTH.load(Ordering::Relaxed)
+}
+
+/// Number of times `vector` has been dispatched since boot
+pub fn interrupt_count(vector: u8) -> u64 {
+    INT_COUNTS[vector as usize].load(Ordering::Relaxed)
+}
+
+/// Print every vector that has fired at least once
+pub fn dump_interrupt_stats() {
+    unsafe {
+        SERIAL_PORT.write_str("=== INTERRUPT STATISTICS ===\n");
+        SERIAL_PORT.write_str("  Max nesting depth: ");
+        SERIAL_PORT.write_decimal(MAX_NESTING_DEPTH.load(Ordering::Relaxed));
+        SERIAL_PORT.write_str("\n");
+        for vector in 0..=255u8 {
+            let count = interrupt_count(vector);
+            if count == 0 {
+                continue;
+            }
+            SERIAL_PORT.write_str("  Vector ");
+            SERIAL_PORT.write_decimal(vector as u32);
+            SERIAL_PORT.write_str(": ");
+            SERIAL_PORT.write_fmt(format_args!("{}", count));
+            SERIAL_PORT.write_str("\n");
+        }
+    }
 }
 
 /// Halt the system permanently
@@ -420,6 +752,12 @@ unsafe extern "C" {
     // Additional utility functions from assembly
     pub unsafe fn get_rsp() -> u64;
     pub unsafe fn get_rbp() -> u64;
+    pub unsafe fn read_cr0() -> u64;
     pub unsafe fn read_cr2() -> u64;
     pub unsafe fn read_cr3() -> u64;
+    pub unsafe fn read_cr4() -> u64;
+
+    // Kernel .text bounds from the linker script
+    static __text_start: u8;
+    static __text_end: u8;
 }
\ No newline at end of file
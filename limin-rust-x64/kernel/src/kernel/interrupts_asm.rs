@@ -6,6 +6,7 @@ r#"
 .intel_syntax noprefix
 
 .extern isr_common_handler
+.extern FPU_SAVE_ENABLED
 
 # ============================================================================
 # COMMON INTERRUPT STUB - 64-bit version
@@ -36,11 +37,30 @@ isr_common_stub:
     # Align stack to 16-byte boundary (required for 64-bit calling convention)
     mov rbp, rsp
     and rsp, -16
-    
+
+    # Opt-in x87/SSE save (fpu::enable_interrupt_save). The FXSAVE area is passed
+    # as the second argument, or null when saving is off. rbx (already saved
+    # above, callee-saved across the call) remembers whether to restore it.
+    movzx ebx, byte ptr [rip + FPU_SAVE_ENABLED]
+    xor esi, esi
+    test ebx, ebx
+    jz .Lskip_fxsave
+    sub rsp, 512
+    fxsave64 [rsp]
+    mov rsi, rsp
+.Lskip_fxsave:
+
     # Call the high-level handler
     call isr_common_handler
+
+    test ebx, ebx
+    jz .Lskip_fxrstor
+    fxrstor64 [rsp]
+.Lskip_fxrstor:
     
-    # Restore stack pointer
+    # Restore stack pointer. rbp is callee-saved, so it still holds the frame
+    # pointer here even if the handler was itself interrupted: a nested interrupt
+    # runs this same stub, which pushes/pops every register around its own call.
     mov rsp, rbp
     
     # Restore all registers
@@ -70,6 +90,11 @@ isr_common_stub:
 # EXCEPTION HANDLERS (0-31) - Manually expanded since no macros
 # ============================================================================
 
+# Every stub leaves the same layout for isr_common_stub: the error code above
+# the vector number (InterruptFrame.err_code after int_no). The CPU pushes a
+# real error code for 8, 10-14, 17, 21, 29 and 30, so those stubs only push the
+# vector; all others push a dummy 0 in the error code's place first.
+
 # Exceptions that don't push error codes
 .globl isr0
 isr0:
@@ -369,6 +394,16 @@ isr128:
     push 128      # Push interrupt number (0x80)
     jmp isr_common_stub
 
+# ============================================================================
+# SCHEDULER YIELD (sched::yield_now)
+# ============================================================================
+
+.globl isr129
+isr129:
+    push 0        # Push dummy error code
+    push 129      # Push interrupt number (0x81)
+    jmp isr_common_stub
+
 # ============================================================================
 # UTILITY FUNCTIONS
 # ============================================================================
@@ -383,6 +418,11 @@ get_rbp:
     mov rax, rbp
     ret
 
+.globl read_cr0
+read_cr0:
+    mov rax, cr0
+    ret
+
 .globl read_cr2
 read_cr2:
     mov rax, cr2
@@ -393,6 +433,11 @@ read_cr3:
     mov rax, cr3
     ret
 
+.globl read_cr4
+read_cr4:
+    mov rax, cr4
+    ret
+
 .att_syntax prefix
 "#
 );
\ No newline at end of file
@@ -0,0 +1,47 @@
+// x86 port I/O. One copy per kernel, next to serial.rs, so every driver uses the
+// same helpers instead of its own asm. All of them are unsafe: a port write can
+// reprogram any device, and a read can pop a byte off a device's FIFO.
+use core::arch::asm;
+
+#[inline]
+pub unsafe fn outb(port: u16, value: u8) {
+    asm!("out dx, al", in("dx") port, in("al") value, options(nostack, nomem));
+}
+
+#[inline]
+pub unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    asm!("in al, dx", in("dx") port, out("al") value, options(nostack, nomem));
+    value
+}
+
+#[inline]
+pub unsafe fn outw(port: u16, value: u16) {
+    asm!("out dx, ax", in("dx") port, in("ax") value, options(nostack, nomem));
+}
+
+#[inline]
+pub unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    asm!("in ax, dx", in("dx") port, out("ax") value, options(nostack, nomem));
+    value
+}
+
+#[inline]
+pub unsafe fn outl(port: u16, value: u32) {
+    asm!("out dx, eax", in("dx") port, in("eax") value, options(nostack, nomem));
+}
+
+#[inline]
+pub unsafe fn inl(port: u16) -> u32 {
+    let value: u32;
+    asm!("in eax, dx", in("dx") port, out("eax") value, options(nostack, nomem));
+    value
+}
+
+/// Short delay for devices (like the PIC) that need time between accesses:
+/// a write to the unused POST diagnostic port 0x80 takes about 1 us
+#[inline]
+pub unsafe fn io_wait() {
+    outb(0x80, 0);
+}
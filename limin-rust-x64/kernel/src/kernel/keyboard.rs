@@ -0,0 +1,341 @@
+// src/kernel/keyboard.rs - PS/2 keyboard scancode decoding (sets 1 and 2)
+//
+// With translation on (the 8042 default) the controller turns whatever the
+// keyboard sends into set 1. Turn translation off and most keyboards deliver
+// set 2 instead: releases are prefixed with 0xF0 rather than having bit 7 set,
+// and extended keys are prefixed with 0xE0 in both sets.
+use crate::kernel::io::{inb, outb};
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use crate::kernel::{interrupts, mouse};
+
+/// Keys we decode. Extended keys (0xE0 prefix) get their own values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCode {
+    Escape,
+    Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, Num0,
+    Minus, Equals, Backspace, Tab,
+    Q, W, E, R, T, Y, U, I, O, P, LeftBracket, RightBracket, Enter,
+    A, S, D, F, G, H, J, K, L, Semicolon, Quote, Backtick, Backslash,
+    Z, X, C, V, B, N, M, Comma, Period, Slash,
+    Space, CapsLock, NumLock, ScrollLock,
+    LeftShift, RightShift, LeftCtrl, RightCtrl, LeftAlt, RightAlt,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    KeypadStar, KeypadEnter,
+    Up, Down, Left, Right, Home, End, PageUp, PageDown, Insert, Delete,
+    Pause,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub pressed: bool,
+}
+
+/// Controller replies that are never key data in set 2
+/// (0xAA self-test passed, 0xEE echo, 0xFA ACK, 0xFE resend, 0x00/0xFF errors)
+const SET2_NON_KEY_BYTES: [u8; 6] = [0x00, 0xAA, 0xEE, 0xFA, 0xFE, 0xFF];
+/// Same for set 1; 0xAA is left out because it is the LeftShift release there
+const SET1_NON_KEY_BYTES: [u8; 4] = [0x00, 0xFA, 0xFE, 0xFF];
+
+// Prefix bytes
+const PREFIX_EXTENDED: u8 = 0xE0;
+const PREFIX_PAUSE: u8 = 0xE1;
+const PREFIX_RELEASE: u8 = 0xF0; // set 2 only
+
+/// Bytes that follow the 0xE1 of the Pause sequence (it has no release code)
+const SET1_PAUSE_TAIL: u8 = 5; // E1 1D 45 E1 9D C5
+const SET2_PAUSE_TAIL: u8 = 7; // E1 14 77 E1 F0 14 F0 77
+
+static SCANCODE_SET: AtomicU8 = AtomicU8::new(1);
+
+// 8042 ports and status bits
+const PS2_DATA: u16 = 0x60;
+const PS2_STATUS: u16 = 0x64;
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+const STATUS_AUX_DATA: u8 = 1 << 5;
+
+// Keyboard command and replies
+const KBD_SET_LEDS: u8 = 0xED;
+const KBD_ACK: u8 = 0xFA;
+const KBD_RESEND: u8 = 0xFE;
+
+/// LED bits in the byte after 0xED; the lock state uses the same layout
+const LED_SCROLL: u8 = 1 << 0;
+const LED_NUM: u8 = 1 << 1;
+const LED_CAPS: u8 = 1 << 2;
+
+/// Status polls before giving up on the controller
+const PS2_TIMEOUT: u32 = 100_000;
+/// Times a byte is sent again after the keyboard answers 0xFE
+const MAX_RESENDS: u32 = 3;
+
+/// Caps/Num/Scroll lock state (LED_* bits)
+static LOCK_STATE: AtomicU8 = AtomicU8::new(0);
+/// Lock keys currently held down (LED_* bits), so typematic repeats of a held
+/// Caps Lock don't toggle it again
+static LOCK_KEYS_HELD: AtomicU8 = AtomicU8::new(0);
+/// LOCK_STATE changed since the LEDs were last set; sync_leds() picks it up
+static LEDS_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Multi-byte sequence state, only touched by the IRQ1 handler
+struct Decoder {
+    set: u8,
+    extended: bool,
+    release: bool,
+    skip: u8,
+}
+
+static mut DECODER: Decoder = Decoder { set: 1, extended: false, release: false, skip: 0 };
+
+fn set1_key(code: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        0x01 => Escape,
+        0x02 => Num1, 0x03 => Num2, 0x04 => Num3, 0x05 => Num4, 0x06 => Num5,
+        0x07 => Num6, 0x08 => Num7, 0x09 => Num8, 0x0A => Num9, 0x0B => Num0,
+        0x0C => Minus, 0x0D => Equals, 0x0E => Backspace, 0x0F => Tab,
+        0x10 => Q, 0x11 => W, 0x12 => E, 0x13 => R, 0x14 => T,
+        0x15 => Y, 0x16 => U, 0x17 => I, 0x18 => O, 0x19 => P,
+        0x1A => LeftBracket, 0x1B => RightBracket, 0x1C => Enter, 0x1D => LeftCtrl,
+        0x1E => A, 0x1F => S, 0x20 => D, 0x21 => F, 0x22 => G,
+        0x23 => H, 0x24 => J, 0x25 => K, 0x26 => L,
+        0x27 => Semicolon, 0x28 => Quote, 0x29 => Backtick, 0x2A => LeftShift, 0x2B => Backslash,
+        0x2C => Z, 0x2D => X, 0x2E => C, 0x2F => V, 0x30 => B, 0x31 => N, 0x32 => M,
+        0x33 => Comma, 0x34 => Period, 0x35 => Slash, 0x36 => RightShift,
+        0x37 => KeypadStar, 0x38 => LeftAlt, 0x39 => Space, 0x3A => CapsLock,
+        0x3B => F1, 0x3C => F2, 0x3D => F3, 0x3E => F4, 0x3F => F5,
+        0x40 => F6, 0x41 => F7, 0x42 => F8, 0x43 => F9, 0x44 => F10,
+        0x45 => NumLock, 0x46 => ScrollLock, 0x57 => F11, 0x58 => F12,
+        _ => return None,
+    })
+}
+
+fn set1_extended_key(code: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        0x1C => KeypadEnter, 0x1D => RightCtrl, 0x38 => RightAlt,
+        0x47 => Home, 0x48 => Up, 0x49 => PageUp, 0x4B => Left, 0x4D => Right,
+        0x4F => End, 0x50 => Down, 0x51 => PageDown, 0x52 => Insert, 0x53 => Delete,
+        _ => return None,
+    })
+}
+
+fn set2_key(code: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        0x76 => Escape,
+        0x16 => Num1, 0x1E => Num2, 0x26 => Num3, 0x25 => Num4, 0x2E => Num5,
+        0x36 => Num6, 0x3D => Num7, 0x3E => Num8, 0x46 => Num9, 0x45 => Num0,
+        0x4E => Minus, 0x55 => Equals, 0x66 => Backspace, 0x0D => Tab,
+        0x15 => Q, 0x1D => W, 0x24 => E, 0x2D => R, 0x2C => T,
+        0x35 => Y, 0x3C => U, 0x43 => I, 0x44 => O, 0x4D => P,
+        0x54 => LeftBracket, 0x5B => RightBracket, 0x5A => Enter, 0x14 => LeftCtrl,
+        0x1C => A, 0x1B => S, 0x23 => D, 0x2B => F, 0x34 => G,
+        0x33 => H, 0x3B => J, 0x42 => K, 0x4B => L,
+        0x4C => Semicolon, 0x52 => Quote, 0x0E => Backtick, 0x12 => LeftShift, 0x5D => Backslash,
+        0x1A => Z, 0x22 => X, 0x21 => C, 0x2A => V, 0x32 => B, 0x31 => N, 0x3A => M,
+        0x41 => Comma, 0x49 => Period, 0x4A => Slash, 0x59 => RightShift,
+        0x7C => KeypadStar, 0x11 => LeftAlt, 0x29 => Space, 0x58 => CapsLock,
+        0x05 => F1, 0x06 => F2, 0x04 => F3, 0x0C => F4, 0x03 => F5,
+        0x0B => F6, 0x83 => F7, 0x0A => F8, 0x01 => F9, 0x09 => F10,
+        0x78 => F11, 0x07 => F12, 0x77 => NumLock, 0x7E => ScrollLock,
+        _ => return None,
+    })
+}
+
+fn set2_extended_key(code: u8) -> Option<KeyCode> {
+    use KeyCode::*;
+    Some(match code {
+        0x5A => KeypadEnter, 0x14 => RightCtrl, 0x11 => RightAlt,
+        0x6C => Home, 0x75 => Up, 0x7D => PageUp, 0x6B => Left, 0x74 => Right,
+        0x69 => End, 0x72 => Down, 0x7A => PageDown, 0x70 => Insert, 0x71 => Delete,
+        _ => return None,
+    })
+}
+
+impl Decoder {
+    fn reset(&mut self, set: u8) {
+        *self = Decoder { set, extended: false, release: false, skip: 0 };
+    }
+
+    fn feed(&mut self, byte: u8) -> Option<KeyEvent> {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return None;
+        }
+        if self.set == 2 { self.feed_set2(byte) } else { self.feed_set1(byte) }
+    }
+
+    fn feed_set1(&mut self, byte: u8) -> Option<KeyEvent> {
+        match byte {
+            PREFIX_EXTENDED => {
+                self.extended = true;
+                return None;
+            }
+            PREFIX_PAUSE => {
+                self.skip = SET1_PAUSE_TAIL;
+                return Some(KeyEvent { code: KeyCode::Pause, pressed: true });
+            }
+            _ if SET1_NON_KEY_BYTES.contains(&byte) => return None,
+            _ => {}
+        }
+
+        let extended = core::mem::take(&mut self.extended);
+        let pressed = byte & 0x80 == 0;
+        let code = byte & 0x7F;
+        // Unknown codes include the fake shifts around Print Screen (E0 2A / E0 B7)
+        let key = if extended { set1_extended_key(code) } else { set1_key(code) }?;
+        Some(KeyEvent { code: key, pressed })
+    }
+
+    fn feed_set2(&mut self, byte: u8) -> Option<KeyEvent> {
+        match byte {
+            PREFIX_EXTENDED => {
+                self.extended = true;
+                return None;
+            }
+            PREFIX_RELEASE => {
+                self.release = true;
+                return None;
+            }
+            PREFIX_PAUSE => {
+                self.skip = SET2_PAUSE_TAIL;
+                return Some(KeyEvent { code: KeyCode::Pause, pressed: true });
+            }
+            _ if SET2_NON_KEY_BYTES.contains(&byte) => return None,
+            _ => {}
+        }
+
+        let extended = core::mem::take(&mut self.extended);
+        let pressed = !core::mem::take(&mut self.release);
+        let key = if extended { set2_extended_key(byte) } else { set2_key(byte) }?;
+        Some(KeyEvent { code: key, pressed })
+    }
+}
+
+// ============================================================================
+// LEDS
+// ============================================================================
+
+/// Send one byte to the keyboard and wait for its ACK, sending it again when
+/// the keyboard asks for a resend. False on timeout or too many resends.
+unsafe fn send_with_ack(byte: u8) -> bool {
+    for _ in 0..=MAX_RESENDS {
+        let mut ready = false;
+        for _ in 0..PS2_TIMEOUT {
+            if inb(PS2_STATUS) & STATUS_INPUT_FULL == 0 {
+                ready = true;
+                break;
+            }
+        }
+        if !ready {
+            return false;
+        }
+        outb(PS2_DATA, byte);
+
+        // Hand anything that isn't a reply to whoever IRQ1/IRQ12 would have
+        // given it to: interrupts are off, so they can't pick it up themselves
+        for _ in 0..PS2_TIMEOUT {
+            let status = inb(PS2_STATUS);
+            if status & STATUS_OUTPUT_FULL == 0 {
+                continue;
+            }
+            let reply = inb(PS2_DATA);
+            if status & STATUS_AUX_DATA != 0 {
+                mouse::handle_byte(reply);
+                continue;
+            }
+            match reply {
+                KBD_ACK => return true,
+                KBD_RESEND => break,
+                key => interrupts::handle_keyboard_byte(key),
+            }
+        }
+    }
+    false
+}
+
+/// Set the Caps/Num/Scroll lock LEDs. Polls port 0x60 for the ACKs with
+/// interrupts off, so the IRQ1 handler can't swallow them. Not for interrupt
+/// context: the keyboard can take milliseconds to answer. Returns false if the
+/// keyboard didn't acknowledge.
+pub fn set_leds(caps: bool, num: bool, scroll: bool) -> bool {
+    let mut mask = 0;
+    if caps { mask |= LED_CAPS; }
+    if num { mask |= LED_NUM; }
+    if scroll { mask |= LED_SCROLL; }
+    interrupts::without_interrupts(|| unsafe { send_with_ack(KBD_SET_LEDS) && send_with_ack(mask) })
+}
+
+/// Mirror the lock state on the LEDs if a lock key changed it since the last
+/// call. The IRQ1 handler only records the change; call this from a polling loop.
+pub fn sync_leds() {
+    if LEDS_PENDING.swap(false, Ordering::Relaxed) {
+        let locks = LOCK_STATE.load(Ordering::Relaxed);
+        set_leds(locks & LED_CAPS != 0, locks & LED_NUM != 0, locks & LED_SCROLL != 0);
+    }
+}
+
+pub fn caps_lock() -> bool {
+    LOCK_STATE.load(Ordering::Relaxed) & LED_CAPS != 0
+}
+
+pub fn num_lock() -> bool {
+    LOCK_STATE.load(Ordering::Relaxed) & LED_NUM != 0
+}
+
+pub fn scroll_lock() -> bool {
+    LOCK_STATE.load(Ordering::Relaxed) & LED_SCROLL != 0
+}
+
+/// Toggle the lock state on the first press of a lock key and leave the LED
+/// update for sync_leds()
+fn update_locks(event: KeyEvent) {
+    let bit = match event.code {
+        KeyCode::CapsLock => LED_CAPS,
+        KeyCode::NumLock => LED_NUM,
+        KeyCode::ScrollLock => LED_SCROLL,
+        _ => return,
+    };
+    if !event.pressed {
+        LOCK_KEYS_HELD.fetch_and(!bit, Ordering::Relaxed);
+        return;
+    }
+    if LOCK_KEYS_HELD.fetch_or(bit, Ordering::Relaxed) & bit != 0 {
+        return;
+    }
+    LOCK_STATE.fetch_xor(bit, Ordering::Relaxed);
+    LEDS_PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Choose which scancode set to decode (1 with 8042 translation on, 2 with it off).
+/// Returns false for anything other than 1 or 2.
+pub fn set_scancode_set(set: u8) -> bool {
+    if set != 1 && set != 2 {
+        return false;
+    }
+    SCANCODE_SET.store(set, Ordering::Relaxed);
+    true
+}
+
+/// Scancode set currently being decoded
+pub fn scancode_set() -> u8 {
+    SCANCODE_SET.load(Ordering::Relaxed)
+}
+
+/// Feed one byte from port 0x60. Returns a key event once a full sequence has
+/// arrived, None for prefixes, controller replies and unknown keys. Lock keys
+/// also toggle their lock state (see sync_leds for the LED).
+/// Only call from the IRQ1 handler, or with interrupts off.
+pub unsafe fn decode(byte: u8) -> Option<KeyEvent> {
+    let decoder = &mut *core::ptr::addr_of_mut!(DECODER);
+    // Drop any half-finished sequence from the previous set
+    let set = SCANCODE_SET.load(Ordering::Relaxed);
+    if decoder.set != set {
+        decoder.reset(set);
+    }
+    let event = decoder.feed(byte)?;
+    update_locks(event);
+    Some(event)
+}
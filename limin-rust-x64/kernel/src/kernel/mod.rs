@@ -1,7 +1,33 @@
 pub mod serial;
+pub mod io;
+pub mod console;
 pub mod loggers;
 pub mod interrupts;
 pub mod idt;
+pub mod gdt;
+pub mod usermode;
 pub mod pic;
 pub mod timer;
 pub mod interrupts_asm;
+
+pub mod syscall;
+pub mod qemu;
+pub mod sched;
+pub mod sync;
+pub mod mouse;
+pub mod ps2;
+pub mod keyboard;
+pub mod ata;
+pub mod shell;
+pub mod fpu;
+pub mod cpuid;
+pub mod symbols;
+pub mod paging;
+pub mod pci;
+pub mod acpi;
+pub mod hpet;
+pub mod rtc;
+pub mod ramfs;
+pub mod array_string;
+#[cfg(feature = "test-faults")]
+pub mod fault_tests;
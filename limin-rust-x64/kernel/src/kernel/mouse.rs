@@ -0,0 +1,209 @@
+// src/kernel/mouse.rs - PS/2 mouse on the 8042 aux port (IRQ12)
+use crate::kernel::io::{inb, outb};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use crate::kernel::interrupts;
+use crate::kernel::serial::SERIAL_PORT;
+
+/// PIC line the 8042 raises for aux port data
+const MOUSE_IRQ: u8 = 12;
+
+// 8042 controller ports
+const PS2_DATA: u16 = 0x60;
+const PS2_STATUS: u16 = 0x64;    // read
+const PS2_COMMAND: u16 = 0x64;   // write
+
+// Status register bits
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+const STATUS_AUX_DATA: u8 = 1 << 5;
+
+// Controller commands
+const CMD_READ_CONFIG: u8 = 0x20;
+const CMD_WRITE_CONFIG: u8 = 0x60;
+const CMD_ENABLE_AUX: u8 = 0xA8;
+const CMD_WRITE_AUX: u8 = 0xD4;
+
+// Controller configuration byte bits
+const CONFIG_AUX_IRQ: u8 = 1 << 1;
+const CONFIG_AUX_CLOCK_DISABLED: u8 = 1 << 5;
+
+// Mouse commands and replies
+const MOUSE_SET_DEFAULTS: u8 = 0xF6;
+const MOUSE_ENABLE_STREAMING: u8 = 0xF4;
+const MOUSE_ACK: u8 = 0xFA;
+
+// First packet byte flags
+const PACKET_ALWAYS_ONE: u8 = 1 << 3;
+const PACKET_X_SIGN: u8 = 1 << 4;
+const PACKET_Y_SIGN: u8 = 1 << 5;
+const PACKET_X_OVERFLOW: u8 = 1 << 6;
+const PACKET_Y_OVERFLOW: u8 = 1 << 7;
+const PACKET_BUTTONS: u8 = 0x07;
+
+/// Status polls before giving up on the controller
+const PS2_TIMEOUT: u32 = 100_000;
+
+/// Size of the event ring buffer
+const EVENT_BUFFER_SIZE: usize = 64;
+
+/// One decoded mouse packet. dy follows the PS/2 convention: positive is up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub dx: i8,
+    pub dy: i8,
+    /// bit 0 = left, bit 1 = right, bit 2 = middle
+    pub buttons: u8,
+}
+
+impl MouseEvent {
+    fn pack(self) -> u32 {
+        (self.dx as u8 as u32) | ((self.dy as u8 as u32) << 8) | ((self.buttons as u32) << 16)
+    }
+
+    fn unpack(value: u32) -> Self {
+        MouseEvent { dx: value as u8 as i8, dy: (value >> 8) as u8 as i8, buttons: (value >> 16) as u8 }
+    }
+}
+
+// Packet assembly state, only touched by the IRQ12 handler
+static mut PACKET: [u8; 3] = [0; 3];
+static mut PACKET_INDEX: usize = 0;
+
+// Single-producer (ISR) / single-consumer (main loop) ring of packed events
+static EVENTS: [AtomicU32; EVENT_BUFFER_SIZE] = [const { AtomicU32::new(0) }; EVENT_BUFFER_SIZE];
+static EVENTS_HEAD: AtomicUsize = AtomicUsize::new(0);
+static EVENTS_TAIL: AtomicUsize = AtomicUsize::new(0);
+
+// ============================================================================
+// 8042 HELPERS
+// ============================================================================
+
+/// Wait until the controller can accept a byte
+unsafe fn wait_write() -> bool {
+    for _ in 0..PS2_TIMEOUT {
+        if inb(PS2_STATUS) & STATUS_INPUT_FULL == 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Wait until there is a byte to read
+unsafe fn wait_read() -> bool {
+    for _ in 0..PS2_TIMEOUT {
+        if inb(PS2_STATUS) & STATUS_OUTPUT_FULL != 0 {
+            return true;
+        }
+    }
+    false
+}
+
+unsafe fn controller_command(cmd: u8) {
+    wait_write();
+    outb(PS2_COMMAND, cmd);
+}
+
+/// Send a command to the mouse and wait for its ACK
+unsafe fn mouse_command(cmd: u8) -> bool {
+    controller_command(CMD_WRITE_AUX);
+    wait_write();
+    outb(PS2_DATA, cmd);
+    wait_read() && inb(PS2_DATA) == MOUSE_ACK
+}
+
+// ============================================================================
+// PUBLIC API
+// ============================================================================
+
+/// Enable the aux port, turn on IRQ12 generation and start streaming packets.
+/// The caller still has to unmask IRQ12 (and the IRQ2 cascade) on the PIC.
+/// Runs with interrupts off so the IRQ1 handler can't read the config byte or
+/// the mouse's ACKs off port 0x60 first.
+pub unsafe fn init() -> bool {
+    interrupts::without_interrupts(|| init_device())
+}
+
+unsafe fn init_device() -> bool {
+    controller_command(CMD_ENABLE_AUX);
+
+    // Enable the aux IRQ and the aux clock in the configuration byte
+    controller_command(CMD_READ_CONFIG);
+    if !wait_read() {
+        SERIAL_PORT.write_str("MOUSE: timeout reading 8042 config\n");
+        return false;
+    }
+    let config = (inb(PS2_DATA) | CONFIG_AUX_IRQ) & !CONFIG_AUX_CLOCK_DISABLED;
+    controller_command(CMD_WRITE_CONFIG);
+    wait_write();
+    outb(PS2_DATA, config);
+
+    if !mouse_command(MOUSE_SET_DEFAULTS) || !mouse_command(MOUSE_ENABLE_STREAMING) {
+        SERIAL_PORT.write_str("MOUSE: no ACK from aux device\n");
+        return false;
+    }
+
+    PACKET_INDEX = 0;
+    interrupts::register_irq_handler(MOUSE_IRQ, handle_interrupt);
+    SERIAL_PORT.write_str("MOUSE: PS/2 mouse streaming enabled\n");
+    true
+}
+
+/// IRQ12 handler: read the pending aux byte, if it really is one
+pub unsafe fn handle_interrupt() {
+    if inb(PS2_STATUS) & (STATUS_OUTPUT_FULL | STATUS_AUX_DATA) != (STATUS_OUTPUT_FULL | STATUS_AUX_DATA) {
+        return;
+    }
+    handle_byte(inb(PS2_DATA));
+}
+
+/// Collect one aux byte already read from the data port, and emit an event
+/// once a packet is complete. Also fed by the IRQ1 handler when it finds the
+/// output buffer holds a mouse byte.
+pub unsafe fn handle_byte(byte: u8) {
+    // Byte 0 always has bit 3 set; anything else means we lost sync
+    if PACKET_INDEX == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+        return;
+    }
+
+    PACKET[PACKET_INDEX] = byte;
+    PACKET_INDEX += 1;
+    if PACKET_INDEX < 3 {
+        return;
+    }
+    PACKET_INDEX = 0;
+
+    let flags = PACKET[0];
+    if flags & (PACKET_X_OVERFLOW | PACKET_Y_OVERFLOW) != 0 {
+        return; // deltas are garbage
+    }
+
+    // 9-bit two's complement deltas: sign bit in byte 0, low 8 bits in byte 1/2
+    let delta = |low: u8, negative: bool| {
+        let value = if negative { low as i16 - 256 } else { low as i16 };
+        value.clamp(i8::MIN as i16, i8::MAX as i16) as i8
+    };
+    let event = MouseEvent {
+        dx: delta(PACKET[1], flags & PACKET_X_SIGN != 0),
+        dy: delta(PACKET[2], flags & PACKET_Y_SIGN != 0),
+        buttons: flags & PACKET_BUTTONS,
+    };
+
+    let head = EVENTS_HEAD.load(Ordering::Relaxed);
+    let next = (head + 1) % EVENT_BUFFER_SIZE;
+    if next == EVENTS_TAIL.load(Ordering::Acquire) {
+        return; // buffer full, drop the event
+    }
+    EVENTS[head].store(event.pack(), Ordering::Relaxed);
+    EVENTS_HEAD.store(next, Ordering::Release);
+}
+
+/// Take the oldest pending mouse event, if any
+pub fn poll_mouse_event() -> Option<MouseEvent> {
+    let tail = EVENTS_TAIL.load(Ordering::Relaxed);
+    if tail == EVENTS_HEAD.load(Ordering::Acquire) {
+        return None;
+    }
+    let event = MouseEvent::unpack(EVENTS[tail].load(Ordering::Relaxed));
+    EVENTS_TAIL.store((tail + 1) % EVENT_BUFFER_SIZE, Ordering::Release);
+    Some(event)
+}
@@ -0,0 +1,207 @@
+// src/kernel/paging.rs - address translation and MMIO mappings in Limine's page tables
+//
+// Limine's HHDM only covers RAM and bootloader-owned ranges, so device registers
+// (HPET, local APIC, ...) usually have no mapping yet. map_mmio() adds an uncached
+// 4 KiB mapping at the HHDM address of the device page, taking any missing
+// page-table pages from a small static pool since there is no frame allocator.
+// map_framebuffer_wc() maps the framebuffer again, write-combining, in a window
+// of its own: on real hardware an uncached framebuffer is several times slower.
+use core::arch::asm;
+use crate::kernel::cpuid::{self, CpuFeature};
+use crate::kernel::serial::SERIAL_PORT;
+
+// Page table entry bits
+const PTE_PRESENT: u64 = 1 << 0;
+const PTE_WRITABLE: u64 = 1 << 1;
+const PTE_WRITE_THROUGH: u64 = 1 << 3;
+const PTE_CACHE_DISABLE: u64 = 1 << 4;
+const PTE_HUGE: u64 = 1 << 7;
+/// Same bit as PTE_HUGE, but in a 4 KiB PTE it selects the upper PAT half
+const PTE_PAT: u64 = 1 << 7;
+const PTE_ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+const PAGE_SIZE: u64 = 4096;
+/// Bytes one page table covers
+const PT_SPAN: u64 = 512 * PAGE_SIZE;
+
+/// Page-table pages available to map_mmio and map_framebuffer_wc; each new
+/// device region far from the others can need up to three (PDPT, PD, PT), and
+/// the framebuffer one PT per 2 MiB on top
+const TABLE_POOL_PAGES: usize = 24;
+
+// Page attribute table. A 4 KiB page's memory type is PAT entry
+// (PAT << 2 | PCD << 1 | PWT), each entry one byte of the IA32_PAT MSR.
+const IA32_PAT: u32 = 0x277;
+const PAT_TYPE_MASK: u64 = 0x07;
+const PAT_TYPE_WC: u64 = 0x01;
+/// Entry turned into WC if none is yet: UC at power-on, and left unspecified
+/// by the Limine protocol, so no existing mapping uses it
+const PAT_WC_FALLBACK_ENTRY: u32 = 7;
+
+/// Virtual window map_framebuffer_wc maps into (PML4 slot 448), clear of the
+/// HHDM at slot 256 up and the kernel in slot 511
+const FRAMEBUFFER_WINDOW: u64 = 0xFFFF_E000_0000_0000;
+
+#[repr(C, align(4096))]
+struct TablePage([u64; 512]);
+
+static mut TABLE_POOL: [TablePage; TABLE_POOL_PAGES] = [const { TablePage([0; 512]) }; TABLE_POOL_PAGES];
+static mut TABLE_POOL_USED: usize = 0;
+
+fn hhdm_offset() -> Option<u64> {
+    crate::HHDM_REQUEST.get_response().map(|r| r.offset())
+}
+
+fn pml4_phys() -> u64 {
+    let cr3: u64;
+    unsafe { asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack, preserves_flags)) };
+    cr3 & PTE_ADDR_MASK
+}
+
+/// Index into the table at `level` (3 = PML4 ... 0 = PT) for `virt`
+fn table_index(virt: u64, level: u32) -> usize {
+    ((virt >> (12 + 9 * level)) & 0x1FF) as usize
+}
+
+/// Physical address `virt` maps to, or None if it is not mapped
+pub unsafe fn translate(virt: u64) -> Option<u64> {
+    let hhdm = hhdm_offset()?;
+    let mut table = pml4_phys();
+    for level in (0..4).rev() {
+        let entry = ((table + hhdm) as *const u64).add(table_index(virt, level)).read_volatile();
+        if entry & PTE_PRESENT == 0 {
+            return None;
+        }
+        // 1 GiB / 2 MiB pages end the walk early
+        if level == 0 || (level < 3 && entry & PTE_HUGE != 0) {
+            let page_mask = (1u64 << (12 + 9 * level)) - 1;
+            return Some((entry & PTE_ADDR_MASK & !page_mask) | (virt & page_mask));
+        }
+        table = entry & PTE_ADDR_MASK;
+    }
+    None
+}
+
+/// Zeroed page from the pool, as its physical address
+unsafe fn alloc_table() -> Option<u64> {
+    if TABLE_POOL_USED >= TABLE_POOL_PAGES {
+        SERIAL_PORT.write_str("PAGING: page-table pool exhausted\n");
+        return None;
+    }
+    let page = &mut (*core::ptr::addr_of_mut!(TABLE_POOL))[TABLE_POOL_USED];
+    TABLE_POOL_USED += 1;
+    page.0 = [0; 512];
+    translate(page as *mut TablePage as u64)
+}
+
+/// Point the 4 KiB page at `virt` to `phys`, present and writable plus `flags`,
+/// creating any missing tables on the way
+unsafe fn map_page(virt: u64, phys: u64, flags: u64) -> Option<()> {
+    let hhdm = hhdm_offset()?;
+    let mut table = pml4_phys();
+    for level in (1..4).rev() {
+        let entry = ((table + hhdm) as *mut u64).add(table_index(virt, level));
+        let mut value = entry.read_volatile();
+        if value & PTE_PRESENT == 0 {
+            value = alloc_table()? | PTE_PRESENT | PTE_WRITABLE;
+            entry.write_volatile(value);
+        }
+        table = value & PTE_ADDR_MASK;
+    }
+
+    let pte = ((table + hhdm) as *mut u64).add(table_index(virt, 0));
+    pte.write_volatile((phys & PTE_ADDR_MASK) | PTE_PRESENT | PTE_WRITABLE | flags);
+    asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
+    Some(())
+}
+
+/// Map the 4 KiB page holding physical address `phys` uncached at its HHDM
+/// address and return the virtual address of `phys`. Pages the HHDM already
+/// covers are returned as they are.
+pub unsafe fn map_mmio(phys: u64) -> Option<u64> {
+    let hhdm = hhdm_offset()?;
+    let virt = hhdm + phys;
+    if translate(virt).is_some() {
+        return Some(virt);
+    }
+    map_page(virt, phys, PTE_WRITE_THROUGH | PTE_CACHE_DISABLE)?;
+    Some(virt)
+}
+
+unsafe fn rdmsr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags));
+    ((high as u64) << 32) | low as u64
+}
+
+unsafe fn wrmsr(msr: u32, value: u64) {
+    asm!("wrmsr", in("ecx") msr, in("eax") value as u32, in("edx") (value >> 32) as u32, options(nostack, preserves_flags));
+}
+
+/// PWT/PCD/PAT bits of a 4 KiB PTE that select PAT entry `entry`
+fn pat_entry_flags(entry: u32) -> u64 {
+    let mut flags = 0;
+    if entry & 1 != 0 {
+        flags |= PTE_WRITE_THROUGH;
+    }
+    if entry & 2 != 0 {
+        flags |= PTE_CACHE_DISABLE;
+    }
+    if entry & 4 != 0 {
+        flags |= PTE_PAT;
+    }
+    flags
+}
+
+/// PTE bits for a write-combining page, programming a PAT entry to WC first if
+/// none is. None if the CPU has no PAT.
+unsafe fn write_combining_flags() -> Option<u64> {
+    if !cpuid::has_feature(CpuFeature::Pat) {
+        return None;
+    }
+    let pat = rdmsr(IA32_PAT);
+    if let Some(entry) = (0..8).find(|&i| (pat >> (i * 8)) & PAT_TYPE_MASK == PAT_TYPE_WC) {
+        return Some(pat_entry_flags(entry));
+    }
+
+    let shift = PAT_WC_FALLBACK_ENTRY * 8;
+    let pat = (pat & !(0xFF << shift)) | (PAT_TYPE_WC << shift);
+    // The SDM asks for caches and TLBs to be flushed around a PAT change; with
+    // one CPU there are no other cores to do it on
+    asm!("wbinvd", options(nostack, preserves_flags));
+    wrmsr(IA32_PAT, pat);
+    asm!("wbinvd", "mov {0}, cr3", "mov cr3, {0}", out(reg) _, options(nostack, preserves_flags));
+    Some(pat_entry_flags(PAT_WC_FALLBACK_ENTRY))
+}
+
+/// Map the `size` byte framebuffer at physical `phys` write-combining, or
+/// uncached if the CPU has no PAT, and return the virtual address to draw at
+/// (`phys` itself need not be page aligned). Call at most once: every call maps
+/// at the same window. None if the page-table pool can't cover it, in which
+/// case nothing was mapped and Limine's mapping should be kept.
+pub unsafe fn map_framebuffer_wc(phys: u64, size: u64) -> Option<*mut u8> {
+    let offset = phys & (PAGE_SIZE - 1);
+    let pages = (offset + size).div_ceil(PAGE_SIZE);
+    // A PDPT and a PD, plus a PT per 2 MiB (one more if it straddles a boundary)
+    let tables_needed = 2 + (pages * PAGE_SIZE).div_ceil(PT_SPAN) as usize + 1;
+    if TABLE_POOL_USED + tables_needed > TABLE_POOL_PAGES || translate(FRAMEBUFFER_WINDOW).is_some() {
+        SERIAL_PORT.write_fmt(format_args!(
+            "PAGING: can't map {} KiB framebuffer at {:#x}\n", size / 1024, phys
+        ));
+        return None;
+    }
+
+    let (flags, kind) = match write_combining_flags() {
+        Some(flags) => (flags, "write-combining"),
+        None => (PTE_WRITE_THROUGH | PTE_CACHE_DISABLE, "uncached (no PAT)"),
+    };
+    let first_page = phys - offset;
+    for page in 0..pages {
+        map_page(FRAMEBUFFER_WINDOW + page * PAGE_SIZE, first_page + page * PAGE_SIZE, flags)?;
+    }
+    SERIAL_PORT.write_fmt(format_args!(
+        "PAGING: framebuffer {:#x} mapped {} at {:#x}, {} pages\n",
+        phys, kind, FRAMEBUFFER_WINDOW + offset, pages
+    ));
+    Some((FRAMEBUFFER_WINDOW + offset) as *mut u8)
+}
@@ -0,0 +1,219 @@
+// src/kernel/pci.rs - PCI device enumeration over the legacy config ports
+//
+// Configuration mechanism #1: write the bus/device/function/register address to
+// CONFIG_ADDRESS (0xCF8), then read the dword at CONFIG_DATA (0xCFC). Every
+// bus/device/function is probed; an absent function reads back vendor 0xFFFF.
+// Only the first 256 bytes of config space are reachable this way, which is
+// all the standard header needs.
+use crate::kernel::interrupts;
+use crate::kernel::io::{inl, outl};
+use crate::kernel::serial::SERIAL_PORT;
+
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+/// Bit 31 of CONFIG_ADDRESS turns the access into a config cycle
+const CONFIG_ENABLE: u32 = 1 << 31;
+
+// Standard header registers (dword offsets)
+const REG_ID: u8 = 0x00;
+const REG_CLASS: u8 = 0x08;
+const REG_HEADER: u8 = 0x0C;
+const REG_BAR0: u8 = 0x10;
+
+/// Vendor ID read back when nothing answers
+const VENDOR_NONE: u16 = 0xFFFF;
+/// Header type bit saying function 0 has siblings
+const HEADER_MULTI_FUNCTION: u8 = 1 << 7;
+const HEADER_TYPE_MASK: u8 = 0x7F;
+/// Header type 0 (endpoints) has 6 BARs, type 1 (PCI-to-PCI bridges) 2, others none
+const HEADER_TYPE_DEVICE: u8 = 0x00;
+const HEADER_TYPE_BRIDGE: u8 = 0x01;
+
+const DEVICES_PER_BUS: u8 = 32;
+const FUNCTIONS_PER_DEVICE: u8 = 8;
+
+// BAR bits
+const BAR_IO: u32 = 1 << 0;
+const BAR_MEM_TYPE_MASK: u32 = 0x6;
+const BAR_MEM_TYPE_64: u32 = 0x4;
+const BAR_PREFETCHABLE: u32 = 1 << 3;
+
+/// One PCI function as found by scan()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciDevice {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    /// Header layout, without the multi-function bit
+    pub header_type: u8,
+    /// Raw BAR registers; only the first bar_count() are meaningful
+    pub bars: [u32; 6],
+}
+
+/// A decoded base address register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    /// I/O port range starting at this port
+    Io(u16),
+    /// Memory range at this physical address
+    Memory { addr: u64, prefetchable: bool },
+}
+
+impl PciDevice {
+    /// Number of BAR registers the header type has
+    pub fn bar_count(&self) -> usize {
+        match self.header_type {
+            HEADER_TYPE_DEVICE => 6,
+            HEADER_TYPE_BRIDGE => 2,
+            _ => 0,
+        }
+    }
+
+    /// Decode BAR `index`. None if it is unused, out of range, or the upper
+    /// half of the 64-bit BAR before it.
+    pub fn bar(&self, index: usize) -> Option<Bar> {
+        if index >= self.bar_count() {
+            return None;
+        }
+        if index > 0 && self.is_64bit_memory_bar(index - 1) {
+            return None;
+        }
+        let raw = self.bars[index];
+        if raw & BAR_IO != 0 {
+            let port = (raw & !0x3) as u16;
+            return if port != 0 { Some(Bar::Io(port)) } else { None };
+        }
+        let mut addr = (raw & !0xF) as u64;
+        if self.is_64bit_memory_bar(index) && index + 1 < self.bar_count() {
+            addr |= (self.bars[index + 1] as u64) << 32;
+        }
+        if addr == 0 {
+            return None;
+        }
+        Some(Bar::Memory { addr, prefetchable: raw & BAR_PREFETCHABLE != 0 })
+    }
+
+    fn is_64bit_memory_bar(&self, index: usize) -> bool {
+        let raw = self.bars[index];
+        raw & BAR_IO == 0 && raw & BAR_MEM_TYPE_MASK == BAR_MEM_TYPE_64
+    }
+
+    /// Human-readable class, e.g. "SATA controller"
+    pub fn class_name(&self) -> &'static str {
+        class_name(self.class, self.subclass)
+    }
+}
+
+/// Read the config dword at `offset` (a multiple of 4) of one function
+pub unsafe fn read_config(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+    let address = CONFIG_ENABLE
+        | (bus as u32) << 16
+        | (device as u32) << 11
+        | (function as u32) << 8
+        | (offset & 0xFC) as u32;
+    // The address and data accesses must not be split by another config access
+    interrupts::without_interrupts(|| {
+        outl(CONFIG_ADDRESS, address);
+        inl(CONFIG_DATA)
+    })
+}
+
+/// Read one function's header, None if nothing answers there
+unsafe fn probe(bus: u8, device: u8, function: u8) -> Option<PciDevice> {
+    let id = read_config(bus, device, function, REG_ID);
+    let vendor_id = id as u16;
+    if vendor_id == VENDOR_NONE {
+        return None;
+    }
+    // Single-function devices may decode every function number as function 0
+    if function > 0 {
+        let header0 = (read_config(bus, device, 0, REG_HEADER) >> 16) as u8;
+        if header0 & HEADER_MULTI_FUNCTION == 0 {
+            return None;
+        }
+    }
+
+    let class = read_config(bus, device, function, REG_CLASS);
+    let header_type = (read_config(bus, device, function, REG_HEADER) >> 16) as u8 & HEADER_TYPE_MASK;
+    let mut dev = PciDevice {
+        bus,
+        device,
+        function,
+        vendor_id,
+        device_id: (id >> 16) as u16,
+        class: (class >> 24) as u8,
+        subclass: (class >> 16) as u8,
+        prog_if: (class >> 8) as u8,
+        header_type,
+        bars: [0; 6],
+    };
+    for i in 0..dev.bar_count() {
+        dev.bars[i] = read_config(bus, device, function, REG_BAR0 + 4 * i as u8);
+    }
+    Some(dev)
+}
+
+/// Every function present on every bus, in bus/device/function order
+pub fn scan() -> impl Iterator<Item = PciDevice> {
+    (0..=u8::MAX).flat_map(|bus| {
+        (0..DEVICES_PER_BUS).flat_map(move |device| {
+            (0..FUNCTIONS_PER_DEVICE).filter_map(move |function| unsafe { probe(bus, device, function) })
+        })
+    })
+}
+
+/// Name for a class/subclass pair, falling back to the class alone
+pub fn class_name(class: u8, subclass: u8) -> &'static str {
+    match (class, subclass) {
+        (0x01, 0x01) => "IDE controller",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "NVMe controller",
+        (0x01, _) => "storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, _) => "network controller",
+        (0x03, 0x00) => "VGA controller",
+        (0x03, _) => "display controller",
+        (0x04, _) => "multimedia controller",
+        (0x05, _) => "memory controller",
+        (0x06, 0x00) => "host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, _) => "bridge",
+        (0x07, _) => "communication controller",
+        (0x08, _) => "system peripheral",
+        (0x09, _) => "input controller",
+        (0x0C, 0x03) => "USB controller",
+        (0x0C, 0x05) => "SMBus controller",
+        (0x0C, _) => "serial bus controller",
+        (0x0D, _) => "wireless controller",
+        _ => "unknown device",
+    }
+}
+
+/// Log every device with its class name and BARs
+pub unsafe fn print_devices() {
+    let mut count = 0;
+    for dev in scan() {
+        SERIAL_PORT.write_fmt(format_args!(
+            "PCI: {:02x}:{:02x}.{} {:04x}:{:04x} {} ({:02x}:{:02x}:{:02x})\n",
+            dev.bus, dev.device, dev.function, dev.vendor_id, dev.device_id,
+            dev.class_name(), dev.class, dev.subclass, dev.prog_if
+        ));
+        for i in 0..dev.bar_count() {
+            match dev.bar(i) {
+                Some(Bar::Io(port)) => SERIAL_PORT.write_fmt(format_args!("       BAR{}: I/O {:#06x}\n", i, port)),
+                Some(Bar::Memory { addr, prefetchable }) => SERIAL_PORT.write_fmt(format_args!(
+                    "       BAR{}: memory {:#x}{}\n", i, addr, if prefetchable { " (prefetchable)" } else { "" }
+                )),
+                None => {}
+            }
+        }
+        count += 1;
+    }
+    SERIAL_PORT.write_fmt(format_args!("PCI: {} functions found\n", count));
+}
@@ -1,6 +1,8 @@
 // src/kernel/pic.rs - 64-bit PIC (Programmable Interrupt Controller) module
+use core::sync::atomic::{AtomicU8, Ordering};
 use crate::kernel::serial::SERIAL_PORT;
-use core::arch::asm;
+use crate::kernel::io::{inb, io_wait, outb};
+use crate::kernel::sched;
 
 // PIC ports (same addresses in 64-bit)
 pub const PIC1_COMMAND: u16 = 0x20;
@@ -8,35 +10,53 @@ pub const PIC1_DATA: u16 = 0x21;
 pub const PIC2_COMMAND: u16 = 0xA0;
 pub const PIC2_DATA: u16 = 0xA1;
 pub const PIC_EOI: u8 = 0x20;
+pub const PIC_READ_IRR: u8 = 0x0A; // OCW3: next read of the command port returns the IRR
+pub const PIC_READ_ISR: u8 = 0x0B; // OCW3: next read of the command port returns the ISR
 
-// I/O wait function using port 0x80 (same in 64-bit)
-unsafe fn io_wait() {
-    asm!(
-        "out 0x80, al", 
-        in("al") 0u8,
-        options(nostack, nomem)
-    );
-}
+/// Where init() puts IRQ0-7 and IRQ8-15: vectors 32-39 and 40-47, right after the exceptions
+pub const DEFAULT_MASTER_OFFSET: u8 = 0x20;
+pub const DEFAULT_SLAVE_OFFSET: u8 = 0x28;
+/// Vectors 0-31 belong to CPU exceptions
+const FIRST_FREE_VECTOR: u8 = 32;
+/// Software interrupts with gates of their own: int 0x80 system calls and the scheduler yield
+const RESERVED_VECTORS: [u8; 2] = [0x80, sched::SCHED_YIELD_VECTOR];
+
+/// Offsets the PICs were last programmed with, for mapping vectors back to IRQs
+static MASTER_OFFSET: AtomicU8 = AtomicU8::new(DEFAULT_MASTER_OFFSET);
+static SLAVE_OFFSET: AtomicU8 = AtomicU8::new(DEFAULT_SLAVE_OFFSET);
 
+/// Remap to the default offsets (IRQ0-15 -> vectors 32-47) with every IRQ masked
 pub unsafe fn init() {
+    init_with_offsets(DEFAULT_MASTER_OFFSET, DEFAULT_SLAVE_OFFSET);
+}
+
+/// Whether 8 IRQs starting at `offset` are a valid vector block clear of the
+/// exceptions and the reserved software vectors
+fn offset_ok(offset: u8) -> bool {
+    offset.is_multiple_of(8)
+        && offset >= FIRST_FREE_VECTOR
+        && !RESERVED_VECTORS.iter().any(|v| (offset..=offset + 7).contains(v))
+}
+
+/// Remap the master's IRQ0-7 to vectors master_offset.. and the slave's IRQ8-15
+/// to slave_offset.., with every IRQ masked. Both offsets must be multiples of 8
+/// (ICW2 ignores the low 3 bits), at least 32, clear of 0x80-0x81 and different.
+/// Returns false and leaves the PICs alone otherwise. The IDT must route the
+/// chosen vectors to the IRQ handlers; the dispatcher finds the IRQ number
+/// through irq_for_vector().
+pub unsafe fn init_with_offsets(master_offset: u8, slave_offset: u8) -> bool {
+    if !offset_ok(master_offset) || !offset_ok(slave_offset) || master_offset == slave_offset {
+        SERIAL_PORT.write_fmt(format_args!(
+            "64-bit PIC: invalid vector offsets {:#x}/{:#x} (need distinct multiples of 8, >= 0x20, not 0x80)\n",
+            master_offset, slave_offset
+        ));
+        return false;
+    }
+
     // Save current masks
-    let mask1: u8;
-    let mask2: u8;
-    
-    asm!(
-        "in al, dx", 
-        out("al") mask1, 
-        in("dx") PIC1_DATA,
-        options(nostack, nomem)
-    );
+    let mask1 = inb(PIC1_DATA);
     io_wait();
-    
-    asm!(
-        "in al, dx", 
-        out("al") mask2, 
-        in("dx") PIC2_DATA,
-        options(nostack, nomem)
-    );
+    let mask2 = inb(PIC2_DATA);
     io_wait();
     
     SERIAL_PORT.write_str("64-bit PIC: Saved masks - Master: 0x");
@@ -47,134 +67,83 @@ pub unsafe fn init() {
 
     // ICW1: Start initialization sequence
     // 0x11 = Edge-triggered, cascade mode, ICW4 needed
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_COMMAND, 
-        in("al") 0x11u8,
-        options(nostack, nomem)
-    );
+    outb(PIC1_COMMAND, 0x11);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_COMMAND, 
-        in("al") 0x11u8,
-        options(nostack, nomem)
-    );
+    outb(PIC2_COMMAND, 0x11);
     io_wait();
     
     // ICW2: Set vector offsets
-    // Master PIC: IRQ0-7 -> Interrupts 32-39
-    // Slave PIC: IRQ8-15 -> Interrupts 40-47
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_DATA, 
-        in("al") 0x20u8,
-        options(nostack, nomem)
-    );
+    // Master PIC: IRQ0-7 -> master_offset..master_offset+7
+    // Slave PIC: IRQ8-15 -> slave_offset..slave_offset+7
+    outb(PIC1_DATA, master_offset);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_DATA, 
-        in("al") 0x28u8,
-        options(nostack, nomem)
-    );
+    outb(PIC2_DATA, slave_offset);
     io_wait();
+    MASTER_OFFSET.store(master_offset, Ordering::Relaxed);
+    SLAVE_OFFSET.store(slave_offset, Ordering::Relaxed);
     
     // ICW3: Set up cascading
     // Master: bit 2 set (IRQ2 has slave)
     // Slave: cascade identity 2
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_DATA, 
-        in("al") 0x04u8,
-        options(nostack, nomem)
-    );
+    outb(PIC1_DATA, 0x04);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_DATA, 
-        in("al") 0x02u8,
-        options(nostack, nomem)
-    );
+    outb(PIC2_DATA, 0x02);
     io_wait();
     
     // ICW4: Set 8086 mode
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_DATA, 
-        in("al") 0x01u8,
-        options(nostack, nomem)
-    );
+    outb(PIC1_DATA, 0x01);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_DATA, 
-        in("al") 0x01u8,
-        options(nostack, nomem)
-    );
+    outb(PIC2_DATA, 0x01);
     io_wait();
     
     // Mask all interrupts initially
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_DATA, 
-        in("al") 0xFFu8,
-        options(nostack, nomem)
-    );
+    outb(PIC1_DATA, 0xFF);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_DATA, 
-        in("al") 0xFFu8,
-        options(nostack, nomem)
-    );
+    outb(PIC2_DATA, 0xFF);
     io_wait();
 
     // Clear any pending interrupts
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_COMMAND, 
-        in("al") PIC_EOI,
-        options(nostack, nomem)
-    );
+    outb(PIC1_COMMAND, PIC_EOI);
     io_wait();
     
-    asm!(
-        "out dx, al", 
-        in("dx") PIC2_COMMAND, 
-        in("al") PIC_EOI,
-        options(nostack, nomem)
-    );
+    outb(PIC2_COMMAND, PIC_EOI);
     io_wait();
 
     SERIAL_PORT.write_str("64-bit PIC initialized:\n");
-    SERIAL_PORT.write_str("  Master vector: 0x20 (32), Slave vector: 0x28 (40)\n");
+    SERIAL_PORT.write_fmt(format_args!(
+        "  Master vector: {:#x} ({}), Slave vector: {:#x} ({})\n",
+        master_offset, master_offset, slave_offset, slave_offset
+    ));
     SERIAL_PORT.write_str("  All IRQs masked initially\n");
+    true
+}
+
+/// The IRQ (0-15) the PICs deliver on `vector`, None if it is not one of theirs
+pub fn irq_for_vector(vector: u64) -> Option<u8> {
+    let master = MASTER_OFFSET.load(Ordering::Relaxed) as u64;
+    let slave = SLAVE_OFFSET.load(Ordering::Relaxed) as u64;
+    if (master..master + 8).contains(&vector) {
+        Some((vector - master) as u8)
+    } else if (slave..slave + 8).contains(&vector) {
+        Some((vector - slave) as u8 + 8)
+    } else {
+        None
+    }
 }
 
 pub unsafe fn send_eoi(irq: u8) {
     // Send EOI to slave PIC if IRQ came from slave (IRQ8-15)
     if irq >= 8 {
-        asm!(
-            "out dx, al", 
-            in("dx") PIC2_COMMAND, 
-            in("al") PIC_EOI,
-            options(nostack, nomem)
-        );
+        outb(PIC2_COMMAND, PIC_EOI);
     }
     
     // Always send EOI to master PIC
-    asm!(
-        "out dx, al", 
-        in("dx") PIC1_COMMAND, 
-        in("al") PIC_EOI,
-        options(nostack, nomem)
-    );
+    outb(PIC1_COMMAND, PIC_EOI);
 }
 
 /// Unmask (enable) a specific IRQ
@@ -182,22 +151,11 @@ pub unsafe fn unmask_irq(irq: u8) {
     let port = if irq < 8 { PIC1_DATA } else { PIC2_DATA };
     let irq_bit = if irq < 8 { irq } else { irq - 8 };
     
-    let mut mask: u8;
-    asm!(
-        "in al, dx", 
-        out("al") mask, 
-        in("dx") port,
-        options(nostack, nomem)
-    );
+    let mut mask = inb(port);
     
     mask &= !(1 << irq_bit);
     
-    asm!(
-        "out dx, al", 
-        in("dx") port, 
-        in("al") mask,
-        options(nostack, nomem)
-    );
+    outb(port, mask);
     
     SERIAL_PORT.write_str("64-bit PIC: Unmasked IRQ");
     SERIAL_PORT.write_decimal(irq as u32);
@@ -211,35 +169,74 @@ pub unsafe fn mask_irq(irq: u8) {
     let port = if irq < 8 { PIC1_DATA } else { PIC2_DATA };
     let irq_bit = if irq < 8 { irq } else { irq - 8 };
     
-    let mut mask: u8;
-    asm!(
-        "in al, dx", 
-        out("al") mask, 
-        in("dx") port,
-        options(nostack, nomem)
-    );
+    let mut mask = inb(port);
     
     mask |= 1 << irq_bit;
     
-    asm!(
-        "out dx, al", 
-        in("dx") port, 
-        in("al") mask,
-        options(nostack, nomem)
-    );
+    outb(port, mask);
 }
 
 /// Get current mask for a PIC (master = false, slave = true)
 pub unsafe fn get_mask(slave: bool) -> u8 {
     let port = if slave { PIC2_DATA } else { PIC1_DATA };
-    let mut mask: u8;
-    
-    asm!(
-        "in al, dx", 
-        out("al") mask, 
-        in("dx") port,
-        options(nostack, nomem)
-    );
-    
-    mask
-}
\ No newline at end of file
+    inb(port)
+}
+
+/// Issue an OCW3 read command to both PICs and combine the results
+/// Bits 0-7 come from the master, bits 8-15 from the slave
+unsafe fn read_irq_reg(ocw3: u8) -> u16 {
+    outb(PIC1_COMMAND, ocw3);
+    outb(PIC2_COMMAND, ocw3);
+
+    let master = inb(PIC1_COMMAND);
+    let slave = inb(PIC2_COMMAND);
+
+    ((slave as u16) << 8) | master as u16
+}
+
+/// Interrupt Request Register: IRQs raised but not yet acknowledged (pending)
+pub unsafe fn read_irr() -> u16 {
+    read_irq_reg(PIC_READ_IRR)
+}
+
+/// In-Service Register: IRQs acknowledged and awaiting EOI
+pub unsafe fn read_isr() -> u16 {
+    read_irq_reg(PIC_READ_ISR)
+}
+
+/// Check whether an IRQ is spurious by reading the In-Service Register.
+/// The PIC raises IRQ7/IRQ15 when a request disappears before it is acknowledged;
+/// a real interrupt has its ISR bit set, a spurious one does not.
+pub unsafe fn is_spurious(irq: u8) -> bool {
+    read_isr() & (1 << irq) == 0
+}
+
+/// Print masks, pending (IRR) and in-service (ISR) IRQs to serial
+pub unsafe fn dump_state() {
+    let mask = ((get_mask(true) as u16) << 8) | get_mask(false) as u16;
+    let irr = read_irr();
+    let isr = read_isr();
+
+    SERIAL_PORT.write_str("=== PIC STATE ===\n");
+    SERIAL_PORT.write_str("  Mask: 0x");
+    SERIAL_PORT.write_hex(mask as u32);
+    SERIAL_PORT.write_str(" IRR: 0x");
+    SERIAL_PORT.write_hex(irr as u32);
+    SERIAL_PORT.write_str(" ISR: 0x");
+    SERIAL_PORT.write_hex(isr as u32);
+    SERIAL_PORT.write_str("\n");
+
+    for irq in 0..16u8 {
+        let bit = 1u16 << irq;
+        if (irr | isr) & bit == 0 {
+            continue;
+        }
+        SERIAL_PORT.write_str("  IRQ");
+        SERIAL_PORT.write_decimal(irq as u32);
+        SERIAL_PORT.write_str(":");
+        if irr & bit != 0 { SERIAL_PORT.write_str(" pending"); }
+        if isr & bit != 0 { SERIAL_PORT.write_str(" in-service"); }
+        if mask & bit != 0 { SERIAL_PORT.write_str(" (masked)"); }
+        SERIAL_PORT.write_str("\n");
+    }
+}
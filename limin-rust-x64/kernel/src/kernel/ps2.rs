@@ -0,0 +1,150 @@
+// src/kernel/ps2.rs - 8042 PS/2 controller bring-up and configuration report
+//
+// The firmware and bootloader leave the controller however it suited them:
+// translation on or off, the aux port enabled, IRQs masked in the configuration
+// byte. init() logs what it found and puts it in a known state (keyboard port
+// and its IRQ on, translation matching the keyboard's scancode set, aux port
+// left for mouse::init) after checking the controller passes its self-test.
+use crate::kernel::io::{inb, outb};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::{interrupts, keyboard};
+
+// 8042 controller ports
+const PS2_DATA: u16 = 0x60;
+const PS2_STATUS: u16 = 0x64;    // read
+const PS2_COMMAND: u16 = 0x64;   // write
+
+// Status register bits
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+
+// Controller commands
+const CMD_READ_CONFIG: u8 = 0x20;
+const CMD_WRITE_CONFIG: u8 = 0x60;
+const CMD_DISABLE_AUX: u8 = 0xA7;
+const CMD_SELF_TEST: u8 = 0xAA;
+const CMD_DISABLE_KEYBOARD: u8 = 0xAD;
+const CMD_ENABLE_KEYBOARD: u8 = 0xAE;
+
+/// Self-test reply from a working controller
+const SELF_TEST_PASSED: u8 = 0x55;
+
+// Controller configuration byte bits
+const CONFIG_KEYBOARD_IRQ: u8 = 1 << 0;
+const CONFIG_AUX_IRQ: u8 = 1 << 1;
+const CONFIG_SYSTEM_FLAG: u8 = 1 << 2;
+const CONFIG_KEYBOARD_CLOCK_DISABLED: u8 = 1 << 4;
+const CONFIG_AUX_CLOCK_DISABLED: u8 = 1 << 5;
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+/// Status polls before giving up on the controller
+const PS2_TIMEOUT: u32 = 100_000;
+/// Stale bytes drained from the output buffer before giving up on emptying it
+const MAX_FLUSH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Error {
+    /// The controller didn't take a command or didn't answer in time
+    Timeout,
+    /// Self-test answered with this instead of 0x55
+    SelfTestFailed(u8),
+}
+
+/// Wait until the controller can accept a byte
+unsafe fn wait_write() -> Result<(), Ps2Error> {
+    for _ in 0..PS2_TIMEOUT {
+        if inb(PS2_STATUS) & STATUS_INPUT_FULL == 0 {
+            return Ok(());
+        }
+    }
+    Err(Ps2Error::Timeout)
+}
+
+/// Wait for a byte and read it
+unsafe fn read_data() -> Result<u8, Ps2Error> {
+    for _ in 0..PS2_TIMEOUT {
+        if inb(PS2_STATUS) & STATUS_OUTPUT_FULL != 0 {
+            return Ok(inb(PS2_DATA));
+        }
+    }
+    Err(Ps2Error::Timeout)
+}
+
+unsafe fn command(cmd: u8) -> Result<(), Ps2Error> {
+    wait_write()?;
+    outb(PS2_COMMAND, cmd);
+    Ok(())
+}
+
+/// Throw away bytes a device sent before its port was disabled
+unsafe fn flush_output() {
+    for _ in 0..MAX_FLUSH {
+        if inb(PS2_STATUS) & STATUS_OUTPUT_FULL == 0 {
+            return;
+        }
+        inb(PS2_DATA);
+    }
+}
+
+/// The controller configuration byte (command 0x20)
+pub unsafe fn read_config() -> Result<u8, Ps2Error> {
+    command(CMD_READ_CONFIG)?;
+    read_data()
+}
+
+/// Replace the controller configuration byte (command 0x60)
+pub unsafe fn write_config(config: u8) -> Result<(), Ps2Error> {
+    command(CMD_WRITE_CONFIG)?;
+    wait_write()?;
+    outb(PS2_DATA, config);
+    Ok(())
+}
+
+/// Log which ports, IRQs and translation `config` turns on
+pub unsafe fn log_config(label: &str, config: u8) {
+    let on = |bit: u8| if config & bit != 0 { "on" } else { "off" };
+    let enabled = |bit: u8| if config & bit == 0 { "on" } else { "off" };
+    SERIAL_PORT.write_fmt(format_args!(
+        "PS2: {} config {:#04x}: keyboard port {} (irq {}), aux port {} (irq {}), translation {}, POST passed {}\n",
+        label, config,
+        enabled(CONFIG_KEYBOARD_CLOCK_DISABLED), on(CONFIG_KEYBOARD_IRQ),
+        enabled(CONFIG_AUX_CLOCK_DISABLED), on(CONFIG_AUX_IRQ),
+        on(CONFIG_TRANSLATION), on(CONFIG_SYSTEM_FLAG)
+    ));
+}
+
+unsafe fn init_controller() -> Result<(), Ps2Error> {
+    // Keep both devices quiet while the configuration changes under them
+    command(CMD_DISABLE_KEYBOARD)?;
+    command(CMD_DISABLE_AUX)?;
+    flush_output();
+
+    let config = read_config()?;
+    log_config("found", config);
+
+    command(CMD_SELF_TEST)?;
+    let reply = read_data()?;
+    if reply != SELF_TEST_PASSED {
+        return Err(Ps2Error::SelfTestFailed(reply));
+    }
+
+    // Some controllers reset the configuration during self-test, so write it
+    // afterwards. The aux IRQ stays off until mouse::init wants it.
+    let mut config = (config | CONFIG_KEYBOARD_IRQ) & !(CONFIG_KEYBOARD_CLOCK_DISABLED | CONFIG_AUX_IRQ);
+    if keyboard::scancode_set() == 1 {
+        config |= CONFIG_TRANSLATION;
+    } else {
+        config &= !CONFIG_TRANSLATION;
+    }
+    write_config(config)?;
+    command(CMD_ENABLE_KEYBOARD)?;
+    log_config("now", read_config()?);
+    Ok(())
+}
+
+/// Report the controller state, self-test it and reset the configuration.
+/// Call before unmasking IRQ1 and before mouse::init, which re-enables the
+/// aux port. Runs with interrupts off so no handler reads the replies.
+pub unsafe fn init() -> Result<(), Ps2Error> {
+    interrupts::without_interrupts(|| init_controller())
+}
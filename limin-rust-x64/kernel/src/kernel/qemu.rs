@@ -0,0 +1,23 @@
+// src/kernel/qemu.rs - QEMU isa-debug-exit device
+// Requires QEMU to be started with: -device isa-debug-exit,iobase=0xf4,iosize=0x04
+use core::arch::asm;
+use crate::kernel::io::outl;
+
+pub const ISA_DEBUG_EXIT_PORT: u16 = 0xF4;
+
+/// Exit codes for automated runs: QEMU exits with status 33 / 35
+pub const EXIT_SUCCESS: u32 = 0x10;
+pub const EXIT_FAILURE: u32 = 0x11;
+
+/// Exit QEMU. The process exit status will be (code << 1) | 1.
+/// Falls back to halting if the debug-exit device is not present.
+pub fn qemu_exit(code: u32) -> ! {
+    unsafe {
+        outl(ISA_DEBUG_EXIT_PORT, code);
+
+        asm!("cli");
+        loop {
+            asm!("hlt", options(nomem, nostack, preserves_flags));
+        }
+    }
+}
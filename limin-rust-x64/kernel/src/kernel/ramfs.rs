@@ -0,0 +1,119 @@
+// src/kernel/ramfs.rs - flat in-memory filesystem for logs and scratch data
+//
+// There is no heap yet, so the file table is a fixed array of fixed-size slots
+// in .bss. One flat namespace, no directories: a "path" is just a file name.
+// Contents are lost at reboot.
+use crate::kernel::sync::SpinLock;
+
+/// Number of files that can exist at once
+pub const MAX_FILES: usize = 16;
+/// Longest file name, in bytes
+pub const MAX_NAME_LEN: usize = 32;
+/// Bytes each file can hold
+pub const FILE_CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamfsError {
+    /// Empty name, or longer than MAX_NAME_LEN
+    BadName,
+    /// A file with that name already exists
+    Exists,
+    /// All MAX_FILES slots are in use
+    NoFreeSlot,
+    /// The id doesn't refer to an existing file
+    BadId,
+    /// The file already holds FILE_CAPACITY bytes
+    FileFull,
+}
+
+/// Handle to a file, returned by create and open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileId(usize);
+
+struct File {
+    used: bool,
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    data: [u8; FILE_CAPACITY],
+    len: usize,
+}
+
+impl File {
+    const EMPTY: File = File { used: false, name: [0; MAX_NAME_LEN], name_len: 0, data: [0; FILE_CAPACITY], len: 0 };
+
+    fn name(&self) -> &str {
+        // Only ever filled from a &str in create
+        core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("")
+    }
+}
+
+/// Taken with lock_irqsave everywhere so an ISR may log into a file too
+static FILES: SpinLock<[File; MAX_FILES]> = SpinLock::new([const { File::EMPTY }; MAX_FILES]);
+
+/// Create an empty file called `path`
+pub fn create(path: &str) -> Result<FileId, RamfsError> {
+    if path.is_empty() || path.len() > MAX_NAME_LEN {
+        return Err(RamfsError::BadName);
+    }
+    let mut files = FILES.lock_irqsave();
+    if files.iter().any(|f| f.used && f.name() == path) {
+        return Err(RamfsError::Exists);
+    }
+    let (index, file) = files
+        .iter_mut()
+        .enumerate()
+        .find(|(_, f)| !f.used)
+        .ok_or(RamfsError::NoFreeSlot)?;
+    file.used = true;
+    file.name[..path.len()].copy_from_slice(path.as_bytes());
+    file.name_len = path.len();
+    file.len = 0;
+    Ok(FileId(index))
+}
+
+/// The file called `path`, if there is one
+pub fn open(path: &str) -> Option<FileId> {
+    let files = FILES.lock_irqsave();
+    files.iter().position(|f| f.used && f.name() == path).map(FileId)
+}
+
+/// Append `data` to the file. Returns how many bytes fit, which is less than
+/// `data.len()` when the file reaches FILE_CAPACITY.
+pub fn write(id: FileId, data: &[u8]) -> Result<usize, RamfsError> {
+    let mut files = FILES.lock_irqsave();
+    let file = files.get_mut(id.0).filter(|f| f.used).ok_or(RamfsError::BadId)?;
+    if !data.is_empty() && file.len == FILE_CAPACITY {
+        return Err(RamfsError::FileFull);
+    }
+    let count = data.len().min(FILE_CAPACITY - file.len);
+    file.data[file.len..file.len + count].copy_from_slice(&data[..count]);
+    file.len += count;
+    Ok(count)
+}
+
+/// Copy the start of the file into `buf`; returns the number of bytes copied
+/// (0 for a bad id)
+pub fn read(id: FileId, buf: &mut [u8]) -> usize {
+    let files = FILES.lock_irqsave();
+    let Some(file) = files.get(id.0).filter(|f| f.used) else {
+        return 0;
+    };
+    let count = buf.len().min(file.len);
+    buf[..count].copy_from_slice(&file.data[..count]);
+    count
+}
+
+/// Current size of the file in bytes, None for a bad id
+pub fn size(id: FileId) -> Option<usize> {
+    let files = FILES.lock_irqsave();
+    files.get(id.0).filter(|f| f.used).map(|f| f.len)
+}
+
+/// Call `visit` with the name and size of every file, in slot order. The table
+/// is locked meanwhile, so `visit` must not call back into ramfs.
+pub fn list(mut visit: impl FnMut(&str, usize)) {
+    let files = FILES.lock_irqsave();
+    for file in files.iter().filter(|f| f.used) {
+        visit(file.name(), file.len);
+    }
+}
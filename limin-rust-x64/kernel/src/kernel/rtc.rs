@@ -0,0 +1,150 @@
+// src/kernel/rtc.rs - wall-clock date and time from the CMOS real-time clock
+//
+// The RTC keeps counting while the machine is off. Its registers are read one
+// at a time through an index/data port pair, and may be mid-update (or in BCD,
+// or in 12-hour mode) depending on how the firmware set it up. Times are
+// whatever the firmware keeps, usually UTC on QEMU and local time on PCs
+// that also boot Windows.
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+use crate::kernel::io::{inb, outb};
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::{acpi, interrupts};
+
+const CMOS_INDEX: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+// CMOS registers
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+/// In 12-hour mode the hours register has this bit set for PM
+const HOUR_PM: u8 = 1 << 7;
+
+/// Offset of the CMOS century register index in the ACPI FADT
+const FADT_CENTURY_OFFSET: usize = 108;
+/// Century assumed when the FADT doesn't name a century register
+const DEFAULT_CENTURY: u16 = 20;
+/// Tries at getting two identical reads before settling for the last one
+const MAX_READ_ATTEMPTS: usize = 8;
+
+/// CMOS index of the century register, 0 if there is none. Set by init().
+static CENTURY_REGISTER: AtomicU8 = AtomicU8::new(0);
+
+/// A calendar date and time of day as kept by the RTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl fmt::Display for DateTime {
+    /// ISO 8601 style: 2024-03-09 14:05:00
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+unsafe fn read_register(reg: u8) -> u8 {
+    // Index and data accesses must not be split by an ISR touching the CMOS too
+    interrupts::without_interrupts(|| {
+        outb(CMOS_INDEX, reg);
+        inb(CMOS_DATA)
+    })
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value & 0x0F) + (value >> 4) * 10
+}
+
+/// Raw register values: seconds, minutes, hours, day, month, year, century
+type RawTime = [u8; 7];
+
+/// One snapshot of the time registers, taken once no update is in progress
+unsafe fn read_raw() -> RawTime {
+    while read_register(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+    let century_reg = CENTURY_REGISTER.load(Ordering::Relaxed);
+    [
+        read_register(REG_SECONDS),
+        read_register(REG_MINUTES),
+        read_register(REG_HOURS),
+        read_register(REG_DAY),
+        read_register(REG_MONTH),
+        read_register(REG_YEAR),
+        if century_reg != 0 { read_register(century_reg) } else { 0 },
+    ]
+}
+
+/// Look up the century register in the FADT. Call once during boot, after
+/// which now() uses it; without it years are taken to be 20xx.
+pub unsafe fn init() {
+    let Some(fadt) = acpi::find_table(b"FACP") else {
+        SERIAL_PORT.write_str("RTC: no FADT, assuming the 21st century\n");
+        return;
+    };
+    if ((*fadt).length as usize) <= FADT_CENTURY_OFFSET {
+        return;
+    }
+    let century_reg = *(fadt as *const u8).add(FADT_CENTURY_OFFSET);
+    CENTURY_REGISTER.store(century_reg, Ordering::Relaxed);
+    SERIAL_PORT.write_fmt(format_args!("RTC: {} (century register {:#x})\n", now(), century_reg));
+}
+
+/// The current date and time from the RTC
+pub fn now() -> DateTime {
+    // The UIP flag only promises about 244 us without an update, so read until
+    // two snapshots agree, which can't straddle a rollover
+    let mut raw = unsafe { read_raw() };
+    for _ in 0..MAX_READ_ATTEMPTS {
+        let again = unsafe { read_raw() };
+        if again == raw {
+            break;
+        }
+        raw = again;
+    }
+    let [mut second, mut minute, hour_reg, mut day, mut month, mut year, mut century] = raw;
+    let status_b = unsafe { read_register(REG_STATUS_B) };
+
+    // The PM flag stays put in BCD mode, so take it off before converting
+    let pm = status_b & STATUS_B_24_HOUR == 0 && hour_reg & HOUR_PM != 0;
+    let mut hour = hour_reg & !HOUR_PM;
+    if status_b & STATUS_B_BINARY == 0 {
+        second = bcd_to_binary(second);
+        minute = bcd_to_binary(minute);
+        hour = bcd_to_binary(hour);
+        day = bcd_to_binary(day);
+        month = bcd_to_binary(month);
+        year = bcd_to_binary(year);
+        century = bcd_to_binary(century);
+    }
+
+    // 12-hour mode counts 12, 1, ..., 11 for both halves of the day
+    if status_b & STATUS_B_24_HOUR == 0 {
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let century = if century != 0 { century as u16 } else { DEFAULT_CENTURY };
+    DateTime { year: century * 100 + year as u16, month, day, hour, minute, second }
+}
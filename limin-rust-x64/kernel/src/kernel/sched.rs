@@ -0,0 +1,197 @@
+// src/kernel/sched.rs - round-robin scheduler over a fixed set of kernel tasks
+//
+// Each task's context is a saved InterruptFrame. Switching always happens on the
+// way out of an interrupt: the timer ISR (preemption) or `int SCHED_YIELD_VECTOR`
+// (yield_now) hands its frame to switch_from_interrupt, which saves it for the
+// current task and overwrites it with the next task's, so the stub's register
+// pops and iretq resume the new task. When fpu saving is enabled the stub's
+// FXSAVE area is swapped the same way, so each task keeps its own x87/SSE state.
+use core::arch::asm;
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::kernel::fpu::FxsaveArea;
+use crate::kernel::interrupts::{self, InterruptFrame, RFLAGS_IF};
+use crate::kernel::serial::SERIAL_PORT;
+
+/// Maximum number of tasks, including the boot task in slot 0
+pub const MAX_TASKS: usize = 8;
+
+/// Timer ticks a task may run before preempt() switches away from it
+pub const TIME_SLICE_TICKS: u32 = 10;
+
+/// Software interrupt used by yield_now (stub isr129)
+pub const SCHED_YIELD_VECTOR: u8 = 0x81;
+
+/// RFLAGS for a new task: interrupts enabled + reserved bit 1
+const INITIAL_RFLAGS: u64 = RFLAGS_IF | 0x2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Unused,
+    Ready,
+}
+
+#[derive(Clone, Copy)]
+struct Task {
+    state: TaskState,
+    /// Registers and iretq frame to resume with; only valid while not running
+    context: InterruptFrame,
+    /// x87/SSE state to resume with; only used while fpu saving is enabled
+    fpu: FxsaveArea,
+    entry: Option<fn() -> !>,
+}
+
+impl Task {
+    const fn empty() -> Self {
+        Task { state: TaskState::Unused, context: InterruptFrame::zeroed(), fpu: FxsaveArea::initial(), entry: None }
+    }
+}
+
+// Slot 0 is whatever was running when the scheduler was first used (kmain);
+// its context is filled in the first time it is switched away from.
+static mut TASKS: [Task; MAX_TASKS] = {
+    let mut tasks = [Task::empty(); MAX_TASKS];
+    tasks[0].state = TaskState::Ready;
+    tasks
+};
+static mut CURRENT: usize = 0;
+static mut SPAWNED: usize = 0;
+
+static SLICE_LEFT: AtomicU32 = AtomicU32::new(TIME_SLICE_TICKS);
+
+/// First code a new task runs (iretq lands here)
+extern "C" fn task_trampoline() -> ! {
+    unsafe {
+        match TASKS[CURRENT].entry {
+            Some(entry) => entry(),
+            None => {
+                SERIAL_PORT.write_str("SCHED: task started without an entry point\n");
+                loop {
+                    asm!("hlt", options(nomem, nostack));
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// PUBLIC API
+// ============================================================================
+
+/// Create a task running `entry` on `stack`. Returns its slot, or None if the
+/// table is full or the stack is too small to be useful.
+pub unsafe fn spawn(entry: fn() -> !, stack: &'static mut [u8]) -> Option<usize> {
+    let slot = (1..MAX_TASKS).find(|&i| TASKS[i].state == TaskState::Unused)?;
+    if stack.len() < 128 {
+        return None;
+    }
+
+    // Start as if the trampoline had just been called: rsp is 8 mod 16
+    let top = (stack.as_mut_ptr().add(stack.len()) as u64) & !0xF;
+
+    let cs: u16;
+    let ss: u16;
+    asm!("mov {0:x}, cs", out(reg) cs, options(nomem, nostack, preserves_flags));
+    asm!("mov {0:x}, ss", out(reg) ss, options(nomem, nostack, preserves_flags));
+
+    let mut context = InterruptFrame::zeroed();
+    context.rip = task_trampoline as usize as u64;
+    context.cs = cs as u64;
+    context.rflags = INITIAL_RFLAGS;
+    context.rsp = top - 8;
+    context.ss = ss as u64;
+
+    // Don't let the timer see a half-written slot
+    interrupts::without_interrupts(|| unsafe {
+        TASKS[slot] = Task { state: TaskState::Ready, context, fpu: FxsaveArea::initial(), entry: Some(entry) };
+        SPAWNED += 1;
+    });
+
+    SERIAL_PORT.write_str("SCHED: spawned task ");
+    SERIAL_PORT.write_decimal(slot as u32);
+    SERIAL_PORT.write_str("\n");
+    Some(slot)
+}
+
+/// Give up the CPU to the next ready task (round-robin). Returns when this task is
+/// scheduled again; returns immediately if no other task is ready.
+pub unsafe fn yield_now() {
+    asm!("int {vector}", vector = const SCHED_YIELD_VECTOR);
+}
+
+/// Timer ISR hook, called after the EOI has been sent. Once the current task's
+/// slice is used up, switches tasks by rewriting `frame`. Does nothing until a
+/// task has been spawned.
+pub unsafe fn preempt(frame: *mut InterruptFrame, fpu: *mut FxsaveArea) {
+    if SPAWNED == 0 {
+        return;
+    }
+    if SLICE_LEFT.fetch_sub(1, Ordering::Relaxed) > 1 {
+        return;
+    }
+    switch_from_interrupt(frame, fpu);
+}
+
+/// Save `frame` as the current task's context and replace it with the next ready
+/// task's. Must only be called on a frame the interrupt stub is about to restore.
+/// `fpu` is the stub's FXSAVE area, or null when fpu saving is off.
+pub unsafe fn switch_from_interrupt(frame: *mut InterruptFrame, fpu: *mut FxsaveArea) {
+    SLICE_LEFT.store(TIME_SLICE_TICKS, Ordering::Relaxed);
+
+    let old = CURRENT;
+    let next = (1..=MAX_TASKS)
+        .map(|i| (old + i) % MAX_TASKS)
+        .find(|&i| TASKS[i].state == TaskState::Ready);
+
+    if let Some(next) = next.filter(|&next| next != old) {
+        TASKS[old].context = *frame;
+        *frame = TASKS[next].context;
+        if !fpu.is_null() {
+            TASKS[old].fpu = *fpu;
+            *fpu = TASKS[next].fpu;
+        }
+        CURRENT = next;
+    }
+}
+
+/// Index of the running task
+pub fn current_task() -> usize {
+    unsafe { CURRENT }
+}
+
+// ============================================================================
+// DEMO
+// ============================================================================
+
+/// Stack for each task started by spawn_demo
+const DEMO_STACK_SIZE: usize = 16 * 1024;
+/// Rounds a demo task reports before it goes quiet
+const DEMO_ROUNDS: u32 = 3;
+
+// One stack per non-boot slot; stacks are never reused since tasks never exit
+static mut DEMO_STACKS: [[u8; DEMO_STACK_SIZE]; MAX_TASKS - 1] = [[0; DEMO_STACK_SIZE]; MAX_TASKS - 1];
+static mut DEMO_STACKS_USED: usize = 0;
+
+/// Body of a demo task: report a few rounds, yielding after each, then keep
+/// handing the CPU straight back since there is nothing to exit to
+fn demo_task() -> ! {
+    let id = current_task();
+    for round in 1..=DEMO_ROUNDS {
+        unsafe {
+            SERIAL_PORT.write_fmt(format_args!("SCHED: task {} round {}/{}\n", id, round, DEMO_ROUNDS));
+            yield_now();
+        }
+    }
+    loop {
+        unsafe { yield_now() };
+    }
+}
+
+/// Spawn a demo task for the shell's `spawn` command. Returns its slot, or None
+/// once the task table or the demo stacks run out.
+pub unsafe fn spawn_demo() -> Option<usize> {
+    let stacks = &mut *core::ptr::addr_of_mut!(DEMO_STACKS);
+    let stack = stacks.get_mut(DEMO_STACKS_USED)?;
+    let slot = spawn(demo_task, stack)?;
+    DEMO_STACKS_USED += 1;
+    Some(slot)
+}
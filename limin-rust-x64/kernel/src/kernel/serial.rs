@@ -1,5 +1,6 @@
-use core::arch::asm;
 use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use super::io;
 // COM1 serial port base address
 const SERIAL_PORT_BASE: u16 = 0x3F8;
 
@@ -15,6 +16,22 @@ const LINE_STATUS_REG: u16 = 5;    // Line status register
 const TRANSMIT_EMPTY: u8 = 1 << 5;  // Transmitter holding register empty
 const DATA_READY: u8 = 1 << 0;      // Data ready
 
+// Interrupt enable register bits
+const IER_DATA_AVAILABLE: u8 = 1 << 0; // Received data available
+
+// Size of the COM1 receive ring buffer (power of two)
+const RX_BUFFER_SIZE: usize = 256;
+
+// Loopback test byte used by self_test
+const LOOPBACK_TEST_BYTE: u8 = 0xAE;
+
+/// Errors reported by the serial port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialError {
+    /// The byte sent in loopback mode did not come back
+    LoopbackMismatch,
+}
+
 pub struct SerialPort {
     base: u16,
 }
@@ -25,7 +42,8 @@ impl SerialPort {
     }
 
     /// Initialize the serial port
-    pub unsafe fn init(&self) {
+    /// Returns the loopback self-test result so callers can decide whether to keep using the port
+    pub unsafe fn init(&self) -> Result<(), SerialError> {
         // Disable interrupts
         self.outb(INT_ENABLE_REG, 0x00);
 
@@ -45,17 +63,31 @@ impl SerialPort {
         // IRQs enabled, RTS/DSR set
         self.outb(MODEM_CTRL_REG, 0x0B);
 
+        let result = self.self_test();
+        match result {
+            Ok(()) => self.write_str("Serial self-test passed\n"),
+            // Probably goes nowhere, but harmless if the chip is only half broken
+            Err(SerialError::LoopbackMismatch) => self.write_str("Serial self-test FAILED: loopback mismatch\n"),
+        }
+        result
+    }
+
+    /// Loopback check: send a byte with the chip in loopback mode and make sure it comes back.
+    /// The port is left in normal operation mode afterward whatever the outcome.
+    pub unsafe fn self_test(&self) -> Result<(), SerialError> {
         // Test serial chip (send byte 0xAE and check if serial returns same byte)
         self.outb(MODEM_CTRL_REG, 0x1E);
-        self.outb(DATA_REG, 0xAE);
+        self.outb(DATA_REG, LOOPBACK_TEST_BYTE);
 
-        // Check if serial is faulty
-        if self.inb(DATA_REG) != 0xAE {
-            // Serial is faulty, but we'll continue anyway
-        }
+        let echoed = self.inb(DATA_REG);
 
         // Set it in normal operation mode
         self.outb(MODEM_CTRL_REG, 0x0F);
+
+        if echoed != LOOPBACK_TEST_BYTE {
+            return Err(SerialError::LoopbackMismatch);
+        }
+        Ok(())
     }
 
     /// Write a byte to the serial port
@@ -74,6 +106,24 @@ impl SerialPort {
         }
     }
 
+    /// Send raw binary data byte for byte (framebuffer dumps, core images).
+    /// Unlike the text methods, nothing here will ever translate or filter bytes.
+    pub unsafe fn write_bytes(&self, data: &[u8]) {
+        for &byte in data {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Send a u32 as 4 raw little-endian bytes, for structured binary dumps
+    pub unsafe fn write_u32_le(&self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Send a u64 as 8 raw little-endian bytes
+    pub unsafe fn write_u64_le(&self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
 
 
     /// Write a formatted hex number (useful for debugging)
@@ -131,6 +181,38 @@ impl SerialPort {
         }
     }
 
+    /// Write a signed decimal number, with a leading '-' for negative values
+    pub unsafe fn write_decimal_signed(&self, value: i64) {
+        if value < 0 {
+            self.write_byte(b'-');
+        }
+        // unsigned_abs doesn't overflow on i64::MIN
+        let mut magnitude = value.unsigned_abs();
+        let mut digits = [0u8; 20]; // Max 20 digits for u64
+        let mut i = 0;
+
+        loop {
+            digits[i] = b'0' + (magnitude % 10) as u8;
+            magnitude /= 10;
+            i += 1;
+            if magnitude == 0 {
+                break;
+            }
+        }
+
+        while i > 0 {
+            i -= 1;
+            self.write_byte(digits[i]);
+        }
+    }
+
+    /// Enable the "received data available" interrupt (IRQ4 for COM1).
+    /// OUT2 in the modem control register, needed to route the IRQ, is already set by init.
+    pub unsafe fn enable_rx_interrupt(&self) {
+        self.outb(INT_ENABLE_REG, IER_DATA_AVAILABLE);
+        RX_INTERRUPTS_ENABLED.store(true, Ordering::Release);
+    }
+
     /// Read a byte from the serial port (if available)
     pub unsafe fn read_byte(&self) -> Option<u8> {
         if (self.inb(LINE_STATUS_REG) & DATA_READY) != 0 {
@@ -140,19 +222,39 @@ impl SerialPort {
         }
     }
 
+    /// Wait for one byte and return it as received: no line buffering, no
+    /// editing, so Enter arrives as b'\r'. With `echo` the byte is sent back
+    /// unchanged. For yes/no prompts and menus that shouldn't wait for Enter.
+    ///
+    /// Once IRQ4 is enabled the handler drains the UART into RX_RING, so polling
+    /// the data register would race it for every byte; read the ring instead.
+    pub unsafe fn read_char_blocking(&self, echo: bool) -> u8 {
+        let byte = loop {
+            let next = if RX_INTERRUPTS_ENABLED.load(Ordering::Acquire) {
+                serial_read_char()
+            } else {
+                self.read_byte()
+            };
+            if let Some(byte) = next {
+                break byte;
+            }
+            core::hint::spin_loop();
+        };
+        if echo {
+            self.write_byte(byte);
+        }
+        byte
+    }
+
     /// Low-level port I/O functions
     #[inline]
     unsafe fn outb(&self, reg: u16, value: u8) {
-        let port = self.base + reg;
-        asm!("out dx, al", in("dx") port, in("al") value, options(nostack, nomem));
+        io::outb(self.base + reg, value);
     }
 
     #[inline]
     unsafe fn inb(&self, reg: u16) -> u8 {
-        let port = self.base + reg;
-        let value: u8;
-        asm!("in al, dx", in("dx") port, out("al") value, options(nostack, nomem));
-        value
+        io::inb(self.base + reg)
     }
 
     /// Write formatted data (supports `format_args!`)
@@ -180,6 +282,72 @@ impl SerialPort {
 // Global serial port instance
 pub static SERIAL_PORT: SerialPort = SerialPort::new(SERIAL_PORT_BASE);
 
+// ============================================================================
+// INTERRUPT-DRIVEN RECEIVE
+// ============================================================================
+
+/// Single-producer (IRQ handler) / single-consumer ring buffer.
+/// `head` is only written by push, `tail` only by pop, so no lock is needed.
+struct RxRing {
+    buf: [AtomicU8; RX_BUFFER_SIZE],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+impl RxRing {
+    const fn new() -> Self {
+        Self {
+            buf: [const { AtomicU8::new(0) }; RX_BUFFER_SIZE],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Append a byte; returns false (dropping it) when the buffer is full
+    fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % RX_BUFFER_SIZE;
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+        self.buf[head].store(byte, Ordering::Relaxed);
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Take the oldest byte, if any
+    fn pop(&self) -> Option<u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        let byte = self.buf[tail].load(Ordering::Relaxed);
+        self.tail.store((tail + 1) % RX_BUFFER_SIZE, Ordering::Release);
+        Some(byte)
+    }
+}
+
+static RX_RING: RxRing = RxRing::new();
+
+/// Set by enable_rx_interrupt: from then on received bytes arrive via RX_RING
+static RX_INTERRUPTS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// COM1 receive interrupt handler: drain the FIFO into the ring buffer.
+/// Reading the data register until LINE_STATUS reports no more data clears the
+/// interrupt, otherwise it would fire again as soon as it is acknowledged.
+pub unsafe fn handle_rx_interrupt() {
+    while (SERIAL_PORT.inb(LINE_STATUS_REG) & DATA_READY) != 0 {
+        let byte = SERIAL_PORT.inb(DATA_REG);
+        // Buffer full: drop the byte, the FIFO must still be drained
+        let _ = RX_RING.push(byte);
+    }
+}
+
+/// Pop the next received byte (filled by handle_rx_interrupt)
+pub fn serial_read_char() -> Option<u8> {
+    RX_RING.pop()
+}
+
 // Convenience macros for logging
 #[macro_export]
 macro_rules! serial_print {
@@ -0,0 +1,207 @@
+// src/kernel/shell.rs - interactive debug shell over the serial port
+use core::hint::spin_loop;
+use crate::kernel::serial::{serial_read_char, SERIAL_PORT};
+use crate::kernel::{hpet, interrupts, keyboard, pci, ramfs, rtc, sched, timer, usermode};
+use limine::memory_map::EntryType;
+
+/// Longest command line accepted; extra characters are ignored
+const MAX_LINE: usize = 128;
+
+const PROMPT: &str = "kshell> ";
+
+const HELP: &str = "Commands:\n\
+    \x20 help               this list\n\
+    \x20 ticks              timer ticks since boot\n\
+    \x20 now                nanoseconds since boot (HPET, else PIT)\n\
+    \x20 date               wall-clock date and time (RTC)\n\
+    \x20 mem                memory map summary\n\
+    \x20 ints               per-vector interrupt counts\n\
+    \x20 lspci              list PCI devices\n\
+    \x20 gpftest            raise a #GP and check its error code\n\
+    \x20 peek <addr>        read a byte (hex address)\n\
+    \x20 poke <addr> <val>  write a byte (hex address and value)\n\
+    \x20 ls                 list ramfs files\n\
+    \x20 cat <file>         print a ramfs file\n\
+    \x20 write <file> <txt> append a line to a ramfs file (created if missing)\n\
+    \x20 user               run the ring 3 demo (exits via sys_exit)\n\
+    \x20 spawn              start a demo task and yield to it\n";
+
+/// Read one line from serial into `buf`, echoing input and handling backspace.
+/// Ends at CR or LF (not stored). Returns the number of bytes in `buf`.
+pub unsafe fn read_line(buf: &mut [u8]) -> usize {
+    let mut len = 0;
+    loop {
+        let byte = match serial_read_char() {
+            Some(byte) => byte,
+            None => {
+                keyboard::sync_leds();
+                spin_loop();
+                continue;
+            }
+        };
+
+        match byte {
+            b'\r' | b'\n' => {
+                SERIAL_PORT.write_str("\n");
+                return len;
+            }
+            // Backspace / DEL: erase the last character on the terminal too
+            0x08 | 0x7F => {
+                if len > 0 {
+                    len -= 1;
+                    SERIAL_PORT.write_str("\x08 \x08");
+                }
+            }
+            0x20..=0x7E if len < buf.len() => {
+                buf[len] = byte;
+                len += 1;
+                SERIAL_PORT.write_byte(byte);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse a hex number, with or without a 0x prefix
+fn parse_hex(s: &str) -> Option<u64> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for c in digits.bytes() {
+        let digit = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => return None,
+        };
+        value = (value << 4) | digit as u64;
+    }
+    Some(value)
+}
+
+/// Print the prompt, read a command, run it; forever
+pub unsafe fn run() -> ! {
+    SERIAL_PORT.write_str("\nSerial shell ready, type 'help' for commands\n");
+    let mut buf = [0u8; MAX_LINE];
+    loop {
+        SERIAL_PORT.write_str(PROMPT);
+        let len = read_line(&mut buf);
+        // read_line only stores printable ASCII
+        let line = core::str::from_utf8(&buf[..len]).unwrap_or("");
+        execute(line);
+    }
+}
+
+/// Run a single command line
+pub unsafe fn execute(line: &str) {
+    let mut args = line.split_whitespace();
+    let Some(command) = args.next() else {
+        return;
+    };
+
+    match command {
+        "help" => SERIAL_PORT.write_str(HELP),
+        "ticks" => {
+            SERIAL_PORT.write_fmt(format_args!("{} ticks ({} ms)\n", timer::get_ticks(), timer::get_uptime_ms()));
+        }
+        "now" => {
+            let source = if hpet::is_available() { "HPET" } else { "PIT" };
+            SERIAL_PORT.write_fmt(format_args!("{} ns ({})\n", hpet::hpet_now_ns(), source));
+        }
+        "date" => SERIAL_PORT.write_fmt(format_args!("{}\n", rtc::now())),
+        "mem" => print_memory_summary(),
+        "ints" => interrupts::dump_interrupt_stats(),
+        "lspci" => pci::print_devices(),
+        "gpftest" => {
+            interrupts::test_gpf_error_code();
+        }
+        "peek" => match args.next().and_then(parse_hex) {
+            Some(addr) => {
+                let value = core::ptr::read_volatile(addr as *const u8);
+                SERIAL_PORT.write_fmt(format_args!("[{:#x}] = {:#04x}\n", addr, value));
+            }
+            None => SERIAL_PORT.write_str("usage: peek <hex addr>\n"),
+        },
+        "poke" => match (args.next().and_then(parse_hex), args.next().and_then(parse_hex)) {
+            (Some(addr), Some(value)) if value <= 0xFF => {
+                core::ptr::write_volatile(addr as *mut u8, value as u8);
+                SERIAL_PORT.write_fmt(format_args!("[{:#x}] <- {:#04x}\n", addr, value));
+            }
+            _ => SERIAL_PORT.write_str("usage: poke <hex addr> <hex byte>\n"),
+        },
+        "ls" => ramfs::list(|name, size| SERIAL_PORT.write_fmt(format_args!("{:>6}  {}\n", size, name))),
+        "cat" => match args.next().and_then(ramfs::open) {
+            Some(id) => {
+                let mut buf = [0u8; ramfs::FILE_CAPACITY];
+                let len = ramfs::read(id, &mut buf);
+                SERIAL_PORT.write_bytes(&buf[..len]);
+            }
+            None => SERIAL_PORT.write_str("usage: cat <existing file>\n"),
+        },
+        "write" => match args.next() {
+            Some(name) => write_line_to_file(name, args),
+            None => SERIAL_PORT.write_str("usage: write <file> <text>\n"),
+        },
+        "user" => usermode::run_demo(),
+        "spawn" => match sched::spawn_demo() {
+            // its first round runs before the next prompt
+            Some(_) => sched::yield_now(),
+            None => SERIAL_PORT.write_str("spawn: no free task slot or demo stack\n"),
+        },
+        _ => {
+            SERIAL_PORT.write_str("unknown command: ");
+            SERIAL_PORT.write_str(command);
+            SERIAL_PORT.write_str(" (try 'help')\n");
+        }
+    }
+}
+
+/// Append the remaining words, space-separated, plus a newline to `name`
+unsafe fn write_line_to_file<'a>(name: &str, words: impl Iterator<Item = &'a str>) {
+    let id = match ramfs::open(name).map_or_else(|| ramfs::create(name), Ok) {
+        Ok(id) => id,
+        Err(e) => {
+            SERIAL_PORT.write_fmt(format_args!("write: {}: {:?}\n", name, e));
+            return;
+        }
+    };
+    let mut result = Ok(0);
+    for (i, word) in words.enumerate() {
+        if i > 0 {
+            result = result.and(ramfs::write(id, b" "));
+        }
+        result = result.and(ramfs::write(id, word.as_bytes()));
+    }
+    if let Err(e) = result.and(ramfs::write(id, b"\n")) {
+        SERIAL_PORT.write_fmt(format_args!("write: {}: {:?}\n", name, e));
+    }
+}
+
+/// Entry count and usable/reserved totals from the Limine memory map
+unsafe fn print_memory_summary() {
+    let Some(response) = crate::MEMMAP_REQUEST.get_response() else {
+        SERIAL_PORT.write_str("no memory map from bootloader\n");
+        return;
+    };
+
+    let mut usable: u64 = 0;
+    let mut reclaimable: u64 = 0;
+    let mut other: u64 = 0;
+    for entry in response.entries() {
+        match entry.entry_type {
+            EntryType::USABLE => usable += entry.length,
+            EntryType::BOOTLOADER_RECLAIMABLE | EntryType::ACPI_RECLAIMABLE => reclaimable += entry.length,
+            _ => other += entry.length,
+        }
+    }
+
+    SERIAL_PORT.write_fmt(format_args!(
+        "{} regions: {} MiB usable, {} MiB reclaimable, {} MiB other\n",
+        response.entries().len(),
+        usable / (1024 * 1024),
+        reclaimable / (1024 * 1024),
+        other / (1024 * 1024),
+    ));
+}
@@ -0,0 +1,142 @@
+// src/kernel/symbols.rs - kernel symbol lookup for backtraces
+//
+// Limine hands over the kernel ELF file as loaded from disk. Its section header
+// table locates .symtab and the .strtab linked from it. Neither is part of a
+// loaded segment, so both are read from the file image at sh_offset, not sh_addr.
+// Needs an unstripped kernel; without symbols, callers fall back to raw addresses.
+// The file sits in bootloader-reclaimable memory, which must stay unreclaimed.
+use crate::kernel::serial::SERIAL_PORT;
+
+// ELF header fields (ELF64)
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const E_SHOFF: usize = 0x28;
+const E_SHENTSIZE: usize = 0x3A;
+const E_SHNUM: usize = 0x3C;
+const ELF64_HEADER_SIZE: usize = 64;
+
+// Section header fields (ELF64)
+const SH_TYPE: usize = 4;
+const SH_OFFSET: usize = 24;
+const SH_SIZE: usize = 32;
+const SH_LINK: usize = 40;
+const SH_ENTSIZE: usize = 56;
+const ELF64_SHDR_SIZE: usize = 64;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+
+// Symbol fields (Elf64_Sym)
+const ST_NAME: usize = 0;
+const ST_INFO: usize = 4;
+const ST_VALUE: usize = 8;
+const ST_SIZE: usize = 16;
+const ELF64_SYM_SIZE: usize = 24;
+const STT_FUNC: u8 = 2;
+
+struct SymbolTable {
+    symtab: &'static [u8],
+    strtab: &'static [u8],
+    /// Size of one symbol entry, from the symtab's sh_entsize
+    entsize: usize,
+}
+
+// Set once by init() during boot, read-only afterwards (including from the panic handler)
+static mut SYMBOLS: Option<SymbolTable> = None;
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Bytes [offset, offset + size) of the file, if they are inside it
+fn file_range(elf: &[u8], offset: u64, size: u64) -> Option<&[u8]> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    elf.get(start..end)
+}
+
+/// Find .symtab and its string table in an ELF64 file image
+fn parse_symbol_table(elf: &'static [u8]) -> Option<SymbolTable> {
+    if elf.len() < ELF64_HEADER_SIZE || elf[..4] != ELF_MAGIC || elf[4] != ELFCLASS64 {
+        return None;
+    }
+    let shoff = read_u64(elf, E_SHOFF)?;
+    let shentsize = read_u16(elf, E_SHENTSIZE)? as u64;
+    let shnum = read_u16(elf, E_SHNUM)? as u64;
+    if (shentsize as usize) < ELF64_SHDR_SIZE {
+        return None;
+    }
+    let section = |index: u64| file_range(elf, shoff + index * shentsize, shentsize);
+
+    let symtab_hdr = (0..shnum)
+        .filter_map(section)
+        .find(|hdr| read_u32(hdr, SH_TYPE) == Some(SHT_SYMTAB))?;
+    let strtab_hdr = section(read_u32(symtab_hdr, SH_LINK)? as u64)?;
+    if read_u32(strtab_hdr, SH_TYPE)? != SHT_STRTAB {
+        return None;
+    }
+
+    let entsize = read_u64(symtab_hdr, SH_ENTSIZE)? as usize;
+    if entsize < ELF64_SYM_SIZE {
+        return None;
+    }
+    Some(SymbolTable {
+        symtab: file_range(elf, read_u64(symtab_hdr, SH_OFFSET)?, read_u64(symtab_hdr, SH_SIZE)?)?,
+        strtab: file_range(elf, read_u64(strtab_hdr, SH_OFFSET)?, read_u64(strtab_hdr, SH_SIZE)?)?,
+        entsize,
+    })
+}
+
+/// Load the symbol table from the kernel file Limine passed us.
+/// Call once during boot; until then symbolize() returns None.
+pub unsafe fn init() {
+    let Some(response) = crate::EXECUTABLE_FILE_REQUEST.get_response() else {
+        SERIAL_PORT.write_str("SYMBOLS: no kernel file from bootloader\n");
+        return;
+    };
+    let file = response.file();
+    let elf = core::slice::from_raw_parts(file.addr() as *const u8, file.size() as usize);
+
+    match parse_symbol_table(elf) {
+        Some(table) => {
+            SERIAL_PORT.write_fmt(format_args!(
+                "SYMBOLS: {} symbol table entries\n",
+                table.symtab.len() / table.entsize
+            ));
+            SYMBOLS = Some(table);
+        }
+        None => SERIAL_PORT.write_str("SYMBOLS: kernel has no symbol table, backtraces show raw addresses\n"),
+    }
+}
+
+/// Name of the function containing `addr` and the offset of `addr` into it.
+/// Searches the symbol table linearly, only looking at function symbols.
+pub fn symbolize(addr: u64) -> Option<(&'static str, u64)> {
+    let table = unsafe { (*core::ptr::addr_of!(SYMBOLS)).as_ref()? };
+
+    for sym in table.symtab.chunks_exact(table.entsize) {
+        if sym[ST_INFO] & 0xF != STT_FUNC {
+            continue;
+        }
+        let value = read_u64(sym, ST_VALUE)?;
+        let size = read_u64(sym, ST_SIZE)?;
+        if addr < value || addr - value >= size {
+            continue;
+        }
+
+        let name_start = read_u32(sym, ST_NAME)? as usize;
+        let name_bytes = table.strtab.get(name_start..)?;
+        let len = name_bytes.iter().position(|&b| b == 0).unwrap_or(name_bytes.len());
+        let name = core::str::from_utf8(&name_bytes[..len]).ok()?;
+        return Some((name, addr - value));
+    }
+    None
+}
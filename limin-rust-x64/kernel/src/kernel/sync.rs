@@ -0,0 +1,110 @@
+// src/kernel/sync.rs - spinlock for state shared between ISRs and the main loop
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::kernel::interrupts;
+
+/// Test-and-set spinlock. No heap, usable in statics.
+///
+/// If an ISR takes the lock, code outside interrupt context must use
+/// `lock_irqsave`, otherwise the ISR can spin forever on a lock held by the
+/// code it interrupted.
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    data: UnsafeCell<T>,
+}
+
+// The lock serializes all access to `data`
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+unsafe impl<T: Send> Send for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        SpinLock { locked: AtomicBool::new(false), data: UnsafeCell::new(value) }
+    }
+
+    /// Spin until the lock is free, then take it
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            // Wait on a plain load so we don't hammer the cache line with writes
+            while self.locked.load(Ordering::Relaxed) {
+                spin_loop();
+            }
+        }
+    }
+
+    /// Take the lock if it is free right now
+    pub fn try_lock(&self) -> Option<SpinLockGuard<'_, T>> {
+        if self.locked.swap(true, Ordering::Acquire) {
+            None
+        } else {
+            Some(SpinLockGuard { lock: self })
+        }
+    }
+
+    /// Disable interrupts, then take the lock. The previous interrupt state is
+    /// restored after the lock is released when the guard is dropped.
+    pub fn lock_irqsave(&self) -> IrqSpinLockGuard<'_, T> {
+        let interrupts_enabled = interrupts::save_and_disable();
+        IrqSpinLockGuard {
+            guard: Some(self.lock()),
+            interrupts_enabled,
+        }
+    }
+}
+
+/// Holds a SpinLock; releases it on drop
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+/// Guard from `lock_irqsave`: releases the lock, then re-enables interrupts if
+/// they were enabled when the lock was taken
+pub struct IrqSpinLockGuard<'a, T> {
+    guard: Option<SpinLockGuard<'a, T>>,
+    interrupts_enabled: bool,
+}
+
+impl<T> Deref for IrqSpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for IrqSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for IrqSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // Unlock first so a pending interrupt can take the lock straight away
+        drop(self.guard.take());
+        interrupts::restore(self.interrupts_enabled);
+    }
+}
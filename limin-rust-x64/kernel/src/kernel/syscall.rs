@@ -0,0 +1,82 @@
+// src/kernel/syscall.rs - int 0x80 system call dispatch table
+use crate::kernel::serial::SERIAL_PORT;
+use crate::kernel::qemu::qemu_exit;
+use crate::kernel::usermode;
+
+/// System call handler: receives rdi, rsi, rdx and returns the value placed in rax
+pub type SyscallFn = fn(a1: u64, a2: u64, a3: u64) -> u64;
+
+/// Number of slots in the dispatch table
+pub const MAX_SYSCALLS: usize = 64;
+
+/// Returned in rax for unknown or failed system calls
+pub const SYSCALL_ERROR: u64 = u64::MAX;
+
+/// Largest buffer one sys_write accepts
+pub const MAX_WRITE_LEN: u64 = 4096;
+
+// System call numbers
+pub const SYS_EXIT: u64 = 0;
+pub const SYS_WRITE: u64 = 1;
+
+static mut SYSCALL_TABLE: [Option<SyscallFn>; MAX_SYSCALLS] = [None; MAX_SYSCALLS];
+
+/// Register the built-in system calls
+pub unsafe fn init() {
+    register_syscall(SYS_EXIT, sys_exit);
+    register_syscall(SYS_WRITE, sys_write);
+
+    SERIAL_PORT.write_str("Syscall table initialized (");
+    SERIAL_PORT.write_decimal(MAX_SYSCALLS as u32);
+    SERIAL_PORT.write_str(" slots)\n");
+}
+
+/// Install a handler for system call `num`. Returns false if `num` is out of range.
+pub unsafe fn register_syscall(num: u64, f: SyscallFn) -> bool {
+    if num as usize >= MAX_SYSCALLS {
+        return false;
+    }
+    SYSCALL_TABLE[num as usize] = Some(f);
+    true
+}
+
+/// Look up and call the handler for `num`, returning SYSCALL_ERROR if none is registered
+pub unsafe fn dispatch(num: u64, a1: u64, a2: u64, a3: u64) -> u64 {
+    if num as usize >= MAX_SYSCALLS {
+        return SYSCALL_ERROR;
+    }
+    match SYSCALL_TABLE[num as usize] {
+        Some(handler) => handler(a1, a2, a3),
+        None => SYSCALL_ERROR,
+    }
+}
+
+// ============================================================================
+// BUILT-IN SYSTEM CALLS
+// ============================================================================
+
+/// sys_exit(code) - leave QEMU with the given exit code
+fn sys_exit(code: u64, _a2: u64, _a3: u64) -> u64 {
+    unsafe { SERIAL_PORT.write_fmt(format_args!("sys_exit({})\n", code)) };
+    qemu_exit(code as u32);
+}
+
+/// sys_write(fd, buf, len) - write `len` bytes at `buf` to the serial port.
+/// The buffer comes from ring 3: fails unless all of it is user-accessible, so a
+/// caller can't make the kernel read its own memory or an unmapped page.
+fn sys_write(_fd: u64, buf: u64, len: u64) -> u64 {
+    if buf == 0 || len > MAX_WRITE_LEN {
+        return SYSCALL_ERROR;
+    }
+    unsafe {
+        // Also rejects buf + len overflowing
+        if !usermode::is_user_range(buf, len) {
+            return SYSCALL_ERROR;
+        }
+        let bytes = core::slice::from_raw_parts(buf as *const u8, len as usize);
+        for &byte in bytes {
+            SERIAL_PORT.write_byte(byte);
+        }
+    }
+    len
+}
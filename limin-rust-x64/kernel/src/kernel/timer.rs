@@ -1,20 +1,36 @@
 // src/kernel/timer.rs - 64-bit timer implementation
 use crate::kernel::serial::SERIAL_PORT;
-use crate::kernel::interrupts::TIMER_TICKS;
+use crate::kernel::interrupts::get_timer_ticks;
+use crate::kernel::cpuid::{self, CpuFeature};
+use crate::kernel::sync::SpinLock;
 use core::arch::asm;
+use crate::kernel::io::{inb, outb};
+
+/// PIT base frequency is ~1.193182 MHz (same in 64-bit)
+pub const PIT_BASE_FREQUENCY: u32 = 1_193_182;
+/// Slowest rate the 16-bit divisor can produce (1193182 / 65535 ~= 18.2Hz)
+pub const PIT_MIN_FREQUENCY: u32 = 18;
+/// Fastest rate: divisor of 1
+pub const PIT_MAX_FREQUENCY: u32 = PIT_BASE_FREQUENCY;
+
+/// Tick rate programmed by init(); 0 until the PIT has been set up
+static mut TIMER_FREQUENCY: u32 = 0;
 
 pub unsafe fn init(freq_hz: u32) {
-    // PIT base frequency is ~1.193182 MHz (same in 64-bit)
-    let pit_freq = 1_193_182;
-    let divisor = pit_freq / freq_hz;
-    
-    // Validate divisor
-    if divisor > 0xFFFF {
-        SERIAL_PORT.write_str("64-bit Timer init - ERROR: Divisor too large: 0x");
-        SERIAL_PORT.write_hex(divisor);
-        SERIAL_PORT.write_str("\n");
+    // Validate frequency before dividing (0 would fault, <18Hz overflows the divisor)
+    if !(PIT_MIN_FREQUENCY..=PIT_MAX_FREQUENCY).contains(&freq_hz) {
+        SERIAL_PORT.write_str("64-bit Timer init - WARNING: Frequency ");
+        SERIAL_PORT.write_decimal(freq_hz);
+        SERIAL_PORT.write_str("Hz out of range (");
+        SERIAL_PORT.write_decimal(PIT_MIN_FREQUENCY);
+        SERIAL_PORT.write_str("..=");
+        SERIAL_PORT.write_decimal(PIT_MAX_FREQUENCY);
+        SERIAL_PORT.write_str("), timer not programmed\n");
         return;
     }
+
+    // 18Hz gives 66287, which the 16-bit counter can't hold; clamp to the slowest rate
+    let divisor = (PIT_BASE_FREQUENCY / freq_hz).min(0xFFFF);
     
     // Program PIT (Channel 0, Mode 2, Rate Generator)
     let divisor_low = (divisor & 0xFF) as u8;
@@ -28,28 +44,15 @@ pub unsafe fn init(freq_hz: u32) {
     
     // Send command: Channel 0, Lo/Hi byte, Mode 2, Binary
     // Note: Port I/O instructions work the same in 64-bit mode
-    asm!(
-        "out dx, al", 
-        in("dx") 0x43u16, 
-        in("al") 0x34u8,
-        options(nostack, nomem)
-    );
+    outb(0x43, 0x34);
     
     // Send divisor low byte
-    asm!(
-        "out dx, al", 
-        in("dx") 0x40u16, 
-        in("al") divisor_low,
-        options(nostack, nomem)
-    );
+    outb(0x40, divisor_low);
     
     // Send divisor high byte
-    asm!(
-        "out dx, al", 
-        in("dx") 0x40u16, 
-        in("al") divisor_high,
-        options(nostack, nomem)
-    );
+    outb(0x40, divisor_high);
+
+    TIMER_FREQUENCY = freq_hz;
     
     SERIAL_PORT.write_str("64-bit PIT programmed - Command: 0x34, Divisor Low: 0x");
     SERIAL_PORT.write_hex(divisor_low as u32);
@@ -58,34 +61,58 @@ pub unsafe fn init(freq_hz: u32) {
     SERIAL_PORT.write_str("\n");
 }
 
+/// Configured tick rate in Hz (0 before init)
+pub fn frequency() -> u32 {
+    unsafe { TIMER_FREQUENCY }
+}
+
 pub unsafe fn get_ticks() -> u64 {
-    TIMER_TICKS
+    get_timer_ticks()
 }
 
 // Additional 64-bit specific timer functions
 
-/// Get uptime in milliseconds (assuming 100Hz timer)
+/// Get uptime in milliseconds (0 before init)
 pub unsafe fn get_uptime_ms() -> u64 {
-    TIMER_TICKS * 10  // 100Hz = 10ms per tick
+    if TIMER_FREQUENCY == 0 {
+        return 0;
+    }
+    get_timer_ticks() * 1000 / TIMER_FREQUENCY as u64
 }
 
-/// Get uptime in seconds
+/// Get uptime in seconds (0 before init)
 pub unsafe fn get_uptime_seconds() -> u64 {
-    TIMER_TICKS / 100  // 100Hz = 100 ticks per second
+    if TIMER_FREQUENCY == 0 {
+        return 0;
+    }
+    get_timer_ticks() / TIMER_FREQUENCY as u64
 }
 
 /// Sleep for approximately the specified number of ticks
 /// Note: This is a busy-wait sleep - not suitable for production
 pub unsafe fn sleep_ticks(ticks: u64) {
-    let start = TIMER_TICKS;
-    while (TIMER_TICKS - start) < ticks {
+    let start = get_timer_ticks();
+    while (get_timer_ticks() - start) < ticks {
         asm!("pause", options(nostack, nomem)); // CPU hint for spin-wait loops
     }
 }
 
+/// Sleep for at least `ms` milliseconds, rounded up to whole ticks
+/// Returns immediately if the timer has not been initialized
+pub unsafe fn sleep_ms(ms: u64) {
+    if TIMER_FREQUENCY == 0 {
+        return;
+    }
+    let ticks = (ms * TIMER_FREQUENCY as u64).div_ceil(1000);
+    sleep_ticks(ticks);
+}
+
 /// High precision timer using RDTSC (Read Time-Stamp Counter)
-/// Returns CPU cycles since reset
-pub unsafe fn rdtsc() -> u64 {
+/// Returns CPU cycles since reset, or None if the CPU has no TSC
+pub unsafe fn rdtsc() -> Option<u64> {
+    if !cpuid::has_feature(CpuFeature::Tsc) {
+        return None;
+    }
     let low: u32;
     let high: u32;
     asm!(
@@ -94,5 +121,151 @@ pub unsafe fn rdtsc() -> u64 {
         out("edx") high,
         options(nostack, nomem)
     );
-    ((high as u64) << 32) | (low as u64)
-}
\ No newline at end of file
+    Some(((high as u64) << 32) | (low as u64))
+}
+
+// ============================================================================
+// PIT CHANNEL 2 BUSY-WAIT
+// ============================================================================
+
+const PIT_CHANNEL2_DATA: u16 = 0x42;
+const PIT_COMMAND: u16 = 0x43;
+/// Keyboard controller port B: channel 2 gate (bit 0), speaker enable (bit 1)
+/// and channel 2 output (bit 5, read only)
+const PORT_B: u16 = 0x61;
+const PORT_B_GATE2: u8 = 1 << 0;
+const PORT_B_SPEAKER: u8 = 1 << 1;
+const PORT_B_OUT2: u8 = 1 << 5;
+/// Channel 2, lo/hi byte, mode 0 (interrupt on terminal count), binary
+const PIT_CHANNEL2_ONE_SHOT: u8 = 0xB0;
+/// Longest delay per countdown: 50 ms is 59659 PIT ticks, inside the 16-bit counter
+const PIT_DELAY_CHUNK_US: u64 = 50_000;
+
+/// Busy-wait at least `us` microseconds on PIT channel 2, polling its output
+/// pin. Needs no interrupts and no init(), so it works for early calibration
+/// (TSC, APIC timer). Channel 0 and its tick are not touched; the speaker is
+/// kept off. Resolution is one PIT tick, about 0.84 us.
+pub unsafe fn pit_delay_us(us: u64) {
+    let saved = inb(PORT_B);
+    let mut remaining = us;
+    while remaining > 0 {
+        let chunk = remaining.min(PIT_DELAY_CHUNK_US);
+        remaining -= chunk;
+        let count = (chunk * PIT_BASE_FREQUENCY as u64).div_ceil(1_000_000) as u16;
+
+        // Gate low while loading, so the countdown starts on the rising edge below
+        outb(PORT_B, saved & !(PORT_B_GATE2 | PORT_B_SPEAKER));
+        outb(PIT_COMMAND, PIT_CHANNEL2_ONE_SHOT);
+        outb(PIT_CHANNEL2_DATA, count as u8);
+        outb(PIT_CHANNEL2_DATA, (count >> 8) as u8);
+        outb(PORT_B, (saved & !PORT_B_SPEAKER) | PORT_B_GATE2);
+
+        // Mode 0: OUT2 is low while counting and goes high at terminal count
+        while inb(PORT_B) & PORT_B_OUT2 == 0 {
+            core::hint::spin_loop();
+        }
+    }
+    outb(PORT_B, saved);
+}
+
+// ============================================================================
+// ONE-SHOT TIMEOUTS
+// ============================================================================
+
+/// Timeouts that can be pending at once
+pub const MAX_TIMEOUTS: usize = 8;
+
+/// Handle returned by set_timeout, for cancel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutId(u32);
+
+#[derive(Clone, Copy)]
+struct Timeout {
+    id: TimeoutId,
+    /// Tick count at which the callback fires
+    expires: u64,
+    callback: fn(),
+}
+
+/// Pending timeouts sorted by expiry, soonest first
+struct TimeoutQueue {
+    entries: [Option<Timeout>; MAX_TIMEOUTS],
+    len: usize,
+    next_id: u32,
+}
+
+impl TimeoutQueue {
+    fn insert(&mut self, expires: u64, callback: fn()) -> Option<TimeoutId> {
+        if self.len == MAX_TIMEOUTS {
+            return None;
+        }
+        let id = TimeoutId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+
+        // After any entry with the same expiry, so equal deadlines fire in order
+        let pos = self.entries[..self.len]
+            .iter()
+            .position(|t| t.is_some_and(|t| t.expires > expires))
+            .unwrap_or(self.len);
+        self.entries.copy_within(pos..self.len, pos + 1);
+        self.entries[pos] = Some(Timeout { id, expires, callback });
+        self.len += 1;
+        Some(id)
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Timeout> {
+        let removed = self.entries[index].take();
+        self.entries.copy_within(index + 1..self.len, index);
+        self.len -= 1;
+        self.entries[self.len] = None;
+        removed
+    }
+
+    /// Take the soonest timeout if it is due at tick `now`
+    fn pop_expired(&mut self, now: u64) -> Option<Timeout> {
+        match self.entries[0] {
+            Some(t) if self.len > 0 && t.expires <= now => self.remove(0),
+            _ => None,
+        }
+    }
+}
+
+// Touched by the timer ISR, so everyone else locks it with interrupts off
+static TIMEOUTS: SpinLock<TimeoutQueue> =
+    SpinLock::new(TimeoutQueue { entries: [None; MAX_TIMEOUTS], len: 0, next_id: 1 });
+
+/// Call `callback` from the timer interrupt once at least `ms` milliseconds have
+/// passed (rounded up to whole ticks). The callback runs in interrupt context:
+/// it must be short and must not wait for anything. Returns None if the timer
+/// is not running or MAX_TIMEOUTS are already pending.
+pub fn set_timeout(ms: u64, callback: fn()) -> Option<TimeoutId> {
+    let freq = frequency() as u64;
+    if freq == 0 {
+        return None;
+    }
+    let ticks = (ms * freq).div_ceil(1000).max(1);
+    let mut queue = TIMEOUTS.lock_irqsave();
+    let now = unsafe { get_timer_ticks() };
+    queue.insert(now + ticks, callback)
+}
+
+/// Drop a pending timeout. Returns false if it already fired or was cancelled.
+pub fn cancel(id: TimeoutId) -> bool {
+    let mut queue = TIMEOUTS.lock_irqsave();
+    let Some(index) = queue.entries[..queue.len].iter().position(|t| t.is_some_and(|t| t.id == id)) else {
+        return false;
+    };
+    queue.remove(index);
+    true
+}
+
+/// Fire every timeout due at tick `now`. Called from the timer ISR; the lock is
+/// released before each callback so callbacks may set new timeouts.
+pub fn run_expired_timeouts(now: u64) {
+    loop {
+        let Some(timeout) = TIMEOUTS.lock().pop_expired(now) else {
+            break;
+        };
+        (timeout.callback)();
+    }
+}
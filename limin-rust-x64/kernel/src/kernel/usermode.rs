@@ -0,0 +1,204 @@
+// src/kernel/usermode.rs - dropping to ring 3 and a tiny userspace demo
+//
+// There is no separate user address space yet: the demo code and its stack are
+// kernel pages that get the U/S bit set in Limine's page tables. Both live on
+// page-aligned, page-sized regions of their own so no other kernel code or data
+// becomes reachable from ring 3.
+use core::arch::{asm, global_asm};
+use crate::kernel::gdt;
+use crate::kernel::serial::SERIAL_PORT;
+
+// Page table entry bits
+const PTE_PRESENT: u64 = 1 << 0;
+const PTE_USER: u64 = 1 << 2;
+const PTE_HUGE: u64 = 1 << 7;
+const PTE_ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+const PAGE_SIZE: u64 = 4096;
+
+/// RFLAGS for ring 3: interrupts enabled + reserved bit 1
+const USER_RFLAGS: u64 = (1 << 9) | 0x2;
+
+const USER_STACK_SIZE: usize = 16 * 1024;
+/// Stack the CPU switches to (TSS.rsp0) when ring 3 takes an interrupt or int 0x80
+const KERNEL_STACK_SIZE: usize = 16 * 1024;
+
+#[repr(C, align(4096))]
+struct PageAlignedStack<const N: usize>([u8; N]);
+
+static mut USER_STACK: PageAlignedStack<USER_STACK_SIZE> = PageAlignedStack([0; USER_STACK_SIZE]);
+static mut KERNEL_STACK: PageAlignedStack<KERNEL_STACK_SIZE> = PageAlignedStack([0; KERNEL_STACK_SIZE]);
+
+// The demo program: sys_write a greeting, then sys_exit. It only uses int 0x80
+// and data inside its own page, since nothing else is mapped for ring 3.
+// The exit code doubles as a check of the syscall path: 0 if sys_write saw
+// SYS_WRITE in rax and returned the length in rax, 1 otherwise.
+global_asm!(
+r#"
+.pushsection .text.user_demo, "ax"
+.balign 4096
+.globl user_demo
+user_demo:
+    mov rax, 1                          # SYS_WRITE
+    mov rdi, 1                          # fd (ignored)
+    lea rsi, [rip + user_demo_msg]
+    lea rdx, [rip + user_demo_msg_end]
+    sub rdx, rsi                        # len
+    mov rbx, rdx                        # expected return value
+    int 0x80
+
+    xor edi, edi
+    cmp rax, rbx
+    setne dil                           # exit code: 0 if rax == len
+    mov rax, 0                          # SYS_EXIT
+    int 0x80
+2:
+    jmp 2b                              # sys_exit does not return
+
+user_demo_msg:
+    .ascii "Hello from ring 3!\n"
+user_demo_msg_end:
+
+.balign 4096
+.globl user_demo_end
+user_demo_end:
+.popsection
+"#
+);
+
+unsafe extern "C" {
+    fn user_demo();
+    fn user_demo_end();
+}
+
+/// Set the U/S bit on every paging level for the page containing `virt`.
+/// Returns false if the page is not mapped.
+unsafe fn set_user_bit(hhdm: u64, virt: u64) -> bool {
+    let cr3: u64;
+    asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack, preserves_flags));
+
+    let mut table = cr3 & PTE_ADDR_MASK;
+    // level 3 = PML4, 2 = PDPT, 1 = PD, 0 = PT
+    for level in (0..4).rev() {
+        let index = ((virt >> (12 + 9 * level)) & 0x1FF) as usize;
+        let entry = ((table + hhdm) as *mut u64).add(index);
+        let value = entry.read_volatile();
+        if value & PTE_PRESENT == 0 {
+            return false;
+        }
+        entry.write_volatile(value | PTE_USER);
+
+        // 1 GiB / 2 MiB pages end the walk early
+        if level == 0 || (level < 3 && value & PTE_HUGE != 0) {
+            break;
+        }
+        table = value & PTE_ADDR_MASK;
+    }
+
+    asm!("invlpg [{}]", in(reg) virt, options(nostack, preserves_flags));
+    true
+}
+
+/// Whether ring 3 can read the page containing `virt`: canonical, present and
+/// U/S set on every paging level
+unsafe fn is_user_page(hhdm: u64, virt: u64) -> bool {
+    if ((virt as i64) << 16 >> 16) as u64 != virt {
+        return false;
+    }
+    let cr3: u64;
+    asm!("mov {}, cr3", out(reg) cr3, options(nomem, nostack, preserves_flags));
+
+    let mut table = cr3 & PTE_ADDR_MASK;
+    for level in (0..4).rev() {
+        let index = ((virt >> (12 + 9 * level)) & 0x1FF) as usize;
+        let value = ((table + hhdm) as *const u64).add(index).read_volatile();
+        if value & (PTE_PRESENT | PTE_USER) != PTE_PRESENT | PTE_USER {
+            return false;
+        }
+        if level == 0 || (level < 3 && value & PTE_HUGE != 0) {
+            break;
+        }
+        table = value & PTE_ADDR_MASK;
+    }
+    true
+}
+
+/// Whether all of [start, start + len) is mapped and readable from ring 3, for
+/// checking buffers passed in by system calls. False if the range wraps.
+pub unsafe fn is_user_range(start: u64, len: u64) -> bool {
+    let Some(end) = start.checked_add(len) else {
+        return false;
+    };
+    let Some(hhdm) = crate::HHDM_REQUEST.get_response() else {
+        return false;
+    };
+
+    let mut page = start & !(PAGE_SIZE - 1);
+    while page < end {
+        if !is_user_page(hhdm.offset(), page) {
+            return false;
+        }
+        page += PAGE_SIZE;
+    }
+    true
+}
+
+/// Make the pages covering [start, end) accessible from ring 3
+unsafe fn map_user_accessible(start: u64, end: u64) -> bool {
+    let Some(hhdm) = crate::HHDM_REQUEST.get_response() else {
+        SERIAL_PORT.write_str("USER: no HHDM from bootloader, can't reach the page tables\n");
+        return false;
+    };
+
+    let mut page = start & !(PAGE_SIZE - 1);
+    while page < end {
+        if !set_user_bit(hhdm.offset(), page) {
+            SERIAL_PORT.write_fmt(format_args!("USER: page {:#x} is not mapped\n", page));
+            return false;
+        }
+        page += PAGE_SIZE;
+    }
+    true
+}
+
+/// Drop to ring 3 at `entry` with stack pointer `user_stack`, by building the
+/// frame iretq expects: SS, RSP, RFLAGS (IF set), CS, RIP. Both selectors
+/// carry RPL 3. Interrupts and int 0x80 from ring 3 land on KERNEL_STACK.
+pub unsafe fn enter_userspace(entry: u64, user_stack: u64) -> ! {
+    let kernel_stack_top = core::ptr::addr_of!(KERNEL_STACK) as u64 + KERNEL_STACK_SIZE as u64;
+    gdt::set_kernel_stack(kernel_stack_top);
+
+    let user_data = gdt::USER_DATA_SELECTOR as u64;
+    asm!(
+        "mov ds, {data:x}",
+        "mov es, {data:x}",
+        "push {data}",
+        "push {rsp}",
+        "push {rflags}",
+        "push {code}",
+        "push {rip}",
+        "iretq",
+        data = in(reg) user_data,
+        rsp = in(reg) user_stack,
+        rflags = in(reg) USER_RFLAGS,
+        code = in(reg) gdt::USER_CODE_SELECTOR as u64,
+        rip = in(reg) entry,
+        options(noreturn)
+    );
+}
+
+/// Run the user_demo program in ring 3. Only returns if its pages could not be
+/// made user-accessible; otherwise the demo's sys_exit ends the session.
+pub unsafe fn run_demo() {
+    let code_start = user_demo as usize as u64;
+    let code_end = user_demo_end as usize as u64;
+    let stack_start = core::ptr::addr_of!(USER_STACK) as u64;
+    let stack_end = stack_start + USER_STACK_SIZE as u64;
+
+    if !map_user_accessible(code_start, code_end) || !map_user_accessible(stack_start, stack_end) {
+        return;
+    }
+
+    SERIAL_PORT.write_fmt(format_args!("USER: entering ring 3 at {:#x}\n", code_start));
+    enter_userspace(code_start, stack_end);
+}
@@ -10,10 +10,11 @@ mod kernel;             // Core kernel subsystems
 
 use core::arch::asm;
 use kernel::serial::SERIAL_PORT;
-use kernel::{idt, interrupts, timer, pic};
+use kernel::{console, cpuid, symbols, gdt, idt, interrupts, timer, hpet, rtc, pic, paging, pci, syscall, mouse, ps2, shell};
+use graphics::{ColorLayout, Framebuffer, GradientDir};
 
 use limine::BaseRevision;
-use limine::request::{FramebufferRequest, RequestsEndMarker, RequestsStartMarker};
+use limine::request::{ExecutableFileRequest, FramebufferRequest, HhdmRequest, MemoryMapRequest, RsdpRequest, RequestsEndMarker, RequestsStartMarker};
 
 /// Sets the base revision to the latest revision supported by the crate.
 /// See specification for further info.
@@ -27,6 +28,25 @@ static BASE_REVISION: BaseRevision = BaseRevision::new();
 #[unsafe(link_section = ".requests")]
 static FRAMEBUFFER_REQUEST: FramebufferRequest = FramebufferRequest::new();
 
+#[used]
+#[unsafe(link_section = ".requests")]
+pub static MEMMAP_REQUEST: MemoryMapRequest = MemoryMapRequest::new();
+
+/// Higher-half direct map offset, needed to reach physical memory such as page tables
+#[used]
+#[unsafe(link_section = ".requests")]
+pub static HHDM_REQUEST: HhdmRequest = HhdmRequest::new();
+
+/// ACPI root pointer, for finding tables such as the HPET's
+#[used]
+#[unsafe(link_section = ".requests")]
+pub static RSDP_REQUEST: RsdpRequest = RsdpRequest::new();
+
+/// The kernel ELF file itself, for its symbol table
+#[used]
+#[unsafe(link_section = ".requests")]
+pub static EXECUTABLE_FILE_REQUEST: ExecutableFileRequest = ExecutableFileRequest::new();
+
 /// Define the stand and end markers for Limine requests.
 #[used]
 #[unsafe(link_section = ".requests_start_marker")]
@@ -99,10 +119,13 @@ unsafe fn check_stack_in_main_loop_64bit(iteration: u64) {
 
 #[unsafe(no_mangle)]
 unsafe extern "C" fn kmain() -> ! {
-    // Initialize serial port first
+    // All limine requests must also be referenced in a called function, otherwise they may be
+    // removed by the linker.
+    assert!(BASE_REVISION.is_supported());
+
     unsafe {
-        // Initialize serial port first
-        SERIAL_PORT.init();
+        // Initialize serial port first (no other output yet, so a failed self-test is ignored)
+        let _ = SERIAL_PORT.init();
         SERIAL_PORT.write_str("\n=== INTERRUPT DEBUG SESSION ===\n");
         SERIAL_PORT.write_str("Waiting to see if timer fires and disables itself...\n");
     }
@@ -115,11 +138,17 @@ unsafe extern "C" fn kmain() -> ! {
         
         // Check system state
         check_system_tables_64bit();
+        cpuid::log_features();
+        symbols::init();
         
+        // Our own GDT + TSS, so the IDT can point vectors at IST stacks
+        gdt::init();
+
         // Initialize 64-bit IDT
         SERIAL_PORT.write_str("Step 2: Initializing 64-bit IDT...\n");
         idt::init();
         SERIAL_PORT.write_str("  ✓ 64-bit IDT loaded\n");
+        syscall::init();
         
         // Verify 64-bit IDT entries
         SERIAL_PORT.write_str("Step 3: Verifying 64-bit IDT entries...\n");
@@ -134,6 +163,9 @@ unsafe extern "C" fn kmain() -> ! {
         SERIAL_PORT.write_str("Step 5: Initializing 64-bit timer...\n");
         timer::init(100); // 100 Hz
         SERIAL_PORT.write_str("  ✓ 64-bit timer initialized at 100Hz\n");
+        hpet::init();
+        rtc::init();
+        pci::print_devices();
         
         // Enable interrupts and test
         SERIAL_PORT.write_str("Step 6: Testing 64-bit interrupt system...\n");
@@ -141,34 +173,94 @@ unsafe extern "C" fn kmain() -> ! {
         
         SERIAL_PORT.write_str("✓ 64-bit interrupt system fully operational\n");
     }
+
+    #[cfg(feature = "test-faults")]
+    unsafe { kernel::fault_tests::run_all() }
      
-    // All limine requests must also be referenced in a called function, otherwise they may be
-    // removed by the linker.
-    // assert!(BASE_REVISION.is_supported());
-
-    // if let Some(framebuffer_response) = FRAMEBUFFER_REQUEST.get_response() {
-    //     if let Some(framebuffer) = framebuffer_response.framebuffers().next() {
-    //         for i in 0..100_u64 {
-    //             // Calculate the pixel offset using the framebuffer information we obtained above.
-    //             // We skip `i` scanlines (pitch is provided in bytes) and add `i * 4` to skip `i` pixels forward.
-    //             let pixel_offset = i * framebuffer.pitch() + i * 4;
-
-    //             // Write 0xFFFFFFFF to the provided pixel offset to fill it white.
-    //             unsafe {
-    //                 framebuffer
-    //                     .addr()
-    //                     .add(pixel_offset as usize)
-    //                     .cast::<u32>()
-    //                     .write(0xFFFFFFFF)
-    //             };
-    //         }
-    //     }
-    // }
-
-    hcf();
+    // Draw a test pattern if Limine gave us a framebuffer
+    unsafe {
+        if let Some(fb) = init_framebuffer() {
+            fb.draw_gradient_ex(0xFF_10_20_60, 0xFF_60_10_40, GradientDir::Diagonal);
+            fb.fill_rect(20, 20, 200, 100, 0xFF_00_80_00);
+            fb.draw_line(0, 0, (fb.width - 1) as isize, (fb.height - 1) as isize, 0xFF_FF_00_00);
+            console::init(fb);
+            console_println!("Framebuffer console ready");
+        }
+    }
+
+    // Hand the serial port over to the debug shell (never returns)
+    unsafe { shell::run() }
+}
+
+
+
+/// Build a Framebuffer from Limine's first framebuffer, logging what was found.
+/// Limine hands out a mapped (HHDM) address, which goes in `phys_addr` as-is.
+unsafe fn init_framebuffer() -> Option<Framebuffer> {
+    let Some(response) = FRAMEBUFFER_REQUEST.get_response() else {
+        SERIAL_PORT.write_str("Framebuffer: no response from Limine\n");
+        return None;
+    };
+    let Some(limine_fb) = response.framebuffers().next() else {
+        SERIAL_PORT.write_str("Framebuffer: Limine returned no framebuffers\n");
+        return None;
+    };
+
+    let bpp = limine_fb.bpp() as usize;
+    let layout = ColorLayout {
+        red_pos: limine_fb.red_mask_shift(),
+        red_size: limine_fb.red_mask_size(),
+        green_pos: limine_fb.green_mask_shift(),
+        green_size: limine_fb.green_mask_size(),
+        blue_pos: limine_fb.blue_mask_shift(),
+        blue_size: limine_fb.blue_mask_size(),
+    };
+    let (pitch, width, height) = (limine_fb.pitch() as usize, limine_fb.width() as usize, limine_fb.height() as usize);
+    let mut fb = match Framebuffer::new_checked(limine_fb.addr() as usize, pitch, width, height, bpp) {
+        Ok(fb) => fb,
+        Err(err) => {
+            SERIAL_PORT.write_fmt(format_args!(
+                "Framebuffer: rejected {}x{} {}bpp, pitch {}: {:?}\n", width, height, bpp, pitch, err
+            ));
+            return None;
+        }
+    };
+    if layout.is_valid_for(bpp) {
+        fb.layout = layout;
+    }
+
+    SERIAL_PORT.write_fmt(format_args!(
+        "Framebuffer: {}x{} {}bpp, pitch {}, at {:#x}\n",
+        fb.width, fb.height, fb.bpp, fb.pitch, fb.phys_addr
+    ));
+    remap_framebuffer_wc(&mut fb);
+    Some(fb)
 }
 
+/// Nanoseconds one full-screen clear takes at the framebuffer's current mapping
+unsafe fn time_clear(fb: &Framebuffer) -> u64 {
+    let start = hpet::hpet_now_ns();
+    fb.fill_rect(0, 0, fb.width, fb.height, 0);
+    hpet::hpet_now_ns() - start
+}
 
+/// Switch `fb` to a write-combining mapping of the same memory, logging how
+/// long a clear takes before and after. Keeps Limine's mapping on failure.
+unsafe fn remap_framebuffer_wc(fb: &mut Framebuffer) {
+    let Some(phys) = paging::translate(fb.phys_addr as u64) else {
+        return;
+    };
+    let before_ns = time_clear(fb);
+    let Some(wc) = paging::map_framebuffer_wc(phys, (fb.pitch * fb.height) as u64) else {
+        return;
+    };
+    fb.phys_addr = wc as usize;
+    let after_ns = time_clear(fb);
+    SERIAL_PORT.write_fmt(format_args!(
+        "Framebuffer: full clear {} us with Limine's mapping, {} us remapped\n",
+        before_ns / 1000, after_ns / 1000
+    ));
+}
 
 fn hcf() -> ! {
     loop {
@@ -291,9 +383,26 @@ unsafe fn test_64bit_interrupts() {
         }
     }
     
+    // Known 8042 state before the keyboard and mouse rely on it
+    if let Err(err) = ps2::init() {
+        SERIAL_PORT.write_fmt(format_args!("  PS/2 controller init failed: {:?}\n", err));
+    }
+
     // Also enable keyboard for interactive testing
     SERIAL_PORT.write_str("  Enabling 64-bit keyboard interrupts...\n");
     pic::unmask_irq(1); // IRQ1 = Keyboard
     SERIAL_PORT.write_str("  ✓ Press keys to test 64-bit keyboard interrupts\n");
+
+    // Serial input is buffered by the IRQ4 handler, read it with serial_read_char()
+    interrupts::register_irq_handler(4, kernel::serial::handle_rx_interrupt);
+    SERIAL_PORT.enable_rx_interrupt();
+    pic::unmask_irq(4); // IRQ4 = COM1
+    SERIAL_PORT.write_str("  ✓ COM1 receive interrupts enabled\n");
+
+    // PS/2 mouse on the slave PIC; events are read with mouse::poll_mouse_event()
+    if mouse::init() {
+        pic::unmask_irq(2);  // IRQ2 = cascade to slave PIC
+        pic::unmask_irq(12); // IRQ12 = PS/2 mouse
+    }
         }
 }
\ No newline at end of file
@@ -5,8 +5,8 @@
 
 use core::panic::PanicInfo;
 use core::arch::asm;
-use core::fmt::Write;
-use crate::kernel::loggers::LOGGER;
+use core::fmt;
+use crate::kernel::console;
 use crate::kernel::serial::SERIAL_PORT;
 
 /// Kernel panic handler - called when the kernel encounters a fatal error
@@ -18,15 +18,15 @@ pub fn panic_handler(info: &PanicInfo) -> ! {
     }
     
     unsafe {
+        // Everything below goes straight to the COM1 registers: SERIAL_PORT takes
+        // no lock, while the logger serialises on LOG_LOCK, which the panicking
+        // code may hold, so the report never goes through LOGGER.
         // Print panic header
         SERIAL_PORT.write_str("\n");
         SERIAL_PORT.write_str("=====================================\n");
         SERIAL_PORT.write_str("       KERNEL PANIC OCCURRED!       \n");
         SERIAL_PORT.write_str("=====================================\n");
         
-        // Log through both serial and logger if available
-        LOGGER.error("KERNEL PANIC - SYSTEM HALTING");
-        
         // Print location information if available
         if let Some(location) = info.location() {
             SERIAL_PORT.write_str("Panic Location:\n");
@@ -56,11 +56,11 @@ pub fn panic_handler(info: &PanicInfo) -> ! {
         }
         
         // TODO: Add more debugging info
-        // - Register dump
-        // - Stack trace  
         // - Memory state
         // - Recent kernel activity log
         print_register_dump();
+        print_backtrace();
+        draw_panic_screen(info);
         
         SERIAL_PORT.write_str("\nSystem State:\n");
         SERIAL_PORT.write_str("  Interrupts: DISABLED\n");
@@ -72,9 +72,6 @@ pub fn panic_handler(info: &PanicInfo) -> ! {
         SERIAL_PORT.write_str("System has been halted for safety.\n");
         SERIAL_PORT.write_str("Restart required.\n");
         SERIAL_PORT.write_str("=====================================\n");
-        
-        // Final log entry
-        LOGGER.error("System halted due to kernel panic - restart required");
     }
     
     // Halt the CPU indefinitely
@@ -189,6 +186,93 @@ unsafe fn print_register_dump() {
     }
 }
 
+/// Deepest call chain print_backtrace walks
+const MAX_BACKTRACE_FRAMES: usize = 32;
+/// Frames shown on the panic screen; the full trace goes to serial
+const PANIC_SCREEN_FRAMES: usize = 8;
+
+/// One return address of a backtrace, printed as `#n 0x... <name+0x..>`
+struct Frame {
+    depth: usize,
+    return_addr: u64,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // return_addr - 1 is still inside the call instruction, so a call that
+        // ends its function doesn't get attributed to the next one
+        match crate::kernel::symbols::symbolize(self.return_addr - 1) {
+            Some((name, offset)) => write!(
+                f, "#{:<2} {:#018x} <{}+{:#x}>", self.depth, self.return_addr, name, offset + 1
+            ),
+            None => write!(f, "#{:<2} {:#018x}", self.depth, self.return_addr),
+        }
+    }
+}
+
+/// Walk the RBP chain (needs -C force-frame-pointers=yes) and hand each of the
+/// first `max_frames` return addresses to `visit`.
+/// Each frame holds the caller's RBP at [rbp] and the return address at [rbp+8].
+#[cfg(target_arch = "x86_64")]
+unsafe fn walk_stack(max_frames: usize, mut visit: impl FnMut(Frame)) {
+    let mut rbp: u64;
+    asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags));
+
+    for depth in 0..max_frames {
+        // Stop at the outermost frame (RBP 0) or anything that can't be a frame
+        if rbp == 0 || !rbp.is_multiple_of(8) {
+            break;
+        }
+        let return_addr = *((rbp + 8) as *const u64);
+        if return_addr == 0 {
+            break;
+        }
+        visit(Frame { depth, return_addr });
+
+        // Caller frames are further up the stack; anything else is a corrupt chain
+        let next = *(rbp as *const u64);
+        if next <= rbp {
+            break;
+        }
+        rbp = next;
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn walk_stack(_max_frames: usize, _visit: impl FnMut(Frame)) {}
+
+/// Print each return address on the stack, with the function it falls in when
+/// the kernel has a symbol table
+unsafe fn print_backtrace() {
+    SERIAL_PORT.write_str("\nBacktrace:\n");
+    walk_stack(MAX_BACKTRACE_FRAMES, |frame| SERIAL_PORT.write_fmt(format_args!("  {}\n", frame)));
+}
+
+/// Show the panic on the framebuffer console, for machines without a serial
+/// cable. Does nothing if the panic came before the framebuffer was set up.
+unsafe fn draw_panic_screen(info: &PanicInfo) {
+    let drawn = console::panic_screen(|out| {
+        let _ = writeln!(out, "KERNEL PANIC - SYSTEM HALTED\n");
+        match info.location() {
+            Some(location) => {
+                let _ = writeln!(out, "at {}:{}:{}", location.file(), location.line(), location.column());
+            }
+            None => {
+                let _ = writeln!(out, "at unknown location");
+            }
+        }
+        let _ = writeln!(out, "{}\n", info.message());
+        let _ = writeln!(out, "Backtrace:");
+        walk_stack(PANIC_SCREEN_FRAMES, |frame| {
+            let _ = writeln!(out, "  {}", frame);
+        });
+        let _ = writeln!(out, "\nRestart required.");
+    });
+    if !drawn {
+        SERIAL_PORT.write_str("(no framebuffer console, panic shown on serial only)\n");
+    }
+}
+
 /// Helper to print 64-bit hex values
 unsafe fn print_hex64(mut value: u64) {
     if value == 0 {
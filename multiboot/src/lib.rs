@@ -0,0 +1,554 @@
+// src/lib.rs - walking the Multiboot2 boot information tag list (used by osone)
+#![cfg_attr(not(test), no_std)]
+// The entry points take the raw MBI address from EBX; their safety contract is
+// spelled out once on tags().
+#![allow(unsafe_op_in_unsafe_fn, clippy::missing_safety_doc)]
+use core::mem::size_of;
+use core::ptr::read_unaligned;
+
+/// Upper bound on tags walked in one MBI; real boots have a few dozen at most
+const MB_MAX_TAGS: usize = 256;
+
+// Tag types we know how to parse
+pub const TAG_END: u32 = 0;
+pub const TAG_CMDLINE: u32 = 1;
+pub const TAG_BOOTLOADER_NAME: u32 = 2;
+pub const TAG_MMAP: u32 = 6;
+pub const TAG_FRAMEBUFFER: u32 = 8;
+pub const TAG_ELF_SECTIONS: u32 = 9;
+pub const TAG_ACPI_OLD: u32 = 14;
+pub const TAG_ACPI_NEW: u32 = 15;
+pub const TAG_EFI_MMAP: u32 = 17;
+
+#[repr(C)]
+struct MbInfoHeader { total_size: u32, reserved: u32 }
+
+#[repr(C)]
+struct TagHeader { typ: u32, size: u32 }
+
+/// One boot information tag. `payload` is everything after the 8-byte tag
+/// header, without the padding up to the next tag.
+#[derive(Clone, Copy)]
+pub struct Tag<'a> {
+    pub typ: u32,
+    pub size: u32,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Tag<'a> {
+    /// Payload as a null-terminated string (cmdline, bootloader name).
+    /// None if it is not valid UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        let len = self.payload.iter().position(|&b| b == 0).unwrap_or(self.payload.len());
+        core::str::from_utf8(&self.payload[..len]).ok()
+    }
+}
+
+/// Iterator over the tags of a Multiboot2 info block.
+///
+/// Every tag it yields lies entirely inside total_size. It stops at the end tag,
+/// at the first tag with a nonsense size, or after MB_MAX_TAGS tags, so a corrupt
+/// tag list can't send a consumer walking through unrelated memory.
+pub struct TagIter<'a> {
+    mbi: &'a [u8],
+    offset: usize,
+    walked: usize,
+}
+
+impl<'a> TagIter<'a> {
+    /// Walk the tags of an info block already in memory. `mbi` is the whole
+    /// block, starting with the total_size/reserved header.
+    pub fn new(mbi: &'a [u8]) -> Self {
+        // Tags start at offset 8
+        TagIter { mbi, offset: size_of::<MbInfoHeader>(), walked: 0 }
+    }
+
+    fn finish(&mut self) -> Option<Tag<'a>> {
+        self.offset = self.mbi.len();
+        None
+    }
+}
+
+impl<'a> Iterator for TagIter<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Tag<'a>> {
+        let remaining = self.mbi.len().saturating_sub(self.offset);
+        if remaining < size_of::<TagHeader>() || self.walked >= MB_MAX_TAGS {
+            return self.finish();
+        }
+        self.walked += 1;
+
+        let start = &self.mbi[self.offset..];
+        let header = unsafe { read_unaligned(start.as_ptr() as *const TagHeader) };
+        if header.typ == TAG_END {
+            return self.finish();
+        }
+
+        // every tag includes its own 8-byte header and must fit inside the MBI
+        let size = header.size as usize;
+        if size < size_of::<TagHeader>() || size > remaining {
+            return self.finish();
+        }
+
+        // tags are padded to 8 bytes; running past the end just ends the walk
+        self.offset += (size + 7) & !7usize;
+
+        Some(Tag { typ: header.typ, size: header.size, payload: &start[size_of::<TagHeader>()..size] })
+    }
+}
+
+/// total_size from the info block header: the whole MBI including all tags.
+/// None for a null pointer or a size too small to hold the header.
+///
+/// Safety: same as tags().
+pub unsafe fn total_size(mbi_ptr: u32) -> Option<u32> {
+    if mbi_ptr == 0 { return None; }
+    let total_size = read_unaligned(mbi_ptr as *const MbInfoHeader).total_size;
+    if (total_size as usize) < size_of::<MbInfoHeader>() { return None; }
+    Some(total_size)
+}
+
+/// Iterate over the tags of the info block at mbi_ptr (EBX at boot).
+///
+/// Safety: mbi_ptr must point to a valid Multiboot2 info block that stays mapped
+/// (identity mapped) for the rest of the kernel's life.
+pub unsafe fn tags(mbi_ptr: u32) -> Option<TagIter<'static>> {
+    let total_size = total_size(mbi_ptr)? as usize;
+    let base = mbi_ptr as *const u8;
+    Some(TagIter::new(core::slice::from_raw_parts(base, total_size)))
+}
+
+// ============================================================================
+// ELF SECTIONS (tag type 9)
+// ============================================================================
+
+// Section header sh_type values we look for
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+
+/// Section header sizes for the two ELF classes
+const ELF32_SHDR_SIZE: usize = 40;
+const ELF64_SHDR_SIZE: usize = 64;
+
+/// Payload of the ELF-sections tag before the header table: num, entsize, shndx
+const ELF_SECTIONS_FIELDS: usize = 12;
+
+/// One ELF section header, widened to 64 bits whichever class the kernel is.
+/// `addr` is where the bootloader loaded the section (0 if it is not loaded).
+#[derive(Clone, Copy, Debug)]
+pub struct SectionHeader {
+    pub name: u32,
+    pub typ: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub entsize: u64,
+}
+
+/// The kernel's section header table as handed over in the ELF-sections tag
+#[derive(Clone, Copy)]
+pub struct ElfSections<'a> {
+    /// Number of headers the tag claims
+    pub num: u32,
+    /// Size of one header, from the tag; 40 for ELF32, 64 for ELF64
+    pub entsize: u32,
+    /// Index of the section holding the section names (.shstrtab)
+    pub shndx: u32,
+    table: &'a [u8],
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn le_u64(bytes: &[u8], at: usize) -> u64 {
+    le_u32(bytes, at) as u64 | (le_u32(bytes, at + 4) as u64) << 32
+}
+
+impl<'a> ElfSections<'a> {
+    /// Parse a tag payload. The header table is cut to the headers that fit in
+    /// the tag, so a `num` larger than the tag just yields fewer headers.
+    fn parse(payload: &'a [u8]) -> Option<Self> {
+        if payload.len() < ELF_SECTIONS_FIELDS { return None; }
+        let num = le_u32(payload, 0);
+        let entsize = le_u32(payload, 4);
+        let shndx = le_u32(payload, 8);
+        if entsize as usize != ELF32_SHDR_SIZE && entsize as usize != ELF64_SHDR_SIZE {
+            return None;
+        }
+
+        let table = &payload[ELF_SECTIONS_FIELDS..];
+        let fits = table.len() / entsize as usize;
+        let len = (num as usize).min(fits) * entsize as usize;
+        Some(ElfSections { num, entsize, shndx, table: &table[..len] })
+    }
+
+    /// Number of headers actually present in the tag
+    pub fn len(&self) -> usize {
+        self.table.len() / self.entsize as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Header `index`, decoded according to entsize
+    pub fn get(&self, index: usize) -> Option<SectionHeader> {
+        let size = self.entsize as usize;
+        let raw = self.table.get(index * size..(index + 1) * size)?;
+        Some(if size == ELF64_SHDR_SIZE {
+            SectionHeader {
+                name: le_u32(raw, 0),
+                typ: le_u32(raw, 4),
+                flags: le_u64(raw, 8),
+                addr: le_u64(raw, 16),
+                offset: le_u64(raw, 24),
+                size: le_u64(raw, 32),
+                link: le_u32(raw, 40),
+                entsize: le_u64(raw, 56),
+            }
+        } else {
+            SectionHeader {
+                name: le_u32(raw, 0),
+                typ: le_u32(raw, 4),
+                flags: le_u32(raw, 8) as u64,
+                addr: le_u32(raw, 12) as u64,
+                offset: le_u32(raw, 16) as u64,
+                size: le_u32(raw, 20) as u64,
+                link: le_u32(raw, 24),
+                entsize: le_u32(raw, 36) as u64,
+            }
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = SectionHeader> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
+
+    /// The symbol table (.symtab) and the string table its names point into
+    /// (.strtab, found through the symtab's sh_link). None if the kernel was
+    /// stripped or the link is not a string table.
+    pub fn symbol_tables(&self) -> Option<(SectionHeader, SectionHeader)> {
+        let symtab = self.iter().find(|s| s.typ == SHT_SYMTAB)?;
+        let strtab = self.get(symtab.link as usize)?;
+        if strtab.typ != SHT_STRTAB { return None; }
+        Some((symtab, strtab))
+    }
+}
+
+/// The kernel's ELF section headers, if the bootloader passed them.
+///
+/// Safety: same as tags().
+pub unsafe fn find_elf_sections(mbi_ptr: u32) -> Option<ElfSections<'static>> {
+    let tag = tags(mbi_ptr)?.find(|t| t.typ == TAG_ELF_SECTIONS)?;
+    ElfSections::parse(tag.payload)
+}
+
+// ============================================================================
+// MEMORY MAPS (tag types 6 and 17)
+// ============================================================================
+
+/// Payload of both memory map tags before the entries: entry size, entry version
+const MMAP_FIELDS: usize = 8;
+
+/// Type-6 entry: base_addr, length, type, reserved
+const MMAP_ENTRY_SIZE: usize = 24;
+/// Type-6 entry type for RAM free to use
+pub const MMAP_AVAILABLE: u32 = 1;
+/// Other type-6 entry types
+const MMAP_ACPI_RECLAIMABLE: u32 = 3;
+const MMAP_ACPI_NVS: u32 = 4;
+const MMAP_BAD_RAM: u32 = 5;
+
+/// EFI_MEMORY_DESCRIPTOR as documented; firmware may use a larger descr_size
+const EFI_DESCRIPTOR_SIZE: usize = 40;
+/// EfiConventionalMemory: free RAM once boot services have exited
+pub const EFI_CONVENTIONAL_MEMORY: u32 = 7;
+/// EFI pages are always 4 KiB, whatever the kernel's page size
+pub const EFI_PAGE_SIZE: u64 = 4096;
+/// Other EFI memory types we name
+const EFI_UNUSABLE_MEMORY: u32 = 8;
+const EFI_ACPI_RECLAIM_MEMORY: u32 = 9;
+const EFI_ACPI_MEMORY_NVS: u32 = 10;
+
+/// What a memory map entry is used for, from either map's type codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    Usable,
+    Reserved,
+    /// ACPI tables, free once they have been read
+    AcpiReclaimable,
+    /// ACPI non-volatile storage, must be preserved
+    AcpiNvs,
+    /// Defective RAM
+    BadRam,
+}
+
+impl RegionKind {
+    fn from_legacy(typ: u32) -> Self {
+        match typ {
+            MMAP_AVAILABLE => RegionKind::Usable,
+            MMAP_ACPI_RECLAIMABLE => RegionKind::AcpiReclaimable,
+            MMAP_ACPI_NVS => RegionKind::AcpiNvs,
+            MMAP_BAD_RAM => RegionKind::BadRam,
+            _ => RegionKind::Reserved,
+        }
+    }
+
+    /// Only conventional memory counts as usable, as in usable_regions();
+    /// boot services and loader memory are left alone
+    fn from_efi(typ: u32) -> Self {
+        match typ {
+            EFI_CONVENTIONAL_MEMORY => RegionKind::Usable,
+            EFI_ACPI_RECLAIM_MEMORY => RegionKind::AcpiReclaimable,
+            EFI_ACPI_MEMORY_NVS => RegionKind::AcpiNvs,
+            EFI_UNUSABLE_MEMORY => RegionKind::BadRam,
+            _ => RegionKind::Reserved,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RegionKind::Usable => "usable",
+            RegionKind::Reserved => "reserved",
+            RegionKind::AcpiReclaimable => "ACPI",
+            RegionKind::AcpiNvs => "ACPI NVS",
+            RegionKind::BadRam => "badram",
+        }
+    }
+}
+
+/// A range of physical RAM the frame allocator may hand out
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryRegion {
+    pub base: u64,
+    pub length: u64,
+}
+
+/// Any memory map entry, usable or not
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryEntry {
+    pub base: u64,
+    pub length: u64,
+    pub kind: RegionKind,
+}
+
+/// One EFI memory descriptor
+#[derive(Clone, Copy, Debug)]
+pub struct EfiMemoryDescriptor {
+    pub typ: u32,
+    pub phys_start: u64,
+    pub num_pages: u64,
+    pub attribute: u64,
+}
+
+/// The EFI memory map from tag 17, as GRUB got it from GetMemoryMap()
+#[derive(Clone, Copy)]
+pub struct EfiMmap<'a> {
+    /// Stride between descriptors; can be larger than the documented 40 bytes
+    pub descr_size: u32,
+    pub descr_version: u32,
+    descriptors: &'a [u8],
+}
+
+impl<'a> EfiMmap<'a> {
+    fn parse(payload: &'a [u8]) -> Option<Self> {
+        if payload.len() < MMAP_FIELDS { return None; }
+        let descr_size = le_u32(payload, 0);
+        let descr_version = le_u32(payload, 4);
+        if (descr_size as usize) < EFI_DESCRIPTOR_SIZE { return None; }
+        Some(EfiMmap { descr_size, descr_version, descriptors: &payload[MMAP_FIELDS..] })
+    }
+
+    /// Every descriptor in the map, stepping by descr_size
+    pub fn iter(&self) -> impl Iterator<Item = EfiMemoryDescriptor> + use<'a> {
+        self.descriptors.chunks_exact(self.descr_size as usize).map(|raw| EfiMemoryDescriptor {
+            typ: le_u32(raw, 0),
+            // 4 bytes of padding, then phys_start, virt_start, num_pages, attribute
+            phys_start: le_u64(raw, 8),
+            num_pages: le_u64(raw, 24),
+            attribute: le_u64(raw, 32),
+        })
+    }
+
+    /// Every descriptor as a byte range with its kind
+    pub fn entries(&self) -> impl Iterator<Item = MemoryEntry> + use<'a> {
+        self.iter().map(|d| MemoryEntry {
+            base: d.phys_start,
+            length: d.num_pages * EFI_PAGE_SIZE,
+            kind: RegionKind::from_efi(d.typ),
+        })
+    }
+
+    /// Conventional memory as byte ranges
+    pub fn usable_regions(&self) -> impl Iterator<Item = MemoryRegion> + use<'a> {
+        self.iter()
+            .filter(|d| d.typ == EFI_CONVENTIONAL_MEMORY)
+            .map(|d| MemoryRegion { base: d.phys_start, length: d.num_pages * EFI_PAGE_SIZE })
+    }
+}
+
+/// The BIOS-style (E820) memory map from tag 6
+#[derive(Clone, Copy)]
+pub struct LegacyMmap<'a> {
+    pub entry_size: u32,
+    entries: &'a [u8],
+}
+
+impl<'a> LegacyMmap<'a> {
+    fn parse(payload: &'a [u8]) -> Option<Self> {
+        if payload.len() < MMAP_FIELDS { return None; }
+        let entry_size = le_u32(payload, 0);
+        if (entry_size as usize) < MMAP_ENTRY_SIZE { return None; }
+        Some(LegacyMmap { entry_size, entries: &payload[MMAP_FIELDS..] })
+    }
+
+    /// Every entry with its kind
+    pub fn entries(&self) -> impl Iterator<Item = MemoryEntry> + use<'a> {
+        self.entries.chunks_exact(self.entry_size as usize).map(|raw| MemoryEntry {
+            base: le_u64(raw, 0),
+            length: le_u64(raw, 8),
+            kind: RegionKind::from_legacy(le_u32(raw, 16)),
+        })
+    }
+
+    /// Available entries as byte ranges
+    pub fn usable_regions(&self) -> impl Iterator<Item = MemoryRegion> + use<'a> {
+        self.entries
+            .chunks_exact(self.entry_size as usize)
+            .filter(|raw| le_u32(raw, 16) == MMAP_AVAILABLE)
+            .map(|raw| MemoryRegion { base: le_u64(raw, 0), length: le_u64(raw, 8) })
+    }
+}
+
+/// Whichever memory map the bootloader passed
+#[derive(Clone, Copy)]
+pub enum MemoryMap<'a> {
+    Efi(EfiMmap<'a>),
+    Legacy(LegacyMmap<'a>),
+}
+
+impl<'a> MemoryMap<'a> {
+    /// RAM free for the frame allocator, from either map
+    pub fn usable_regions(&self) -> impl Iterator<Item = MemoryRegion> + 'a {
+        let (efi, legacy) = match *self {
+            MemoryMap::Efi(map) => (Some(map.usable_regions()), None),
+            MemoryMap::Legacy(map) => (None, Some(map.usable_regions())),
+        };
+        efi.into_iter().flatten().chain(legacy.into_iter().flatten())
+    }
+
+    /// Every entry of either map, in the order the firmware listed them
+    pub fn entries(&self) -> impl Iterator<Item = MemoryEntry> + 'a {
+        let (efi, legacy) = match *self {
+            MemoryMap::Efi(map) => (Some(map.entries()), None),
+            MemoryMap::Legacy(map) => (None, Some(map.entries())),
+        };
+        efi.into_iter().flatten().chain(legacy.into_iter().flatten())
+    }
+
+    /// "EFI" or "legacy", for log lines
+    pub fn source(&self) -> &'static str {
+        match self {
+            MemoryMap::Efi(_) => "EFI",
+            MemoryMap::Legacy(_) => "legacy",
+        }
+    }
+}
+
+/// The EFI memory map (tag 17), if this was a UEFI boot.
+///
+/// Safety: same as tags().
+pub unsafe fn find_efi_memory_map(mbi_ptr: u32) -> Option<EfiMmap<'static>> {
+    let tag = tags(mbi_ptr)?.find(|t| t.typ == TAG_EFI_MMAP)?;
+    EfiMmap::parse(tag.payload)
+}
+
+/// The memory map to build the frame allocator from. On UEFI boots the type-6
+/// map can be missing or incomplete, so the EFI map wins when both are present.
+///
+/// Safety: same as tags().
+pub unsafe fn find_memory_map(mbi_ptr: u32) -> Option<MemoryMap<'static>> {
+    if let Some(efi) = find_efi_memory_map(mbi_ptr) {
+        return Some(MemoryMap::Efi(efi));
+    }
+    let tag = tags(mbi_ptr)?.find(|t| t.typ == TAG_MMAP)?;
+    LegacyMmap::parse(tag.payload).map(MemoryMap::Legacy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An info block holding `tags` (type, size field, payload), each padded to
+    /// 8 bytes, with total_size covering exactly `len` bytes of it
+    fn mbi(tags: &[(u32, u32, &[u8])], len: Option<usize>) -> Vec<u8> {
+        let mut out = vec![0u8; 8];
+        for &(typ, size, payload) in tags {
+            out.extend_from_slice(&typ.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+            out.extend_from_slice(payload);
+            while !out.len().is_multiple_of(8) {
+                out.push(0);
+            }
+        }
+        let total = len.unwrap_or(out.len());
+        out.truncate(total);
+        out[..4].copy_from_slice(&(total as u32).to_le_bytes());
+        out
+    }
+
+    fn types(mbi: &[u8]) -> Vec<u32> {
+        TagIter::new(mbi).map(|t| t.typ).collect()
+    }
+
+    const END: (u32, u32, &[u8]) = (TAG_END, 8, &[]);
+
+    #[test]
+    fn walks_until_the_end_tag() {
+        let block = mbi(&[(TAG_CMDLINE, 14, b"quiet\0"), (TAG_BOOTLOADER_NAME, 13, b"GRUB\0"), END, (TAG_MMAP, 8, &[])], None);
+        let tags: Vec<Tag> = TagIter::new(&block).collect();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].as_str(), Some("quiet"));
+        // the payload stops at the tag's size, not at the padding
+        assert_eq!(tags[1].payload, b"GRUB\0");
+        assert_eq!(tags[1].as_str(), Some("GRUB"));
+    }
+
+    #[test]
+    fn tag_larger_than_the_block_ends_the_walk() {
+        // second tag claims 64 bytes but only 16 are left
+        let block = mbi(&[(TAG_CMDLINE, 14, b"quiet\0"), (TAG_MMAP, 64, &[0; 8]), END], None);
+        assert_eq!(types(&block), [TAG_CMDLINE]);
+    }
+
+    #[test]
+    fn tag_smaller_than_its_header_ends_the_walk() {
+        // size 0 would otherwise never advance; size 4 can't hold the header
+        for size in [0, 4] {
+            let block = mbi(&[(TAG_CMDLINE, 14, b"quiet\0"), (TAG_FRAMEBUFFER, size, &[]), (TAG_MMAP, 8, &[]), END], None);
+            assert_eq!(types(&block), [TAG_CMDLINE], "size {}", size);
+        }
+    }
+
+    #[test]
+    fn block_ending_inside_the_padding_or_a_header() {
+        // total_size stops right after a 13-byte tag, before its padding
+        let block = mbi(&[(TAG_CMDLINE, 13, b"quiet")], Some(8 + 13));
+        assert_eq!(types(&block), [TAG_CMDLINE]);
+
+        // total_size leaves half of the next tag header
+        let block = mbi(&[(TAG_CMDLINE, 14, b"quiet\0"), (TAG_MMAP, 8, &[])], Some(8 + 16 + 4));
+        assert_eq!(types(&block), [TAG_CMDLINE]);
+    }
+
+    #[test]
+    fn stops_after_max_tags() {
+        let many = vec![(TAG_CMDLINE, 8, &[][..]); MB_MAX_TAGS + 10];
+        let block = mbi(&many, None);
+        assert_eq!(TagIter::new(&block).count(), MB_MAX_TAGS);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod serial;
-pub mod loggers;
\ No newline at end of file
+pub mod io;
+pub mod loggers;
+pub mod vga;
@@ -1,5 +1,5 @@
-use core::arch::asm;
 use core::fmt;
+use super::io;
 // COM1 serial port base address
 const SERIAL_PORT_BASE: u16 = 0x3F8;
 
@@ -15,6 +15,16 @@ const LINE_STATUS_REG: u16 = 5;    // Line status register
 const TRANSMIT_EMPTY: u8 = 1 << 5;  // Transmitter holding register empty
 const DATA_READY: u8 = 1 << 0;      // Data ready
 
+// Loopback test byte used by self_test
+const LOOPBACK_TEST_BYTE: u8 = 0xAE;
+
+/// Errors reported by the serial port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialError {
+    /// The byte sent in loopback mode did not come back
+    LoopbackMismatch,
+}
+
 pub struct SerialPort {
     base: u16,
 }
@@ -25,7 +35,8 @@ impl SerialPort {
     }
 
     /// Initialize the serial port
-    pub unsafe fn init(&self) {
+    /// Returns the loopback self-test result so callers can decide whether to keep using the port
+    pub unsafe fn init(&self) -> Result<(), SerialError> {
         // Disable interrupts
         self.outb(INT_ENABLE_REG, 0x00);
 
@@ -45,17 +56,31 @@ impl SerialPort {
         // IRQs enabled, RTS/DSR set
         self.outb(MODEM_CTRL_REG, 0x0B);
 
+        let result = self.self_test();
+        match result {
+            Ok(()) => self.write_str("Serial self-test passed\n"),
+            // Probably goes nowhere, but harmless if the chip is only half broken
+            Err(SerialError::LoopbackMismatch) => self.write_str("Serial self-test FAILED: loopback mismatch\n"),
+        }
+        result
+    }
+
+    /// Loopback check: send a byte with the chip in loopback mode and make sure it comes back.
+    /// The port is left in normal operation mode afterward whatever the outcome.
+    pub unsafe fn self_test(&self) -> Result<(), SerialError> {
         // Test serial chip (send byte 0xAE and check if serial returns same byte)
         self.outb(MODEM_CTRL_REG, 0x1E);
-        self.outb(DATA_REG, 0xAE);
+        self.outb(DATA_REG, LOOPBACK_TEST_BYTE);
 
-        // Check if serial is faulty
-        if self.inb(DATA_REG) != 0xAE {
-            // Serial is faulty, but we'll continue anyway
-        }
+        let echoed = self.inb(DATA_REG);
 
         // Set it in normal operation mode
         self.outb(MODEM_CTRL_REG, 0x0F);
+
+        if echoed != LOOPBACK_TEST_BYTE {
+            return Err(SerialError::LoopbackMismatch);
+        }
+        Ok(())
     }
 
     /// Write a byte to the serial port
@@ -74,6 +99,24 @@ impl SerialPort {
         }
     }
 
+    /// Send raw binary data byte for byte (framebuffer dumps, core images).
+    /// Unlike the text methods, nothing here will ever translate or filter bytes.
+    pub unsafe fn write_bytes(&self, data: &[u8]) {
+        for &byte in data {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Send a u32 as 4 raw little-endian bytes, for structured binary dumps
+    pub unsafe fn write_u32_le(&self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Send a u64 as 8 raw little-endian bytes
+    pub unsafe fn write_u64_le(&self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
 
 
     /// Write a formatted hex number (useful for debugging)
@@ -131,6 +174,31 @@ impl SerialPort {
         }
     }
 
+    /// Write a signed decimal number, with a leading '-' for negative values
+    pub unsafe fn write_decimal_signed(&self, value: i64) {
+        if value < 0 {
+            self.write_byte(b'-');
+        }
+        // unsigned_abs doesn't overflow on i64::MIN
+        let mut magnitude = value.unsigned_abs();
+        let mut digits = [0u8; 20]; // Max 20 digits for u64
+        let mut i = 0;
+
+        loop {
+            digits[i] = b'0' + (magnitude % 10) as u8;
+            magnitude /= 10;
+            i += 1;
+            if magnitude == 0 {
+                break;
+            }
+        }
+
+        while i > 0 {
+            i -= 1;
+            self.write_byte(digits[i]);
+        }
+    }
+
     /// Read a byte from the serial port (if available)
     pub unsafe fn read_byte(&self) -> Option<u8> {
         if (self.inb(LINE_STATUS_REG) & DATA_READY) != 0 {
@@ -140,19 +208,31 @@ impl SerialPort {
         }
     }
 
+    /// Wait for one byte and return it as received: no line buffering, no
+    /// editing, so Enter arrives as b'\r'. With `echo` the byte is sent back
+    /// unchanged. For yes/no prompts and menus that shouldn't wait for Enter.
+    pub unsafe fn read_char_blocking(&self, echo: bool) -> u8 {
+        let byte = loop {
+            if let Some(byte) = self.read_byte() {
+                break byte;
+            }
+            core::hint::spin_loop();
+        };
+        if echo {
+            self.write_byte(byte);
+        }
+        byte
+    }
+
     /// Low-level port I/O functions
     #[inline]
     unsafe fn outb(&self, reg: u16, value: u8) {
-        let port = self.base + reg;
-        asm!("out dx, al", in("dx") port, in("al") value, options(nostack, nomem));
+        io::outb(self.base + reg, value);
     }
 
     #[inline]
     unsafe fn inb(&self, reg: u16) -> u8 {
-        let port = self.base + reg;
-        let value: u8;
-        asm!("in al, dx", in("dx") port, out("al") value, options(nostack, nomem));
-        value
+        io::inb(self.base + reg)
     }
 
     /// Write formatted data (supports `format_args!`)
@@ -0,0 +1,132 @@
+// src/kernel/vga.rs - VGA text mode console (80x25 at 0xB8000)
+//
+// Only visible when the machine is still in text mode, i.e. GRUB was told
+// `set gfxpayload=text` instead of handing over the 1024x768 framebuffer.
+// Writing here in graphics mode is harmless, it just shows nothing.
+use core::fmt;
+use core::ptr::write_volatile;
+
+const VGA_BUFFER: usize = 0xB8000;
+pub const VGA_WIDTH: usize = 80;
+pub const VGA_HEIGHT: usize = 25;
+const TAB_WIDTH: usize = 8;
+const BACKSPACE: u8 = 0x08;
+
+/// The 16 text mode colors
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Color {
+    Black = 0,
+    Blue = 1,
+    Green = 2,
+    Cyan = 3,
+    Red = 4,
+    Magenta = 5,
+    Brown = 6,
+    LightGray = 7,
+    DarkGray = 8,
+    LightBlue = 9,
+    LightGreen = 10,
+    LightCyan = 11,
+    LightRed = 12,
+    Pink = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+/// Attribute byte: background in the high nibble, foreground in the low one
+const fn attribute(fg: Color, bg: Color) -> u8 {
+    ((bg as u8) << 4) | (fg as u8)
+}
+
+// Cursor and current attribute; only touched from the main path
+static mut CURSOR_X: usize = 0;
+static mut CURSOR_Y: usize = 0;
+static mut ATTRIBUTE: u8 = attribute(Color::LightGray, Color::Black);
+
+unsafe fn write_cell(x: usize, y: usize, ch: u8, attr: u8) {
+    let cell = (VGA_BUFFER as *mut u16).add(y * VGA_WIDTH + x);
+    write_volatile(cell, ((attr as u16) << 8) | ch as u16);
+}
+
+/// Colors used for characters printed from now on
+pub unsafe fn set_color(fg: Color, bg: Color) {
+    ATTRIBUTE = attribute(fg, bg);
+}
+
+/// Blank the screen in the current colors and home the cursor
+pub unsafe fn clear_screen() {
+    for y in 0..VGA_HEIGHT {
+        for x in 0..VGA_WIDTH {
+            write_cell(x, y, b' ', ATTRIBUTE);
+        }
+    }
+    CURSOR_X = 0;
+    CURSOR_Y = 0;
+}
+
+/// Move every line up one and blank the bottom line
+unsafe fn scroll() {
+    let buffer = VGA_BUFFER as *mut u16;
+    core::ptr::copy(buffer.add(VGA_WIDTH), buffer, (VGA_HEIGHT - 1) * VGA_WIDTH);
+    for x in 0..VGA_WIDTH {
+        write_cell(x, VGA_HEIGHT - 1, b' ', ATTRIBUTE);
+    }
+}
+
+unsafe fn newline() {
+    CURSOR_X = 0;
+    if CURSOR_Y + 1 < VGA_HEIGHT {
+        CURSOR_Y += 1;
+    } else {
+        scroll();
+    }
+}
+
+/// Print one character at the cursor. '\n' starts a new line, '\r' returns to
+/// column 0, '\t' advances to the next multiple of 8 and backspace erases the
+/// previous character on the line, like the framebuffer console. Long lines wrap
+/// and the screen scrolls once the last line is full.
+pub unsafe fn put_char(ch: u8) {
+    match ch {
+        b'\n' => newline(),
+        b'\r' => CURSOR_X = 0,
+        b'\t' => loop {
+            put_char(b' ');
+            // a tab that reaches the end of the line stops at the wrap
+            if CURSOR_X.is_multiple_of(TAB_WIDTH) {
+                break;
+            }
+        },
+        BACKSPACE => {
+            if CURSOR_X > 0 {
+                CURSOR_X -= 1;
+                write_cell(CURSOR_X, CURSOR_Y, b' ', ATTRIBUTE);
+            }
+        }
+        _ => {
+            write_cell(CURSOR_X, CURSOR_Y, ch, ATTRIBUTE);
+            CURSOR_X += 1;
+            if CURSOR_X >= VGA_WIDTH {
+                newline();
+            }
+        }
+    }
+}
+
+pub unsafe fn print_str(s: &str) {
+    for byte in s.bytes() {
+        put_char(byte);
+    }
+}
+
+/// core::fmt sink for the VGA console, for write!/format_args! output
+pub struct VgaWriter;
+
+impl fmt::Write for VgaWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        unsafe { print_str(s) };
+        Ok(())
+    }
+}
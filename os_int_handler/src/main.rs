@@ -12,6 +12,7 @@ mod kernel;             // Core kernel subsystems
 // IMPORTS - Only what we need for early boot
 // ============================================================================
 use core::arch::asm;
+use kernel::io::outb;
 use kernel::serial::SERIAL_PORT;
 
 // Multiboot2 constants (keep your existing ones)
@@ -166,19 +167,6 @@ unsafe extern "C" fn fault_handler() {
     );
 }
 
-// Port I/O helpers
-#[inline]
-unsafe fn outb(port: u16, value: u8) {
-    asm!("out dx, al", in("dx") port, in("al") value, options(nostack, nomem));
-}
-
-#[inline]
-unsafe fn inb(port: u16) -> u8 {
-    let value: u8;
-    asm!("in al, dx", in("dx") port, out("al") value, options(nostack, nomem));
-    value
-}
-
 // Minimal interrupt setup
 unsafe fn init_minimal_interrupts() {
     unsafe{
@@ -253,9 +241,12 @@ unsafe fn init_minimal_pic() {
 #[unsafe(no_mangle)]
 pub extern "C" fn _start() -> ! {
     unsafe {
-        // Initialize serial port first
-        SERIAL_PORT.init();
-        SERIAL_PORT.write_str("\n=== INTERRUPT DEBUG SESSION ===\n");
+        // Initialize serial port first (no other output yet, so a failed self-test is ignored)
+        let _ = SERIAL_PORT.init();
+        SERIAL_PORT.write_str("\n");
+        // Mirror log lines on the text console too (blank if GRUB set a graphics mode)
+        kernel::loggers::Logger::set_vga_available(true);
+        log_info!("=== INTERRUPT DEBUG SESSION ===");
         // Second test: minimal interrupt setup (uncomment after first test works)
         init_minimal_interrupts();
         // 
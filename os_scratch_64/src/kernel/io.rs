@@ -0,0 +1,18 @@
+// x86 port I/O. One copy per kernel, next to serial.rs, so every driver uses the
+// same helpers instead of its own asm. All of them are unsafe: a port write can
+// reprogram any device, and a read can pop a byte off a device's FIFO.
+// Only the byte accessors live here; the Limine kernel's copy adds the 16/32-bit
+// ones and io_wait for its drivers.
+use core::arch::asm;
+
+#[inline]
+pub unsafe fn outb(port: u16, value: u8) {
+    asm!("out dx, al", in("dx") port, in("al") value, options(nostack, nomem));
+}
+
+#[inline]
+pub unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    asm!("in al, dx", in("dx") port, out("al") value, options(nostack, nomem));
+    value
+}
@@ -1,4 +1,6 @@
 use super::serial::SERIAL_PORT;
+use core::arch::asm;
+use core::fmt;
 use core::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug, Clone, Copy)]
@@ -32,6 +34,43 @@ impl LogLevel {
 // Use atomic bool for framebuffer availability to avoid mutable static issues
 static FRAMEBUFFER_AVAILABLE: AtomicBool = AtomicBool::new(false);
 
+/// RFLAGS interrupt-enable bit
+const RFLAGS_IF: u64 = 1 << 9;
+
+/// Held for a whole log line so lines from the main loop and ISRs don't interleave
+static LOG_LOCK: AtomicBool = AtomicBool::new(false);
+
+/// Interrupts off and LOG_LOCK held for one log line. Dropping it releases the
+/// lock and restores the previous interrupt state.
+struct LogGuard {
+    interrupts_enabled: bool,
+    /// False when the lock was already held
+    locked: bool,
+}
+
+impl LogGuard {
+    fn acquire() -> Self {
+        let rflags: u64;
+        unsafe { asm!("pushfq", "pop {}", "cli", out(reg) rflags) };
+        // With interrupts off nothing else on this CPU can release the lock, so a
+        // held lock means a fault or panic in the middle of a log line. Waiting
+        // would hang; print anyway, a garbled line beats a silent hang.
+        let locked = !LOG_LOCK.swap(true, Ordering::Acquire);
+        LogGuard { interrupts_enabled: rflags & RFLAGS_IF != 0, locked }
+    }
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        if self.locked {
+            LOG_LOCK.store(false, Ordering::Release);
+        }
+        if self.interrupts_enabled {
+            unsafe { asm!("sti", options(nostack)) };
+        }
+    }
+}
+
 pub struct Logger;
 
 impl Logger {
@@ -44,26 +83,33 @@ impl Logger {
     }
 
     pub fn log(&self, level: LogLevel, message: &str) {
+        self.log_fmt(level, format_args!("{}", message));
+    }
+
+    /// Log a formatted message. The whole line goes out with interrupts off, so
+    /// an ISR that logs can't split it.
+    pub fn log_fmt(&self, level: LogLevel, args: fmt::Arguments) {
+        let _guard = LogGuard::acquire();
         unsafe {
             // Always log to serial port
-            self.log_to_serial(level, message);
+            self.log_to_serial(level, args);
             
             // If framebuffer is available, also log there
             if FRAMEBUFFER_AVAILABLE.load(Ordering::Relaxed) {
-                self.log_to_framebuffer(level, message);
+                self.log_to_framebuffer(level, args);
             }
         }
     }
 
-    unsafe fn log_to_serial(&self, level: LogLevel, message: &str) {
+    unsafe fn log_to_serial(&self, level: LogLevel, args: fmt::Arguments) {
         SERIAL_PORT.write_str("[");
         SERIAL_PORT.write_str(level.as_str());
         SERIAL_PORT.write_str("] ");
-        SERIAL_PORT.write_str(message);
+        SERIAL_PORT.write_fmt(args);
         SERIAL_PORT.write_str("\n");
     }
 
-    unsafe fn log_to_framebuffer(&self, _level: LogLevel, _message: &str) {
+    unsafe fn log_to_framebuffer(&self, _level: LogLevel, _args: fmt::Arguments) {
         // TODO: Implement framebuffer text rendering
         // This would involve drawing characters to the framebuffer
         // For now, this is a placeholder
@@ -88,6 +134,7 @@ impl Logger {
 
     // Hex logging for debugging
     pub fn debug_hex(&self, name: &str, value: u32) {
+        let _guard = LogGuard::acquire();
         unsafe {
             SERIAL_PORT.write_str("[DEBUG] ");
             SERIAL_PORT.write_str(name);
@@ -99,6 +146,7 @@ impl Logger {
 
     // Decimal logging for debugging
     pub fn debug_decimal(&self, name: &str, value: u32) {
+        let _guard = LogGuard::acquire();
         unsafe {
             SERIAL_PORT.write_str("[DEBUG] ");
             SERIAL_PORT.write_str(name);
@@ -115,28 +163,28 @@ pub static LOGGER: Logger = Logger::new();
 // Convenience macros
 #[macro_export]
 macro_rules! log_debug {
-    ($($arg:expr),*) => {
-        unsafe { $crate::logger::LOGGER.debug(&format_args!($($arg),*)); }
+    ($($arg:tt)*) => {
+        $crate::kernel::loggers::LOGGER.log_fmt($crate::kernel::loggers::LogLevel::Debug, format_args!($($arg)*))
     };
 }
 
 #[macro_export]
 macro_rules! log_info {
-    ($($arg:expr),*) => {
-        unsafe { $crate::logger::LOGGER.info(&format_args!($($arg),*)); }
+    ($($arg:tt)*) => {
+        $crate::kernel::loggers::LOGGER.log_fmt($crate::kernel::loggers::LogLevel::Info, format_args!($($arg)*))
     };
 }
 
 #[macro_export]
 macro_rules! log_warn {
-    ($($arg:expr),*) => {
-        unsafe { $crate::logger::LOGGER.warn(&format_args!($($arg),*)); }
+    ($($arg:tt)*) => {
+        $crate::kernel::loggers::LOGGER.log_fmt($crate::kernel::loggers::LogLevel::Warn, format_args!($($arg)*))
     };
 }
 
 #[macro_export]
 macro_rules! log_error {
-    ($($arg:expr),*) => {
-        unsafe { $crate::logger::LOGGER.error(&format_args!($($arg),*)); }
+    ($($arg:tt)*) => {
+        $crate::kernel::loggers::LOGGER.log_fmt($crate::kernel::loggers::LogLevel::Error, format_args!($($arg)*))
     };
 }
\ No newline at end of file
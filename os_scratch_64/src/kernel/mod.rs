@@ -1,2 +1,3 @@
 pub mod serial;
+pub mod io;
 pub mod loggers;
\ No newline at end of file
@@ -6,7 +6,6 @@
 use core::panic::PanicInfo;
 use core::arch::asm;
 use core::fmt::Write;
-use crate::kernel::loggers::LOGGER;
 use crate::kernel::serial::SERIAL_PORT;
 
 /// Kernel panic handler - called when the kernel encounters a fatal error
@@ -18,15 +17,15 @@ pub fn panic_handler(info: &PanicInfo) -> ! {
     }
     
     unsafe {
+        // Everything below goes straight to the COM1 registers: SERIAL_PORT takes
+        // no lock, while the logger serialises on LOG_LOCK, which the panicking
+        // code may hold, so the report never goes through LOGGER.
         // Print panic header
         SERIAL_PORT.write_str("\n");
         SERIAL_PORT.write_str("=====================================\n");
         SERIAL_PORT.write_str("       KERNEL PANIC OCCURRED!       \n");
         SERIAL_PORT.write_str("=====================================\n");
         
-        // Log through both serial and logger if available
-        LOGGER.error("KERNEL PANIC - SYSTEM HALTING");
-        
         // Print location information if available
         if let Some(location) = info.location() {
             SERIAL_PORT.write_str("Panic Location:\n");
@@ -72,9 +71,6 @@ pub fn panic_handler(info: &PanicInfo) -> ! {
         SERIAL_PORT.write_str("System has been halted for safety.\n");
         SERIAL_PORT.write_str("Restart required.\n");
         SERIAL_PORT.write_str("=====================================\n");
-        
-        // Final log entry
-        LOGGER.error("System halted due to kernel panic - restart required");
     }
     
     // Halt the CPU indefinitely
@@ -2,237 +2,228 @@
 #![no_main]
 
 
+mod io;
 mod mem;
-use core::panic::PanicInfo;
+mod panic;
+mod serial;
 use core::arch::asm;
 use core::mem::size_of;
-use core::ptr::{read_unaligned, write_volatile};
-
-#[repr(C)]
-struct MbInfoHeader { total_size: u32, reserved: u32 }
-
-#[repr(C)]
-struct TagHeader { typ: u32, size: u32 }
+use core::ptr::read_unaligned;
+use graphics::{ColorLayout, Framebuffer, GradientDir};
+use serial::SERIAL_PORT;
 
+/// Framebuffer tag (type 8) payload, after the tag header
 #[repr(C)]
 struct FramebufferInfo {
-    typ: u32,
-    size: u32,
     framebuffer_addr: u64,
     framebuffer_pitch: u32,
     framebuffer_width: u32,
     framebuffer_height: u32,
     framebuffer_bpp: u8,
-    // color info (variable) follows — not represented here
+    framebuffer_type: u8, // 0 = indexed, 1 = direct RGB, 2 = EGA text
+    reserved: u16,
+    // color info (variable) follows, see ColorInfoRgb
 }
 
-pub struct Framebuffer {
-    pub phys_addr: usize,
-    pub pitch: usize,
-    pub width: usize,
-    pub height: usize,
-    pub bpp: usize,
+/// Color info for framebuffer_type 1 (direct RGB), directly after FramebufferInfo
+#[repr(C)]
+struct ColorInfoRgb {
+    red_field_position: u8,
+    red_mask_size: u8,
+    green_field_position: u8,
+    green_mask_size: u8,
+    blue_field_position: u8,
+    blue_mask_size: u8,
 }
 
-impl Framebuffer {
+/// Parse the Multiboot2 info block (mbi_ptr from EBX) and return Framebuffer if available.
+///
+/// Safety: Caller must ensure mbi_ptr is a valid pointer (provided by bootloader) and
+/// that physical addresses are accessible (identity mapped) if you dereference framebuffer.
+unsafe fn find_framebuffer(mbi_ptr: u32) -> Option<Framebuffer> {
+    let tag = multiboot::tags(mbi_ptr)?.find(|t| t.typ == multiboot::TAG_FRAMEBUFFER)?;
+
+    // ensure we can read framebuffer info struct fully
+    if tag.payload.len() < size_of::<FramebufferInfo>() {
+        return None;
+    }
+    let fb = read_unaligned(tag.payload.as_ptr() as *const FramebufferInfo);
+
+    let addr = fb.framebuffer_addr as usize;
+    let pitch = fb.framebuffer_pitch as usize;
+    let width = fb.framebuffer_width as usize;
+    let height = fb.framebuffer_height as usize;
+    let bpp = fb.framebuffer_bpp as usize;
+
+    let mut framebuffer = match Framebuffer::new_checked(addr, pitch, width, height, bpp) {
+        Ok(framebuffer) => framebuffer,
+        Err(err) => {
+            SERIAL_PORT.write_fmt(format_args!(
+                "Framebuffer tag rejected: {:?} ({}x{} {}bpp, pitch {})\n", err, width, height, bpp, pitch
+            ));
+            return None;
+        }
+    };
+
+    // direct RGB framebuffers describe where each channel lives
+    let color_info_size = size_of::<FramebufferInfo>() + size_of::<ColorInfoRgb>();
+    if fb.framebuffer_type == 1 && tag.payload.len() >= color_info_size {
+        let ci = read_unaligned(tag.payload[size_of::<FramebufferInfo>()..].as_ptr() as *const ColorInfoRgb);
+        let parsed = ColorLayout {
+            red_pos: ci.red_field_position,
+            red_size: ci.red_mask_size,
+            green_pos: ci.green_field_position,
+            green_size: ci.green_mask_size,
+            blue_pos: ci.blue_field_position,
+            blue_size: ci.blue_mask_size,
+        };
+        // ignore nonsense positions rather than shifting out of range
+        if parsed.is_valid_for(bpp) {
+            framebuffer.layout = parsed;
+        }
+    }
+
+    Some(framebuffer)
+}
 
-        /// Pack a 0xAARRGGBB color into the framebuffer format and write at (x,y).
-    /// Supports common bpps: 32 (4 bytes), 24 (3 bytes), 16 (RGB565).
-    /// 8bpp paletted is not handled here.
-    pub unsafe fn put_pixel(&self, x: usize, y: usize, color: u32) {
-        if x >= self.width || y >= self.height { return; }
+/// Locate the boot command line (Multiboot2 tag type 1), e.g. "loglevel=debug novga".
+///
+/// Safety: mbi_ptr must point to a valid Multiboot2 info block that stays mapped.
+/// Returns None if the tag is missing, extends past total_size, or is not valid UTF-8.
+unsafe fn find_cmdline(mbi_ptr: u32) -> Option<&'static str> {
+    // command line tag: 8-byte header followed by a null-terminated string
+    multiboot::tags(mbi_ptr)?.find(|t| t.typ == multiboot::TAG_CMDLINE)?.as_str()
+}
 
-        let base = self.phys_addr as *mut u8;
-        let offset = y * self.pitch + x * (self.bpp / 8);
-        let p = base.add(offset);
+/// Locate the bootloader name (Multiboot2 tag type 2), e.g. "GRUB 2.06".
+///
+/// Safety: mbi_ptr must point to a valid Multiboot2 info block that stays mapped.
+/// Returns None if the tag is missing, extends past total_size, or is not valid UTF-8.
+unsafe fn find_bootloader_name(mbi_ptr: u32) -> Option<&'static str> {
+    multiboot::tags(mbi_ptr)?.find(|t| t.typ == multiboot::TAG_BOOTLOADER_NAME)?.as_str()
+}
 
-        match self.bpp {
-            32 => {
-                // write full u32
-                let ptr = p as *mut u32;
-                write_volatile(ptr, color);
-            }
-            24 => {
-                // little-endian: write in memory as (B, G, R)
-                // color is 0xAARRGGBB; extract bytes
-                let b = (color & 0xFF) as u8;
-                let g = ((color >> 8) & 0xFF) as u8;
-                let r = ((color >> 16) & 0xFF) as u8;
-                // write bytes
-                core::ptr::write_volatile(p, b);
-                core::ptr::write_volatile(p.add(1), g);
-                core::ptr::write_volatile(p.add(2), r);
-            }
-            16 => {
-                // RGB565 packing: R:5 G:6 B:5
-                let r8 = ((color >> 16) & 0xFF) as u16;
-                let g8 = ((color >> 8) & 0xFF) as u16;
-                let b8 = (color & 0xFF) as u16;
-                let r5 = (r8 >> 3) & 0x1F;
-                let g6 = (g8 >> 2) & 0x3F;
-                let b5 = (b8 >> 3) & 0x1F;
-                let pixel16: u16 = (r5 << 11) | (g6 << 5) | b5;
-                let ptr16 = p as *mut u16;
-                write_volatile(ptr16, pixel16);
-            }
-            other => {
-                // unsupported bpp: do nothing or fallback
-                let _ = other;
-            }
+/// Look up `key` in a whitespace-separated command line.
+/// "key=value" yields Some("value"), a bare "key" flag yields Some("").
+fn boot_arg<'a>(cmdline: &'a str, key: &str) -> Option<&'a str> {
+    for arg in cmdline.split_whitespace() {
+        match arg.split_once('=') {
+            Some((k, v)) if k == key => return Some(v),
+            None if arg == key => return Some(""),
+            _ => {}
         }
     }
+    None
+}
 
-    /// Fast horizontal fill of a row for bytes-per-pixel that is a power of two.
-    /// Used to implement fast clear/rect.
-    unsafe fn fill_row_bytes(&self, y: usize, x0: usize, x1: usize, pixel_bytes: &[u8]) {
-        if y >= self.height || x0 >= x1 { return; }
-        let base = self.phys_addr as *mut u8;
-        let stride = self.pitch;
-        let start = base.add(y * stride + x0 * pixel_bytes.len());
-        let mut dst = start;
-        let count = x1 - x0;
-        // naive loop; can be optimized w/ word writes or memcpy-like writes
-        for _ in 0..count {
-            // write bytes of pixel
-            for i in 0..pixel_bytes.len() {
-                core::ptr::write_volatile(dst.add(i), pixel_bytes[i]);
-            }
-            dst = dst.add(pixel_bytes.len());
+/// ACPI 1.0 Root System Description Pointer (20 bytes)
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct RsdpV1 {
+    pub signature: [u8; 8], // "RSD PTR "
+    pub checksum: u8,       // bytes 0..20 sum to zero
+    pub oem_id: [u8; 6],
+    pub revision: u8,       // 0 for ACPI 1.0, 2 for ACPI 2.0+
+    pub rsdt_address: u32,
+}
+
+/// ACPI 2.0+ Root System Description Pointer (36 bytes, extends RsdpV1)
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct RsdpV2 {
+    pub v1: RsdpV1,
+    pub length: u32,            // size of the whole structure
+    pub xsdt_address: u64,
+    pub extended_checksum: u8,  // bytes 0..length sum to zero
+    pub reserved: [u8; 3],
+}
+
+/// RSDP handed over by the bootloader: v1 points at the RSDT, v2 at the XSDT
+#[derive(Clone, Copy)]
+pub enum Rsdp {
+    V1(RsdpV1),
+    V2(RsdpV2),
+}
+
+impl Rsdp {
+    /// 0 for ACPI 1.0, 2 for ACPI 2.0+
+    pub fn revision(&self) -> u8 {
+        match self {
+            Rsdp::V1(r) => r.revision,
+            Rsdp::V2(r) => r.v1.revision,
         }
     }
 
-    /// Draw filled rectangle. Uses put_pixel currently; for speed use fill_row_bytes.
-    pub unsafe fn fill_rect(&self, x: usize, y: usize, w: usize, h: usize, color: u32) {
-        let x1 = (x + w).min(self.width);
-        let y1 = (y + h).min(self.height);
-        if self.bpp == 32 {
-            for yy in y..y1 {
-                let base = self.phys_addr as *mut u8;
-                let mut ptr = base.add(yy * self.pitch + x * 4) as *mut u32;
-                for _ in x..x1 {
-                    write_volatile(ptr, color);
-                    ptr = ptr.add(1);
-                }
-            }
-        } else {
-            for yy in y..y1 {
-                for xx in x..x1 {
-                    self.put_pixel(xx, yy, color);
-                }
-            }
+    pub fn rsdt_address(&self) -> u32 {
+        match self {
+            Rsdp::V1(r) => r.rsdt_address,
+            Rsdp::V2(r) => r.v1.rsdt_address,
         }
     }
 
-    /// Bresenham line (integer) — draws a 1px wide line.
-    pub unsafe fn draw_line(&self, x0: isize, y0: isize, x1: isize, y1: isize, color: u32) {
-        let dx = (x1 - x0).abs();
-        let sx = if x0 < x1 { 1 } else { -1 };
-        let dy = -(y1 - y0).abs();
-        let sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx + dy;
-        let mut x = x0;
-        let mut y = y0;
-        loop {
-            if x >= 0 && (x as usize) < self.width && y >= 0 && (y as usize) < self.height {
-                self.put_pixel(x as usize, y as usize, color);
-            }
-            if x == x1 && y == y1 { break; }
-            let e2 = 2*err;
-            if e2 >= dy {
-                err += dy;
-                x += sx;
-            }
-            if e2 <= dx {
-                err += dx;
-                y += sy;
-            }
+    /// XSDT address, only present for ACPI 2.0+
+    pub fn xsdt_address(&self) -> Option<u64> {
+        match self {
+            Rsdp::V1(_) => None,
+            Rsdp::V2(r) => Some(r.xsdt_address),
         }
     }
+}
 
-    /// Draw a simple gradient background (horizontal).
-    pub unsafe fn draw_gradient(&self) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                // mix two colors based on x/width
-                let t = (x * 255) / (self.width.saturating_sub(1));
-                // red to blue gradient
-                let r = t as u32;
-                let g = ((y * 128) / (self.height.saturating_sub(1))) as u32;
-                let b = (255 - t) as u32;
-                let color = (0xFF << 24) | (r << 16) | (g << 8) | b;
-                self.put_pixel(x, y, color);
-            }
-        }
+/// Sum of all bytes must be zero (mod 256) for ACPI checksums
+fn acpi_checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
+
+/// Validate and copy out an RSDP from a tag payload.
+/// `typ` is the Multiboot2 tag type: 14 = ACPI 1.0 (old RSDP), 15 = ACPI 2.0 (new RSDP).
+fn parse_rsdp(typ: u32, payload: &[u8]) -> Option<Rsdp> {
+    let v1_size = core::mem::size_of::<RsdpV1>();
+    if payload.len() < v1_size || &payload[..8] != b"RSD PTR " {
+        return None;
     }
-    /// Write a pixel in 32bpp (ARGB/ABGR layout depends on platform).
-    /// color is 0xAARRGGBB (alpha ignored for many modes).
-    /// This assumes physical == virtual (identity mapping). Map if using paging.
-    pub unsafe fn put_pixel_32(&self, x: usize, y: usize, color: u32) {
-        if x >= self.width || y >= self.height { return; }
-        let base = self.phys_addr as *mut u8;
-        let offset = y * self.pitch + x * 4;
-        let ptr = base.add(offset) as *mut u32;
-        write_volatile(ptr, color);
+    if !acpi_checksum_ok(&payload[..v1_size]) {
+        return None;
     }
 
-    /// Clear screen (32bpp) to color.
-    pub unsafe fn clear_32(&self, color: u32) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                self.put_pixel_32(x, y, color);
+    match typ {
+        14 => {
+            let v1 = unsafe { read_unaligned(payload.as_ptr() as *const RsdpV1) };
+            Some(Rsdp::V1(v1))
+        }
+        15 => {
+            if payload.len() < core::mem::size_of::<RsdpV2>() {
+                return None;
             }
+            let v2 = unsafe { read_unaligned(payload.as_ptr() as *const RsdpV2) };
+            let length = v2.length as usize;
+            if length < core::mem::size_of::<RsdpV2>() || length > payload.len() {
+                return None;
+            }
+            if !acpi_checksum_ok(&payload[..length]) {
+                return None;
+            }
+            Some(Rsdp::V2(v2))
         }
+        _ => None,
     }
 }
 
-
-/// Parse the Multiboot2 info block (mbi_ptr from EBX) and return Framebuffer if available.
+/// Locate the ACPI RSDP (Multiboot2 tag type 14 or 15), preferring the ACPI 2.0 copy.
 ///
-/// Safety: Caller must ensure mbi_ptr is a valid pointer (provided by bootloader) and
-/// that physical addresses are accessible (identity mapped) if you dereference framebuffer.
-unsafe fn find_framebuffer(mbi_ptr: u32) -> Option<Framebuffer> {
-    let base = mbi_ptr as *const u8;
-
-    // Read total_size first (mbi header)
-    let total_size = read_unaligned(base as *const u32) as usize;
-    if total_size == 0 { return None; }
-
-    // Tags start at offset 8
-    let mut offset: usize = 8;
-    while offset + core::mem::size_of::<TagHeader>() <= total_size {
-        let tag_ptr = base.add(offset) as *const TagHeader;
-        // read unaligned tag header
-        let tag = read_unaligned(tag_ptr);
-        if tag.typ == 0 && tag.size == 8 {
-            // end tag
-            break;
-        }
-
-        if tag.typ == 8 {
-            // framebuffer info tag found
-            // ensure we can read framebuffer info struct fully
-            if offset + (tag.size as usize) > total_size {
-                return None;
+/// Safety: mbi_ptr must point to a valid Multiboot2 info block that stays mapped.
+unsafe fn find_rsdp(mbi_ptr: u32) -> Option<Rsdp> {
+    let mut found_v1: Option<Rsdp> = None;
+    for tag in multiboot::tags(mbi_ptr)? {
+        if tag.typ == multiboot::TAG_ACPI_OLD || tag.typ == multiboot::TAG_ACPI_NEW {
+            match parse_rsdp(tag.typ, tag.payload) {
+                Some(rsdp @ Rsdp::V2(_)) => return Some(rsdp),
+                Some(rsdp) => found_v1 = Some(rsdp),
+                None => {}
             }
-            let fb_ptr = base.add(offset) as *const FramebufferInfo;
-            let fb = read_unaligned(fb_ptr);
-
-            let addr = fb.framebuffer_addr as usize;
-            let pitch = fb.framebuffer_pitch as usize;
-            let width = fb.framebuffer_width as usize;
-            let height = fb.framebuffer_height as usize;
-            let bpp = fb.framebuffer_bpp as usize;
-
-            return Some(Framebuffer { phys_addr: addr, pitch, width, height, bpp });
         }
-
-        // advance to next tag, tags are padded to 8 bytes
-        let mut next = offset + ((tag.size as usize + 7) & !7usize);
-        if next <= offset { break; } // sanity
-        offset = next;
     }
-
-    None
+    found_v1
 }
 
 
@@ -297,6 +288,47 @@ pub static MULTIBOOT2_HEADER: MbHeader = MbHeader {
     end_size: END_TAG_SIZE,
 };
 
+/// Value a Multiboot2 loader leaves in EAX when it jumps to _start
+const MULTIBOOT2_BOOTLOADER_MAGIC: u32 = 0x36d76289;
+
+/// Why the boot handoff from the bootloader was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BootError {
+    /// EAX did not hold the Multiboot2 magic; contains what it held instead
+    BadMagic(u32),
+    /// EBX (the boot information pointer) was 0
+    NullInfo,
+}
+
+/// Check the registers the bootloader handed us and return the info pointer
+fn verify_multiboot(magic: u32, info_ptr: u32) -> Result<u32, BootError> {
+    if magic != MULTIBOOT2_BOOTLOADER_MAGIC {
+        return Err(BootError::BadMagic(magic));
+    }
+    if info_ptr == 0 {
+        return Err(BootError::NullInfo);
+    }
+    Ok(info_ptr)
+}
+
+/// Report a rejected boot on serial and halt for good
+unsafe fn boot_failed(err: BootError) -> ! {
+    match err {
+        BootError::BadMagic(magic) => {
+            SERIAL_PORT.write_str("BOOT: bad Multiboot2 magic ");
+            SERIAL_PORT.write_hex(magic);
+            SERIAL_PORT.write_str(", expected ");
+            SERIAL_PORT.write_hex(MULTIBOOT2_BOOTLOADER_MAGIC);
+            SERIAL_PORT.write_str(" (not booted by a Multiboot2 loader?)\n");
+        }
+        BootError::NullInfo => SERIAL_PORT.write_str("BOOT: Multiboot2 info pointer is null\n"),
+    }
+    SERIAL_PORT.write_str("BOOT: halting\n");
+    loop {
+        asm!("cli", "hlt", options(nomem, nostack));
+    }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn _start() -> ! {
     let magic: u32;
@@ -313,8 +345,56 @@ pub extern "C" fn _start() -> ! {
         );
     }
 
-    if magic != 0x36d76289 {
-        loop {}
+    // Serial first so a rejected boot can say why
+    unsafe {
+        let _ = SERIAL_PORT.init();
+    }
+    let info_ptr = match verify_multiboot(magic, info_ptr) {
+        Ok(info_ptr) => info_ptr,
+        Err(err) => unsafe { boot_failed(err) },
+    };
+
+    // Boot banner: which loader started us and with what command line
+    unsafe {
+        SERIAL_PORT.write_str("Booted by: ");
+        SERIAL_PORT.write_str(find_bootloader_name(info_ptr).unwrap_or("<unknown>"));
+        SERIAL_PORT.write_str("\nCommand line: ");
+        SERIAL_PORT.write_str(find_cmdline(info_ptr).unwrap_or("<none>"));
+        SERIAL_PORT.write_str("\n");
+
+        // Symbol tables for backtraces, if the kernel image kept them
+        match multiboot::find_elf_sections(info_ptr) {
+            Some(sections) => match sections.symbol_tables() {
+                Some((symtab, strtab)) => SERIAL_PORT.write_fmt(format_args!(
+                    "ELF sections: {} headers, .symtab at {:#x} ({} bytes), .strtab at {:#x}\n",
+                    sections.len(), symtab.addr, symtab.size, strtab.addr
+                )),
+                None => SERIAL_PORT.write_fmt(format_args!(
+                    "ELF sections: {} headers, no symbol table\n", sections.len()
+                )),
+            },
+            None => SERIAL_PORT.write_str("ELF sections: tag not present\n"),
+        }
+
+        // ACPI root pointer, where the table walk will start
+        match find_rsdp(info_ptr) {
+            Some(rsdp) => {
+                SERIAL_PORT.write_fmt(format_args!(
+                    "ACPI: RSDP revision {}, RSDT at {:#x}", rsdp.revision(), rsdp.rsdt_address()
+                ));
+                if let Some(xsdt) = rsdp.xsdt_address() {
+                    SERIAL_PORT.write_fmt(format_args!(", XSDT at {:#x}", xsdt));
+                }
+                SERIAL_PORT.write_str("\n");
+            }
+            None => SERIAL_PORT.write_str("ACPI: no valid RSDP tag\n"),
+        }
+
+        // Usable RAM, preferring the EFI map on UEFI boots
+        match multiboot::find_memory_map(info_ptr) {
+            Some(map) => mem::print_memory_map(&map),
+            None => SERIAL_PORT.write_str("Memory map: tag not present\n"),
+        }
     }
 
 
@@ -322,26 +402,100 @@ pub extern "C" fn _start() -> ! {
 
 if let Some(fb) = fb_opt {
     unsafe {
-        if fb.bpp == 32 {
+        let cmdline = find_cmdline(info_ptr).unwrap_or("");
+        draw_fb_test(&fb, FbTest::from_cmdline(cmdline));
+    }
+} else {
+    unsafe { run_serial_only(info_ptr) }
+}
+
+    loop {}
+}
+
+
+/// What _start draws on the framebuffer, picked with `fbtest=<name>` on the
+/// command line so the display can be checked without rebuilding
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FbTest {
+    /// Gradient, border rect, diagonal lines and a triangle (the default)
+    Demo,
+    /// draw_test_pattern: bars, checkerboard and ramps for checking a new mode
+    Bars,
+    /// Only the full-screen gradient
+    Gradient,
+    /// Leave the framebuffer as the bootloader left it
+    None,
+}
+
+impl FbTest {
+    fn from_name(name: &str) -> Option<FbTest> {
+        match name {
+            "demo" => Some(FbTest::Demo),
+            "bars" => Some(FbTest::Bars),
+            "gradient" => Some(FbTest::Gradient),
+            "none" => Some(FbTest::None),
+            _ => None,
+        }
+    }
+
+    /// `fbtest=<name>` from the command line, Demo if absent or unknown. The
+    /// older bare "testpattern" flag still means Bars.
+    unsafe fn from_cmdline(cmdline: &str) -> FbTest {
+        match boot_arg(cmdline, "fbtest") {
+            Some(name) => FbTest::from_name(name).unwrap_or_else(|| {
+                SERIAL_PORT.write_fmt(format_args!(
+                    "fbtest={} unknown (demo, bars, gradient, none), using demo\n", name
+                ));
+                FbTest::Demo
+            }),
+            None if boot_arg(cmdline, "testpattern").is_some() => FbTest::Bars,
+            None => FbTest::Demo,
+        }
+    }
+}
+
+unsafe fn draw_fb_test(fb: &Framebuffer, test: FbTest) {
+    match test {
+        FbTest::None => {}
+        FbTest::Bars => fb.draw_test_pattern(),
+        FbTest::Gradient => fb.draw_gradient_ex(0xFF_10_20_60, 0xFF_60_10_40, GradientDir::Diagonal),
+        FbTest::Demo if fb.bpp == 32 => {
             // gradient fills the whole screen (visual test)
-            fb.draw_gradient();
+            fb.draw_gradient_ex(0xFF_10_20_60, 0xFF_60_10_40, GradientDir::Diagonal);
 
             // draw border rectangle
             fb.fill_rect(20, 20, fb.width - 40, fb.height - 40, 0xFF_00_80_00);
 
-            // draw diagonal lines
-            fb.draw_line(0, 0, (fb.width-1) as isize, (fb.height-1) as isize, 0xFF_FF_00_00);
-            fb.draw_line((fb.width-1) as isize, 0, 0, (fb.height-1) as isize, 0xFF_00_FF_00);
-        } else {
+            // draw anti-aliased diagonal lines
+            let (right, bottom) = ((fb.width - 1) as f32, (fb.height - 1) as f32);
+            fb.draw_line_aa(0.0, 0.0, right, bottom, 0xFF_FF_00_00);
+            fb.draw_line_aa(right, 0.0, 0.0, bottom, 0xFF_00_FF_00);
+
+            // filled triangle with its outline on top
+            let cx = (fb.width / 2) as isize;
+            let cy = (fb.height / 2) as isize;
+            let (p0, p1, p2) = ((cx, cy - 100), (cx - 100, cy + 80), (cx + 100, cy + 80));
+            fb.fill_triangle(p0, p1, p2, 0xFF_FF_C0_00);
+            fb.draw_triangle(p0, p1, p2, 0xFF_FF_FF_FF);
+        }
+        FbTest::Demo => {
             // fallback: paint a solid color
-            fb.clear_32(0xFF_20_20_40);
+            fb.fill(0xFF_20_20_40);
         }
     }
 }
 
-    loop {}
+/// Headless boot: say why there is no display, then keep the serial console
+/// alive by echoing whatever arrives, so the host can tell we are still running.
+unsafe fn run_serial_only(info_ptr: u32) -> ! {
+    SERIAL_PORT.write_fmt(format_args!(
+        "No framebuffer tag found in MBI (total_size {} bytes at {:#x})\n",
+        multiboot::total_size(info_ptr).unwrap_or(0), info_ptr
+    ));
+    SERIAL_PORT.write_str("Continuing serial-only; input is echoed back\n");
+    loop {
+        if let Some(byte) = SERIAL_PORT.read_byte() {
+            SERIAL_PORT.write_byte(byte);
+        }
+    }
 }
-
-
-#[panic_handler]
-fn panic(_info: &PanicInfo) -> ! { loop {} }
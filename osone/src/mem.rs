@@ -31,3 +31,50 @@ pub unsafe extern "C" fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32 {
     }
     0
 }
+
+// ============================================================================
+// MEMORY MAP REPORT
+// ============================================================================
+
+use core::fmt;
+use multiboot::MemoryMap;
+use crate::serial::SERIAL_PORT;
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const GIB: u64 = 1024 * MIB;
+
+/// A byte count in the largest whole unit: "512 KiB", "127 MiB", "4 GiB"
+pub struct HumanSize(pub u64);
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            n if n >= GIB => write!(f, "{} GiB", n / GIB),
+            n if n >= MIB => write!(f, "{} MiB", n / MIB),
+            n if n >= KIB => write!(f, "{} KiB", n / KIB),
+            n => write!(f, "{} B", n),
+        }
+    }
+}
+
+/// Print every memory map entry, e820 style, then the total the frame
+/// allocator can use (map.usable_regions()):
+///   [0x0000000000100000-0x0000000007FFFFFF] usable (127 MiB)
+pub unsafe fn print_memory_map(map: &MemoryMap) {
+    SERIAL_PORT.write_fmt(format_args!("Memory map ({}):\n", map.source()));
+    for entry in map.entries() {
+        if entry.length == 0 {
+            continue;
+        }
+        let last = entry.base.saturating_add(entry.length - 1);
+        SERIAL_PORT.write_fmt(format_args!(
+            "  [{:#018x}-{:#018x}] {} ({})\n",
+            entry.base, last, entry.kind.name(), HumanSize(entry.length)
+        ));
+    }
+    let (regions, usable) = map.usable_regions().fold((0, 0u64), |(n, total), r| (n + 1, total + r.length));
+    SERIAL_PORT.write_fmt(format_args!(
+        "Usable memory: {} ({} bytes in {} regions)\n", HumanSize(usable), usable, regions
+    ));
+}
@@ -0,0 +1,83 @@
+//! Kernel panic handler and assertion macros
+//!
+//! Same interface as the panic modules of the other kernels (kernel_panic,
+//! kernel_panic_fmt, kernel_assert!, kernel_panic!), so code and habits carry
+//! over. Reports go to COM1; the framebuffer may be the thing that broke.
+
+use core::arch::asm;
+use core::panic::PanicInfo;
+use crate::serial::SERIAL_PORT;
+
+/// Kernel panic handler - print where and why, then halt
+#[panic_handler]
+pub fn panic_handler(info: &PanicInfo) -> ! {
+    // Nothing else gets to run from here on
+    unsafe {
+        asm!("cli", options(nostack, nomem));
+    }
+
+    unsafe {
+        SERIAL_PORT.write_str("\n=====================================\n");
+        SERIAL_PORT.write_str("       KERNEL PANIC OCCURRED!       \n");
+        SERIAL_PORT.write_str("=====================================\n");
+
+        match info.location() {
+            Some(location) => SERIAL_PORT.write_fmt(format_args!(
+                "Panic Location: {}:{}:{}\n",
+                location.file(), location.line(), location.column()
+            )),
+            None => SERIAL_PORT.write_str("Panic Location: Unknown\n"),
+        }
+        SERIAL_PORT.write_fmt(format_args!("Panic Message: {}\n", info.message()));
+        SERIAL_PORT.write_str("System has been halted for safety.\n");
+    }
+
+    halt_system();
+}
+
+/// Halt the system safely
+fn halt_system() -> ! {
+    unsafe {
+        loop {
+            asm!("hlt", options(nostack, nomem));
+        }
+    }
+}
+
+/// Panic naming the subsystem that failed (for internal kernel use)
+pub fn kernel_panic(subsystem: &str, reason: &str) -> ! {
+    panic!("Kernel subsystem failure: {}: {}", subsystem, reason);
+}
+
+/// kernel_panic with a formatted reason
+pub fn kernel_panic_fmt(subsystem: &str, args: core::fmt::Arguments) -> ! {
+    panic!("Kernel subsystem failure: {}: {}", subsystem, args);
+}
+
+/// Enhanced assert macro for kernel debugging
+#[macro_export]
+macro_rules! kernel_assert {
+    ($condition:expr) => {
+        if !($condition) {
+            $crate::panic::kernel_panic("assertion", stringify!($condition));
+        }
+    };
+    ($condition:expr, $message:expr) => {
+        if !($condition) {
+            $crate::panic::kernel_panic("assertion", $message);
+        }
+    };
+    ($condition:expr, $($args:tt)*) => {
+        if !($condition) {
+            $crate::panic::kernel_panic_fmt("assertion", format_args!($($args)*));
+        }
+    };
+}
+
+/// Convenience macro for formatted kernel panics
+#[macro_export]
+macro_rules! kernel_panic {
+    ($subsystem:expr, $($args:tt)*) => {
+        $crate::panic::kernel_panic_fmt($subsystem, format_args!($($args)*))
+    };
+}